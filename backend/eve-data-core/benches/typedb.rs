@@ -0,0 +1,177 @@
+//! Latency of the type lookups done by a waitlist list request, with many requests in flight.
+//!
+//! Each simulated request resolves the names of a few hulls and loads the types of a fit's
+//! modules, like `GET /api/waitlist` does. Types are picked at random from the whole SDE so
+//! that a good share of the lookups miss the cache, which is the case that used to block the
+//! executor. Run with `cargo bench -p eve_data_core --features async`, setting `SDE_PATH`
+//! if the database isn't in the current directory.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use eve_data_core::{AsyncTypeDB, TypeDB, TypeID, DEFAULT_CONNECTIONS};
+
+const REQUESTS: usize = 2000;
+const IN_FLIGHT: usize = 64;
+const WORKERS: usize = 4;
+const HULLS_PER_REQUEST: usize = 15;
+const MODULES_PER_REQUEST: usize = 40;
+
+// Small xorshift so that every run looks up the same types
+struct Rng(u64);
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn pick(&mut self, from: &[TypeID], count: usize) -> Vec<TypeID> {
+        (0..count)
+            .map(|_| from[self.next() as usize % from.len()])
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Mode {
+    Blocking,
+    Async,
+}
+
+struct Scenario {
+    name: &'static str,
+    connections: usize,
+    preload: bool,
+    mode: Mode,
+}
+
+fn published_type_ids(path: &str) -> Vec<TypeID> {
+    let conn = rusqlite::Connection::open(path).expect("Could not open SDE");
+    let mut prepared = conn
+        .prepare("SELECT typeID FROM invTypes WHERE published = 1")
+        .unwrap();
+    let rows = prepared.query_map([], |row| row.get(0)).unwrap();
+    rows.collect::<Result<_, _>>().unwrap()
+}
+
+async fn request(mode: Mode, hulls: Vec<TypeID>, modules: Vec<TypeID>) -> Duration {
+    let start = Instant::now();
+    match mode {
+        Mode::Blocking => {
+            TypeDB::names_of(&hulls).unwrap();
+            TypeDB::load_types(&modules).unwrap();
+        }
+        Mode::Async => {
+            AsyncTypeDB::names_of(&hulls).await.unwrap();
+            AsyncTypeDB::load_types(&modules).await.unwrap();
+        }
+    }
+    start.elapsed()
+}
+
+fn run(scenario: &Scenario, path: &str, type_ids: &[TypeID]) {
+    let sde = TypeDB::open_with_connections(path, scenario.connections).unwrap();
+    if scenario.preload {
+        sde.preload().unwrap();
+    }
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(WORKERS)
+        .build()
+        .unwrap();
+
+    let mut rng = Rng(0x5eed);
+    let work: Vec<(Vec<TypeID>, Vec<TypeID>)> = (0..REQUESTS)
+        .map(|_| {
+            (
+                rng.pick(type_ids, HULLS_PER_REQUEST),
+                rng.pick(type_ids, MODULES_PER_REQUEST),
+            )
+        })
+        .collect();
+    let work = Arc::new(std::sync::Mutex::new(work));
+
+    let start = Instant::now();
+    let mut latencies: Vec<Duration> = runtime.block_on(async {
+        let mut handles = Vec::new();
+        for _ in 0..IN_FLIGHT {
+            let work = work.clone();
+            let mode = scenario.mode;
+            handles.push(tokio::spawn(async move {
+                let mut latencies = Vec::new();
+                loop {
+                    let next = work.lock().unwrap().pop();
+                    match next {
+                        Some((hulls, modules)) => {
+                            latencies.push(request(mode, hulls, modules).await)
+                        }
+                        None => break latencies,
+                    }
+                }
+            }));
+        }
+
+        let mut latencies = Vec::new();
+        for handle in handles {
+            latencies.extend(handle.await.unwrap());
+        }
+        latencies
+    });
+    let elapsed = start.elapsed();
+
+    latencies.sort();
+    let percentile = |p: usize| latencies[(latencies.len() - 1) * p / 100];
+    println!(
+        "{:<36} p50 {:>9.2?}  p95 {:>9.2?}  p99 {:>9.2?}  {:>7.0} req/s",
+        scenario.name,
+        percentile(50),
+        percentile(95),
+        percentile(99),
+        REQUESTS as f64 / elapsed.as_secs_f64()
+    );
+}
+
+fn main() {
+    let path = std::env::var("SDE_PATH").unwrap_or_else(|_| "sqlite-shrunk.sqlite".to_string());
+    let type_ids = published_type_ids(&path);
+
+    let scenarios = [
+        Scenario {
+            name: "blocking, 1 connection",
+            connections: 1,
+            preload: false,
+            mode: Mode::Blocking,
+        },
+        Scenario {
+            name: "async, 1 connection",
+            connections: 1,
+            preload: false,
+            mode: Mode::Async,
+        },
+        Scenario {
+            name: "async, pooled",
+            connections: DEFAULT_CONNECTIONS,
+            preload: false,
+            mode: Mode::Async,
+        },
+        Scenario {
+            name: "async, pooled, preloaded names",
+            connections: DEFAULT_CONNECTIONS,
+            preload: true,
+            mode: Mode::Async,
+        },
+    ];
+
+    println!(
+        "{} requests, {} in flight, {} workers, {} published types",
+        REQUESTS,
+        IN_FLIGHT,
+        WORKERS,
+        type_ids.len()
+    );
+    for scenario in &scenarios {
+        run(scenario, &path, &type_ids);
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{Type, TypeDB, TypeError, TypeID, SDE};
+
+/// `TypeDB` for async code. Lookups that can be answered from memory return straight away,
+/// anything that needs SQLite runs on tokio's blocking pool instead of the calling worker.
+pub struct AsyncTypeDB {}
+impl AsyncTypeDB {
+    /// Runs `f` against the active SDE on the blocking pool.
+    pub async fn run<T, F>(f: F) -> Result<T, TypeError>
+    where
+        F: FnOnce(&SDE) -> Result<T, TypeError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let sde = TypeDB::active()?;
+        Self::run_on(sde, f).await
+    }
+
+    async fn run_on<T, F>(sde: Arc<SDE>, f: F) -> Result<T, TypeError>
+    where
+        F: FnOnce(&SDE) -> Result<T, TypeError> + Send + 'static,
+        T: Send + 'static,
+    {
+        match tokio::task::spawn_blocking(move || f(&sde)).await {
+            Ok(result) => result,
+            Err(_) => Err(TypeError::Background),
+        }
+    }
+
+    pub async fn load_types(
+        ids: &[TypeID],
+    ) -> Result<HashMap<TypeID, Option<Arc<Type>>>, TypeError> {
+        let sde = TypeDB::active()?;
+        if let Some(types) = sde.cached_types(ids) {
+            return Ok(types);
+        }
+        let ids = ids.to_vec();
+        Self::run_on(sde, move |sde| sde.load_types(&ids)).await
+    }
+
+    pub async fn load_type(id: TypeID) -> Result<Arc<Type>, TypeError> {
+        match Self::load_types(&[id]).await?.remove(&id) {
+            Some(Some(the_type)) => Ok(the_type),
+            _ => Err(TypeError::NothingMatched),
+        }
+    }
+
+    pub async fn names_of(ids: &[TypeID]) -> Result<HashMap<TypeID, String>, TypeError> {
+        let sde = TypeDB::active()?;
+        if let Some(names) = sde.cached_names_of(ids) {
+            return Ok(names);
+        }
+        let ids = ids.to_vec();
+        Self::run_on(sde, move |sde| sde.names_of(&ids)).await
+    }
+
+    pub async fn name_of(id: TypeID) -> Result<String, TypeError> {
+        match Self::names_of(&[id]).await?.remove(&id) {
+            Some(name) => Ok(name),
+            None => Err(TypeError::NothingMatched),
+        }
+    }
+
+    pub async fn names_of_locale(
+        ids: &[TypeID],
+        locale: &str,
+    ) -> Result<HashMap<TypeID, String>, TypeError> {
+        let ids = ids.to_vec();
+        let locale = locale.to_string();
+        Self::run(move |sde| sde.names_of_locale(&ids, &locale)).await
+    }
+
+    pub async fn ids_of(names: &[&str]) -> Result<HashMap<String, TypeID>, TypeError> {
+        let sde = TypeDB::active()?;
+        if let Some(ids) = sde.cached_ids_of(names) {
+            return Ok(ids);
+        }
+        let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        Self::run_on(sde, move |sde| {
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            Ok(sde
+                .ids_of(&names)?
+                .into_iter()
+                .map(|(name, id)| (name.to_string(), id))
+                .collect())
+        })
+        .await
+    }
+
+    pub async fn id_of(name: &str) -> Result<TypeID, TypeError> {
+        match Self::ids_of(&[name]).await?.remove(name) {
+            Some(id) => Ok(id),
+            None => Err(TypeError::NothingMatched),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AsyncTypeDB;
+    use crate::TypeDB;
+
+    #[test]
+    fn test_async_lookups() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let capsule = AsyncTypeDB::id_of("Capsule").await.unwrap();
+            assert_eq!(capsule, 670);
+            assert_eq!(AsyncTypeDB::name_of(capsule).await.unwrap(), "Capsule");
+            assert_eq!(
+                AsyncTypeDB::names_of(&[capsule, -1]).await.unwrap(),
+                TypeDB::names_of(&[capsule, -1]).unwrap()
+            );
+            assert_eq!(
+                AsyncTypeDB::load_type(capsule).await.unwrap().name,
+                "Capsule"
+            );
+            assert!(AsyncTypeDB::id_of("TDF Titan").await.is_err());
+        });
+    }
+}
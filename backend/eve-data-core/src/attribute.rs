@@ -21,6 +21,31 @@ pub enum Attribute {
     SenarySkill,
     SenarySkillLevel,
 
+    LowSlots,
+    MedSlots,
+    HiSlots,
+    RigSlots,
+    LowSlotModifier,
+    MedSlotModifier,
+    HiSlotModifier,
+    TurretSlotsLeft,
+    LauncherSlotsLeft,
+    TurretHardpointModifier,
+    LauncherHardpointModifier,
+    MaxSubsystems,
+
+    CpuOutput,
+    PowerOutput,
+    UpgradeCapacity,
+    Cpu,
+    Power,
+    UpgradeCost,
+    CpuMultiplier,
+    CpuOutputBonus,
+    PowerOutputMultiplier,
+    PowerOutputBonus,
+    PowerIncrease,
+
     Other(i32),
 }
 
@@ -48,6 +73,31 @@ impl Attribute {
             1290 => Self::SenarySkill,
             1288 => Self::SenarySkillLevel,
 
+            12 => Self::LowSlots,
+            13 => Self::MedSlots,
+            14 => Self::HiSlots,
+            1137 => Self::RigSlots,
+            1376 => Self::LowSlotModifier,
+            1375 => Self::MedSlotModifier,
+            1374 => Self::HiSlotModifier,
+            102 => Self::TurretSlotsLeft,
+            101 => Self::LauncherSlotsLeft,
+            1368 => Self::TurretHardpointModifier,
+            1369 => Self::LauncherHardpointModifier,
+            1367 => Self::MaxSubsystems,
+
+            48 => Self::CpuOutput,
+            11 => Self::PowerOutput,
+            1132 => Self::UpgradeCapacity,
+            50 => Self::Cpu,
+            30 => Self::Power,
+            1153 => Self::UpgradeCost,
+            202 => Self::CpuMultiplier,
+            424 => Self::CpuOutputBonus,
+            145 => Self::PowerOutputMultiplier,
+            313 => Self::PowerOutputBonus,
+            549 => Self::PowerIncrease,
+
             i => Self::Other(i),
         }
     }
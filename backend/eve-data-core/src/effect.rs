@@ -13,4 +13,13 @@ impl Effect {
     pub fn rig_slot() -> Effect {
         Effect(2663)
     }
+    pub fn subsystem() -> Effect {
+        Effect(3772)
+    }
+    pub fn turret_fitted() -> Effect {
+        Effect(42)
+    }
+    pub fn launcher_fitted() -> Effect {
+        Effect(40)
+    }
 }
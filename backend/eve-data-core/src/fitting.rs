@@ -5,13 +5,15 @@ use std::{
 
 use crate::TypeError;
 
-use super::{Category, TypeDB, TypeID};
+use super::{Category, SkillTree, Type, TypeDB, TypeID};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Fitting {
     pub hull: TypeID,
     pub modules: BTreeMap<TypeID, i64>,
     pub cargo: BTreeMap<TypeID, i64>,
+    /// Loaded charges: module type -> charge type -> how many of those modules have it loaded
+    pub charges: BTreeMap<TypeID, BTreeMap<TypeID, i64>>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -24,6 +26,18 @@ pub enum FitError {
     InvalidCount,
     #[error("only ships can fly")]
     InvalidHull,
+    #[error("{used} {slot} modules fitted, the ship only has {available}")]
+    TooManyModules {
+        slot: &'static str,
+        used: i64,
+        available: i64,
+    },
+    #[error("fit needs {used:.2} {resource}, the ship only has {available:.2}")]
+    Overfitted {
+        resource: &'static str,
+        used: f64,
+        available: f64,
+    },
     #[error("internal error: {0}")]
     Internal(#[source] TypeError),
 }
@@ -37,12 +51,106 @@ impl From<ParseIntError> for FitError {
 impl From<TypeError> for FitError {
     fn from(e: TypeError) -> Self {
         match &e {
-            TypeError::Database(_) | &TypeError::MultipleMatches => FitError::Internal(e),
+            TypeError::Database(_) | TypeError::MultipleMatches | TypeError::Background => {
+                FitError::Internal(e)
+            }
             TypeError::NothingMatched => FitError::InvalidModule,
         }
     }
 }
 
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EftLineError {
+    #[error("line is not part of a fit, fits start with [Hull, Fit name]")]
+    OutsideFit,
+    #[error("unknown item type")]
+    UnknownType,
+    #[error("invalid item count")]
+    InvalidCount,
+}
+
+impl EftLineError {
+    /// Whether the fit came out different from what the line asked for. Text pasted around
+    /// the fit doesn't change it, so it's only worth a warning.
+    pub fn changes_fit(self) -> bool {
+        !matches!(self, EftLineError::OutsideFit)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EftDiagnostic {
+    /// 1-based, as shown in a text editor
+    pub line: usize,
+    pub text: String,
+    pub error: EftLineError,
+    pub suggestion: Option<String>,
+}
+
+impl std::fmt::Display for EftDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Line {}: {} ({})",
+            self.line,
+            self.error,
+            self.text.trim()
+        )?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", did you mean {}?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct EftParse {
+    pub fittings: Vec<Fitting>,
+    pub diagnostics: Vec<EftDiagnostic>,
+}
+
+// A single item line: "Name", "Name xCount", "Module, Charge", each optionally followed by "/OFFLINE"
+struct EftLine<'a> {
+    name: &'a str,
+    charge: Option<&'a str>,
+    count: Option<Result<i64, ParseIntError>>,
+}
+
+impl<'a> EftLine<'a> {
+    fn parse(line: &'a str) -> EftLine<'a> {
+        let line = line.strip_suffix("/OFFLINE").unwrap_or(line).trim_end();
+
+        let (line, count) = match line.rsplit_once(" x") {
+            Some((name, count))
+                if !count.is_empty() && count.chars().all(|c| c.is_ascii_digit() || c == '-') =>
+            {
+                (name.trim_end(), Some(count.parse()))
+            }
+            _ => (line, None),
+        };
+
+        match line.split_once(',') {
+            Some((name, charge)) => EftLine {
+                name: name.trim(),
+                charge: Some(charge.trim()).filter(|charge| !charge.is_empty()),
+                count,
+            },
+            None => EftLine {
+                name: line,
+                charge: None,
+                count,
+            },
+        }
+    }
+}
+
+fn lookup(name: &str) -> Result<Option<TypeID>, FitError> {
+    match TypeDB::id_of(name) {
+        Ok(type_id) => Ok(Some(type_id)),
+        Err(TypeError::NothingMatched) => Ok(None),
+        Err(e) => Err(FitError::Internal(e)),
+    }
+}
+
 impl Fitting {
     pub fn from_dna(dna: &str) -> Result<Fitting, FitError> {
         let mut pieces = dna.split(':');
@@ -93,13 +201,15 @@ impl Fitting {
             hull,
             modules,
             cargo,
+            charges: BTreeMap::new(),
         })
     }
 
+    /// Ship DNA has no place for loaded charges, see `charges_to_string` for those.
     pub fn to_dna(&self) -> Result<String, FitError> {
         let mut dna = format!("{}:", self.hull);
 
-        for (id, count) in &self.modules {
+        for (id, &count) in &self.modules {
             // XXX We could/should sort this by slot
             dna += &format!("{};{}:", id, count);
         }
@@ -116,66 +226,265 @@ impl Fitting {
         Ok(dna + ":")
     }
 
+    /// The loaded charges as `module;charge;count` entries separated by `:`, to be kept
+    /// next to the DNA.
+    pub fn charges_to_string(&self) -> String {
+        let mut entries = Vec::new();
+        for (module, charges) in &self.charges {
+            for (charge, count) in charges {
+                entries.push(format!("{};{};{}", module, charge, count));
+            }
+        }
+        entries.join(":")
+    }
+
+    /// Loads charges from what `charges_to_string` wrote into the fitted modules.
+    pub fn with_charges(mut self, charges: &str) -> Result<Fitting, FitError> {
+        for entry in charges.split(':').filter(|entry| !entry.is_empty()) {
+            let mut split = entry.split(';');
+            let (module, charge, count) = match (split.next(), split.next(), split.next()) {
+                (Some(module), Some(charge), Some(count)) => (
+                    module.parse::<TypeID>()?,
+                    charge.parse::<TypeID>()?,
+                    count.parse::<i64>()?,
+                ),
+                _ => return Err(FitError::ParseError),
+            };
+            if split.next().is_some() || !self.modules.contains_key(&module) {
+                return Err(FitError::ParseError);
+            }
+            *self
+                .charges
+                .entry(module)
+                .or_default()
+                .entry(charge)
+                .or_insert(0) += count;
+        }
+        Ok(self)
+    }
+
+    pub fn to_eft(&self, name: &str) -> Result<String, FitError> {
+        let mut all_ids = vec![self.hull];
+        all_ids.extend(self.modules.keys());
+        all_ids.extend(self.cargo.keys());
+        all_ids.extend(self.charges.values().flat_map(|charges| charges.keys()));
+        let types = TypeDB::load_types(&all_ids)?;
+        let get_type = |id: &TypeID| -> Result<&Type, FitError> {
+            match types.get(id) {
+                Some(Some(the_type)) => Ok(the_type),
+                _ => Err(FitError::InvalidModule),
+            }
+        };
+
+        let hull = get_type(&self.hull)?;
+        let mut fitted = Vec::new();
+        for (id, &count) in &self.modules {
+            let mut charges = Vec::new();
+            for (charge, &loaded) in self.charges.get(id).into_iter().flatten() {
+                charges.push((get_type(charge)?, loaded));
+            }
+            fitted.push((get_type(id)?, count, charges));
+        }
+
+        // Sections are low,med,high,rig,subsystem, an empty one, then drones
+        let mut sections: [Vec<String>; 7] = Default::default();
+        for (module, count, charges) in &fitted {
+            let (module, count) = (*module, *count);
+            match module.slot() {
+                Some("drone") => sections[6].push(format!("{} x{}", module.name, count)),
+                slot => {
+                    let section = match slot {
+                        Some("low") => 0,
+                        Some("med") => 1,
+                        Some("high") => 2,
+                        Some("rig") => 3,
+                        _ => 4,
+                    };
+                    let mut unloaded = count;
+                    for &(charge, loaded) in charges {
+                        for _ in 0..loaded {
+                            sections[section].push(format!("{}, {}", module.name, charge.name));
+                        }
+                        unloaded -= loaded;
+                    }
+                    for _ in 0..unloaded {
+                        sections[section].push(module.name.clone());
+                    }
+                }
+            }
+        }
+
+        let slots = self.layout_with(&types, |_| 0)?.slots;
+        let empty_slots = [
+            ("Low", slots.low),
+            ("Med", slots.med),
+            ("High", slots.high),
+            ("Rig", slots.rig),
+        ];
+        for (section, &(slot_name, total)) in empty_slots.iter().enumerate() {
+            let used = sections[section].len() as i64;
+            for _ in used..total {
+                sections[section].push(format!("[Empty {} slot]", slot_name));
+            }
+        }
+
+        let mut eft = format!("[{}, {}]\n", hull.name, name);
+        for section in &sections {
+            for line in section {
+                eft += line;
+                eft += "\n";
+            }
+            eft += "\n";
+        }
+        for (id, count) in &self.cargo {
+            eft += &format!("{} x{}\n", get_type(id)?.name, count);
+        }
+
+        Ok(eft)
+    }
+
     pub fn from_eft(eft: &str) -> Result<Vec<Fitting>, FitError> {
+        let parsed = Self::from_eft_lenient(eft)?;
+        let mut diagnostics = parsed.diagnostics.into_iter();
+        if let Some(diagnostic) = diagnostics.find(|d| d.error.changes_fit()) {
+            return Err(match diagnostic.error {
+                EftLineError::UnknownType => FitError::InvalidModule,
+                _ => FitError::ParseError,
+            });
+        }
+        Ok(parsed.fittings)
+    }
+
+    /// Parses as much of the EFT as possible, reporting every line that couldn't be used
+    /// instead of stopping at the first one. Only internal errors are returned as `Err`.
+    pub fn from_eft_lenient(eft: &str) -> Result<EftParse, FitError> {
         let mut fittings = Vec::new();
+        let mut diagnostics = Vec::new();
         let mut section = 0;
-        for line in eft.lines() {
-            let line = line.trim();
+        // Set when the last header was bad, so we don't report every line of that fit
+        let mut skipping = false;
+
+        for (line_number, raw_line) in eft.lines().enumerate() {
+            let line = raw_line.trim();
+            let mut report = |error: EftLineError, suggestion: Option<String>| {
+                diagnostics.push(EftDiagnostic {
+                    line: line_number + 1,
+                    text: raw_line.to_string(),
+                    error,
+                    suggestion,
+                });
+            };
 
             if line.starts_with('[') && line.ends_with(']') && line.contains(',') {
                 let line = line.strip_prefix('[').unwrap().strip_suffix(']').unwrap();
-                let mut pieces = line.splitn(2, ',');
-                let hull_name = pieces.next().unwrap().trim(); // 1st elmt
-                let ship_name = pieces.next();
-                if ship_name.is_none() {
-                    return Err(FitError::ParseError);
-                }
-                let hull = TypeDB::id_of(hull_name)?;
-                fittings.push(Fitting {
-                    hull,
-                    cargo: BTreeMap::new(),
-                    modules: BTreeMap::new(),
-                });
+                let (hull_name, _ship_name) = line.split_once(',').unwrap();
+                let hull_name = hull_name.trim();
                 section = 0;
-            } else if let Some(fit) = fittings.last_mut() {
-                if line.starts_with("[Empty ") {
-                    continue;
+                match lookup(hull_name)? {
+                    Some(hull) => {
+                        fittings.push(Fitting {
+                            hull,
+                            cargo: BTreeMap::new(),
+                            modules: BTreeMap::new(),
+                            charges: BTreeMap::new(),
+                        });
+                        skipping = false;
+                    }
+                    None => {
+                        report(EftLineError::UnknownType, TypeDB::suggest(hull_name)?);
+                        skipping = true;
+                    }
                 }
-                if line.is_empty() {
-                    section += 1;
-                } else {
-                    let mut pieces = line.split(" x");
-                    let type_name = pieces.next().unwrap(); // 1st elmt
-                    let type_id = TypeDB::id_of(type_name)?;
-
-                    let (count, stacked) = match pieces.next() {
-                        None => (1, false),
-                        Some(s) => (s.parse()?, true),
-                    };
+                continue;
+            }
 
-                    let is_cargo = if section >= 7 {
-                        // Sections are high,med,low,rig,subsystem,drone, then cargo
-                        true
-                    } else {
-                        let type_obj = TypeDB::load_type(type_id)?;
-                        type_obj.is_always_cargo()
-                            || (stacked && type_obj.category != Category::Drone)
-                    };
+            if skipping {
+                continue;
+            }
+            let fit = match fittings.last_mut() {
+                Some(fit) => fit,
+                None => {
+                    if !line.is_empty() {
+                        report(EftLineError::OutsideFit, None);
+                    }
+                    continue;
+                }
+            };
 
-                    let desto = if is_cargo {
-                        &mut fit.cargo
-                    } else {
-                        &mut fit.modules
-                    };
+            // Empty slot placeholders, "[Empty Low slot]" in English but translated in other clients
+            if line.starts_with('[') && line.ends_with(']') {
+                continue;
+            }
+            if line.is_empty() {
+                section += 1;
+                continue;
+            }
 
-                    *desto.entry(type_id).or_insert(0) += count;
+            let parsed = EftLine::parse(line);
+            let count = match parsed.count {
+                None => 1,
+                Some(Ok(count)) if count > 0 => count,
+                Some(_) => {
+                    report(EftLineError::InvalidCount, None);
+                    continue;
                 }
+            };
+            let type_id = match lookup(parsed.name)? {
+                Some(type_id) => type_id,
+                None => {
+                    report(EftLineError::UnknownType, TypeDB::suggest(parsed.name)?);
+                    continue;
+                }
+            };
+            let charge = match parsed.charge {
+                None => None,
+                Some(charge) => match lookup(charge)? {
+                    Some(charge) => Some(charge),
+                    None => {
+                        report(EftLineError::UnknownType, TypeDB::suggest(charge)?);
+                        continue;
+                    }
+                },
+            };
+
+            let is_cargo = if section >= 7 {
+                // Sections are low,med,high,rig,subsystem, an empty one, drones, then cargo
+                true
             } else {
-                return Err(FitError::ParseError);
+                let type_obj = TypeDB::load_type(type_id)?;
+                type_obj.is_always_cargo()
+                    || (parsed.count.is_some() && type_obj.category != Category::Drone)
+            };
+
+            let desto = if is_cargo {
+                &mut fit.cargo
+            } else {
+                &mut fit.modules
+            };
+
+            *desto.entry(type_id).or_insert(0) += count;
+
+            if let (Some(charge), false) = (charge, is_cargo) {
+                *fit.charges
+                    .entry(type_id)
+                    .or_insert_with(BTreeMap::new)
+                    .entry(charge)
+                    .or_insert(0) += count;
             }
         }
 
-        Ok(fittings)
+        Ok(EftParse {
+            fittings,
+            diagnostics,
+        })
+    }
+
+    /// Everything needed to fly the hull and use the fitted modules and loaded charges.
+    pub fn skill_tree(&self) -> Result<SkillTree, FitError> {
+        let mut ids = vec![self.hull];
+        ids.extend(self.modules.keys());
+        ids.extend(self.charges.values().flat_map(|charges| charges.keys()));
+        Ok(TypeDB::skill_tree(&ids)?)
     }
 
     pub fn validate(&self) -> Result<(), FitError> {
@@ -194,6 +503,14 @@ impl Fitting {
                 return Err(FitError::InvalidCount);
             }
         }
+        for (module, charges) in &self.charges {
+            let fitted = self.modules.get(module).copied().unwrap_or(0);
+            let loaded: i64 = charges.values().sum();
+            if loaded > fitted || charges.values().any(|&count| count <= 0) {
+                return Err(FitError::InvalidCount);
+            }
+            all_ids.extend(charges.keys());
+        }
         let all_ids = all_ids.into_iter().collect::<Vec<_>>();
 
         // Load stuff!
@@ -222,13 +539,17 @@ impl Fitting {
             }
         }
 
-        Ok(())
+        // Make sure the modules fit in the slots. CPU and powergrid are left to the fit
+        // checker, as hull bonuses and implants aren't modelled.
+        self.layout_with(&loaded_types, |_| 5)?.check_slots()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Fitting;
+    use super::{EftLine, EftLineError, Fitting};
+    use proptest::prelude::*;
+    use std::collections::BTreeMap;
 
     #[test]
     fn test_parse_dna() {
@@ -314,4 +635,156 @@ Hobgoblin II x2
         assert_eq!(*parsed.modules.get(&2456).unwrap(), 2);
         assert_eq!(*parsed.cargo.get(&20353).unwrap(), 1);
     }
+
+    #[test]
+    fn test_loaded_charges() {
+        let parsed = Fitting::from_eft(
+            "[Nightmare, Nightmare]
+
+Mega Pulse Laser II, Conflagration L
+Mega Pulse Laser II, Conflagration L
+Mega Pulse Laser II
+",
+        )
+        .unwrap()
+        .pop()
+        .unwrap();
+        assert_eq!(*parsed.modules.get(&3057).unwrap(), 3);
+        assert_eq!(*parsed.charges.get(&3057).unwrap().get(&12816).unwrap(), 2);
+        assert!(parsed.cargo.is_empty());
+        assert_eq!(parsed.to_dna().unwrap(), "17736:3057;3::");
+        assert_eq!(parsed.charges_to_string(), "3057;12816;2");
+
+        let from_dna = Fitting::from_dna("17736:3057;3::")
+            .unwrap()
+            .with_charges("3057;12816;2")
+            .unwrap();
+        assert_eq!(from_dna, parsed);
+        assert!(Fitting::from_dna("17736:3057;2;12816::").is_err());
+        let unfitted = Fitting::from_dna("17736:3057;3::").unwrap();
+        assert!(unfitted.with_charges("2456;12816;1").is_err());
+    }
+
+    #[test]
+    fn test_eft_line() {
+        let line = EftLine::parse("Mega Pulse Laser II, Conflagration L /OFFLINE");
+        assert_eq!(line.name, "Mega Pulse Laser II");
+        assert_eq!(line.charge, Some("Conflagration L"));
+        assert!(line.count.is_none());
+
+        let line = EftLine::parse("Hobgoblin II x5");
+        assert_eq!(line.name, "Hobgoblin II");
+        assert_eq!(line.count, Some(Ok(5)));
+
+        let line = EftLine::parse("Hobgoblin II xlarge");
+        assert_eq!(line.name, "Hobgoblin II xlarge");
+        assert!(line.count.is_none());
+    }
+
+    #[test]
+    fn test_parse_eft_lenient() {
+        let parsed = Fitting::from_eft_lenient(
+            "x-up pls
+[Nightmare, Nightmare]
+Mega Pulse Lazer II
+Mega Pulse Laser II, Conflagration L
+[Empty High slot]
+
+Conflagration L x0
+
+[Nightmaer, Typo]
+Mega Pulse Laser II
+",
+        )
+        .unwrap();
+        assert_eq!(parsed.fittings.len(), 1);
+        assert_eq!(*parsed.fittings[0].modules.get(&3057).unwrap(), 1);
+
+        let errors: Vec<_> = parsed
+            .diagnostics
+            .iter()
+            .map(|d| (d.line, d.error, d.suggestion.as_deref()))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (1, EftLineError::OutsideFit, None),
+                (3, EftLineError::UnknownType, Some("Mega Pulse Laser II")),
+                (7, EftLineError::InvalidCount, None),
+                (9, EftLineError::UnknownType, Some("Nightmare")),
+            ]
+        );
+
+        assert!(Fitting::from_eft("[Nightmare, Nightmare]\nMega Pulse Lazer II").is_err());
+        assert_eq!(
+            Fitting::from_eft("x-up pls\n[Nightmare, Nightmare]\nMega Pulse Laser II")
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_to_eft() {
+        let parsed = Fitting::from_dna("17736:3057;4:12816;2:4383_;1:2456;2::").unwrap();
+        let eft = parsed.to_eft("Nightmare").unwrap();
+        assert!(eft.starts_with("[Nightmare, Nightmare]\n"));
+        assert_eq!(eft.matches("Mega Pulse Laser II\n").count(), 4);
+        assert!(eft.contains("\nHobgoblin II x2\n"));
+        assert!(eft.ends_with("\nLarge Micro Jump Drive x1\nConflagration L x2\n"));
+
+        let reparsed = Fitting::from_eft(&eft).unwrap().pop().unwrap();
+        assert_eq!(reparsed, parsed);
+        assert_eq!(reparsed.to_eft("Nightmare").unwrap(), eft);
+    }
+
+    // Nightmare, Vindicator, Venture
+    const HULLS: &[i32] = &[17736, 17740, 32880];
+    // Mega Pulse Laser II, 1600mm Steel Plates II, Large Micro Jump Drive, Hobgoblin II
+    const MODULES: &[i32] = &[3057, 20353, 4383, 2456];
+    // Conflagration L, Large Micro Jump Drive, Hobgoblin II
+    const CARGO: &[i32] = &[12816, 4383, 2456];
+
+    fn fitting_strategy() -> impl Strategy<Value = Fitting> {
+        (
+            prop::sample::select(HULLS),
+            prop::collection::btree_map(prop::sample::select(MODULES), 1..5i64, 0..4),
+            prop::collection::btree_map(prop::sample::select(CARGO), 1..1000i64, 0..3),
+            0..5i64,
+        )
+            .prop_map(|(hull, modules, cargo, loaded)| {
+                // Load Conflagration L into some of the lasers
+                let mut charges = BTreeMap::new();
+                let loaded = loaded.min(modules.get(&3057).copied().unwrap_or(0));
+                if loaded > 0 {
+                    charges.insert(3057, BTreeMap::from([(12816, loaded)]));
+                }
+                Fitting {
+                    hull,
+                    modules,
+                    cargo,
+                    charges,
+                }
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn roundtrip_eft(fit in fitting_strategy()) {
+            let eft = fit.to_eft("Roundtrip").unwrap();
+            let parsed = Fitting::from_eft(&eft).unwrap();
+            prop_assert_eq!(parsed.len(), 1);
+            prop_assert_eq!(&parsed[0], &fit);
+            prop_assert_eq!(parsed[0].to_eft("Roundtrip").unwrap(), eft);
+        }
+
+        #[test]
+        fn roundtrip_dna(fit in fitting_strategy()) {
+            let dna = fit.to_dna().unwrap();
+            let charges = fit.charges_to_string();
+            let parsed = Fitting::from_dna(&dna).unwrap().with_charges(&charges).unwrap();
+            prop_assert_eq!(&parsed, &fit);
+            prop_assert_eq!(parsed.to_dna().unwrap(), dna);
+        }
+    }
 }
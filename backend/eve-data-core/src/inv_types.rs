@@ -2,7 +2,8 @@ use super::{Attribute, Category, Effect};
 use rusqlite::OptionalExtension;
 use std::collections::{HashMap, HashSet};
 use std::iter;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 
 pub type TypeID = i32;
 pub type SkillLevel = i8;
@@ -15,6 +16,8 @@ pub enum TypeError {
     NothingMatched,
     #[error("unexpectedly got multiple item matches")]
     MultipleMatches,
+    #[error("background lookup did not complete")]
+    Background,
 }
 
 #[derive(Clone, Debug)]
@@ -22,6 +25,8 @@ pub struct Type {
     pub id: TypeID,
     pub name: String,
     pub category: Category,
+    pub group_id: i32,
+    pub market_group_id: Option<i32>,
     pub attributes: HashMap<Attribute, f32>,
     pub effects: HashSet<Effect>,
     pub skill_requirements: HashMap<TypeID, SkillLevel>,
@@ -52,21 +57,177 @@ impl Type {
     }
 }
 
-std::thread_local!(static CONN: rusqlite::Connection = rusqlite::Connection::open("sqlite-shrunk.sqlite").unwrap());
+const DEFAULT_PATH: &str = "sqlite-shrunk.sqlite";
+pub const DEFAULT_CONNECTIONS: usize = 4;
 
 lazy_static::lazy_static! {
-    static ref TYPE_CACHE: RwLock<HashMap<TypeID, Option<Arc<Type>>>> = RwLock::new(HashMap::new());
-    static ref NAME_CACHE: RwLock<HashMap<String, TypeID>> = RwLock::new(HashMap::new());
-    static ref MAX_TYPE_ID: TypeID = TypeDB::get_max_type_id().unwrap();
+    static ref ACTIVE: RwLock<Option<Arc<SDE>>> = RwLock::new(None);
 }
 
-pub struct TypeDB {}
-impl TypeDB {
-    fn load_types_from_db(ids: &[TypeID]) -> Result<HashMap<TypeID, Option<Type>>, TypeError> {
+/// An opened SDE database, along with everything we cached from it.
+pub struct SDE {
+    path: String,
+    conns: Vec<Mutex<rusqlite::Connection>>,
+    next_conn: AtomicUsize,
+    type_cache: RwLock<HashMap<TypeID, Option<Arc<Type>>>>,
+    name_cache: RwLock<HashMap<String, TypeID>>,
+    type_names: RwLock<Option<Arc<HashMap<TypeID, String>>>>,
+    published_names: RwLock<Option<Arc<Vec<String>>>>,
+    max_type_id: TypeID,
+    build_number: Option<i64>,
+    has_localized_names: bool,
+    has_market_groups: bool,
+}
+
+impl SDE {
+    pub fn open(path: &str) -> Result<SDE, TypeError> {
+        Self::open_with_connections(path, DEFAULT_CONNECTIONS)
+    }
+
+    /// Opens the database with `connections` read-only handles, so that that many lookups
+    /// can run at the same time.
+    pub fn open_with_connections(path: &str, connections: usize) -> Result<SDE, TypeError> {
+        let mut conns = Vec::new();
+        for _ in 0..connections.max(1) {
+            conns.push(Mutex::new(rusqlite::Connection::open_with_flags(
+                path,
+                rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+            )?));
+        }
+        let mut sde = SDE {
+            path: path.to_string(),
+            conns,
+            next_conn: AtomicUsize::new(0),
+            type_cache: RwLock::new(HashMap::new()),
+            name_cache: RwLock::new(HashMap::new()),
+            type_names: RwLock::new(None),
+            published_names: RwLock::new(None),
+            max_type_id: 0,
+            build_number: None,
+            has_localized_names: false,
+            has_market_groups: false,
+        };
+        sde.max_type_id = sde.get_max_type_id()?;
+        sde.build_number = sde.get_build_number()?;
+        sde.has_localized_names = sde.has_table("invTypeNames")?;
+        sde.has_market_groups = sde.has_table("invMarketGroups")?;
+        Ok(sde)
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn build_number(&self) -> Option<i64> {
+        self.build_number
+    }
+
+    fn has_table(&self, table: &str) -> Result<bool, TypeError> {
+        Ok(self.with_conn(|conn| {
+            conn.query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?",
+                [table],
+                |row| row.get::<_, i64>(0),
+            )
+        })? > 0)
+    }
+
+    fn with_conn<T>(&self, f: impl FnOnce(&rusqlite::Connection) -> T) -> T {
+        // Use whichever connection is free, and only wait when they're all busy
+        let start = self.next_conn.fetch_add(1, Ordering::Relaxed);
+        for i in 0..self.conns.len() {
+            if let Ok(conn) = self.conns[(start + i) % self.conns.len()].try_lock() {
+                return f(&conn);
+            }
+        }
+        f(&self.conns[start % self.conns.len()].lock().unwrap())
+    }
+
+    /// Loads the name of every type into memory, so that name and ID lookups never have to
+    /// touch the database. Type details are still loaded on demand.
+    pub fn preload(&self) -> Result<(), TypeError> {
+        let rows = self.with_conn(|conn| -> Result<Vec<(TypeID, String, bool)>, rusqlite::Error> {
+            let mut prepared = conn
+                .prepare("SELECT typeID, typeName, published FROM invTypes ORDER BY published ASC")?;
+            let rows = prepared.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+            rows.collect()
+        })?;
+
+        let mut type_names = HashMap::with_capacity(rows.len());
+        let mut published = Vec::new();
+        {
+            // Published types come last, so they win when a name is used more than once
+            let mut cache = self.name_cache.write().unwrap();
+            for (id, name, is_published) in rows {
+                cache.insert(name.clone(), id);
+                if is_published {
+                    published.push(name.clone());
+                }
+                type_names.insert(id, name);
+            }
+        }
+        *self.type_names.write().unwrap() = Some(Arc::new(type_names));
+        *self.published_names.write().unwrap() = Some(Arc::new(published));
+        Ok(())
+    }
+
+    /// Answers `names_of` from memory, or returns `None` if that would need the database.
+    pub fn cached_names_of(&self, ids: &[TypeID]) -> Option<HashMap<TypeID, String>> {
+        let mut result = HashMap::new();
+        if let Some(type_names) = self.type_names.read().unwrap().as_ref() {
+            for id in ids {
+                // Everything is in the index, so a miss means the type doesn't exist
+                if let Some(name) = type_names.get(id) {
+                    result.insert(*id, name.clone());
+                }
+            }
+            return Some(result);
+        }
+
+        let cache = self.type_cache.read().unwrap();
+        for id in ids {
+            match cache.get(id) {
+                Some(Some(the_type)) => {
+                    result.insert(*id, the_type.name.clone());
+                }
+                Some(None) => (),
+                None => return None,
+            }
+        }
+        Some(result)
+    }
+
+    /// Answers `ids_of` from memory, or returns `None` if that would need the database.
+    pub fn cached_ids_of(&self, names: &[&str]) -> Option<HashMap<String, TypeID>> {
+        let cache = self.name_cache.read().unwrap();
+        let mut result = HashMap::new();
+        for &name in names {
+            result.insert(name.to_string(), *cache.get(name)?);
+        }
+        Some(result)
+    }
+
+    /// Answers `load_types` from memory, or returns `None` if that would need the database.
+    pub fn cached_types(&self, ids: &[TypeID]) -> Option<HashMap<TypeID, Option<Arc<Type>>>> {
+        let cache = self.type_cache.read().unwrap();
+        let mut result = HashMap::new();
+        for id in ids {
+            if *id > self.max_type_id || *id <= 0 {
+                result.insert(*id, None);
+            } else {
+                result.insert(*id, cache.get(id)?.clone());
+            }
+        }
+        Some(result)
+    }
+
+    fn load_types_from_db(
+        &self,
+        ids: &[TypeID],
+    ) -> Result<HashMap<TypeID, Option<Type>>, TypeError> {
         assert!(!ids.is_empty());
 
-        let placeholders = iter::repeat("?")
-            .take(ids.len())
+        let placeholders = iter::repeat_n("?", ids.len())
             .collect::<Vec<&str>>()
             .join(",");
 
@@ -74,18 +235,29 @@ impl TypeDB {
             id: TypeID,
             name: String,
             category: Category,
+            group_id: i32,
+            market_group_id: Option<i32>,
         }
 
-        let mut basic_data = CONN.with(|conn| -> Result<_, rusqlite::Error> {
+        // Older databases don't know about market groups
+        let market_group_column = if self.has_market_groups {
+            "marketGroupID"
+        } else {
+            "NULL"
+        };
+
+        let mut basic_data = self.with_conn(|conn| -> Result<_, rusqlite::Error> {
             let query = format!("
                 SELECT
                     typeID,
                     typeName,
-                    (SELECT categoryID FROM invGroups WHERE invGroups.groupID = invTypes.groupID) categoryID
+                    (SELECT categoryID FROM invGroups WHERE invGroups.groupID = invTypes.groupID) categoryID,
+                    groupID,
+                    {}
                 FROM invTypes
                 WHERE typeID IN ({})
                 ORDER BY published ASC
-            ", placeholders);
+            ", market_group_column, placeholders);
 
             let mut prepared = conn.prepare(&query)?;
             let rows = prepared.query_map(rusqlite::params_from_iter(ids), |row| {
@@ -93,6 +265,8 @@ impl TypeDB {
                     id: row.get(0)?,
                     name: row.get(1)?,
                     category: Category::from_id(row.get(2)?),
+                    group_id: row.get(3)?,
+                    market_group_id: row.get(4)?,
                 })
             })?;
             let mut basic = HashMap::new();
@@ -103,7 +277,7 @@ impl TypeDB {
             Ok(basic)
         })?;
 
-        let mut attributes = CONN.with(|conn| -> Result<_, rusqlite::Error> {
+        let mut attributes = self.with_conn(|conn| -> Result<_, rusqlite::Error> {
             let query = format!("
                 SELECT typeID, attributeID, COALESCE(valueInt,valueFloat) FROM dgmTypeAttributes WHERE typeID IN ({})
             ", placeholders);
@@ -127,7 +301,7 @@ impl TypeDB {
             Ok(result)
         })?;
 
-        let mut effects = CONN.with(|conn| -> Result<_, rusqlite::Error> {
+        let mut effects = self.with_conn(|conn| -> Result<_, rusqlite::Error> {
             let query = format!(
                 "
                 SELECT typeID, effectID FROM dgmTypeEffects WHERE typeID IN ({})
@@ -210,6 +384,8 @@ impl TypeDB {
                         id,
                         name: basic.name,
                         category: basic.category,
+                        group_id: basic.group_id,
+                        market_group_id: basic.market_group_id,
                         attributes: attrs,
                         effects: effects.remove(&id).unwrap(),
                         skill_requirements,
@@ -223,7 +399,10 @@ impl TypeDB {
         Ok(result)
     }
 
-    pub fn load_types(ids: &[TypeID]) -> Result<HashMap<TypeID, Option<Arc<Type>>>, TypeError> {
+    pub fn load_types(
+        &self,
+        ids: &[TypeID],
+    ) -> Result<HashMap<TypeID, Option<Arc<Type>>>, TypeError> {
         let mut unique_ids = HashSet::new();
         for &id in ids {
             unique_ids.insert(id);
@@ -234,8 +413,8 @@ impl TypeDB {
 
         // Fetch from cache
         {
-            let max_id: TypeID = *MAX_TYPE_ID;
-            let cache = TYPE_CACHE.read().unwrap();
+            let max_id: TypeID = self.max_type_id;
+            let cache = self.type_cache.read().unwrap();
             for id in &unique_ids {
                 if *id > max_id || *id <= 0 {
                     result.insert(*id, None);
@@ -248,12 +427,12 @@ impl TypeDB {
         }
 
         if !missing.is_empty() {
-            let from_db = Self::load_types_from_db(&missing)?;
+            let from_db = self.load_types_from_db(&missing)?;
             assert!(from_db.len() == missing.len());
 
             // Save the names just in case we didn't have them yet
             {
-                let mut cache = NAME_CACHE.write().unwrap();
+                let mut cache = self.name_cache.write().unwrap();
                 for (id, typ) in &from_db {
                     if let Some(typ) = typ {
                         cache.insert(typ.name.clone(), *id);
@@ -263,7 +442,7 @@ impl TypeDB {
 
             // Save to cache
             {
-                let mut cache = TYPE_CACHE.write().unwrap();
+                let mut cache = self.type_cache.write().unwrap();
                 for (id, typ) in from_db {
                     let type_opt = typ.map(Arc::new);
                     result.insert(id, type_opt.clone());
@@ -277,8 +456,8 @@ impl TypeDB {
         Ok(result)
     }
 
-    pub fn load_type(id: TypeID) -> Result<Arc<Type>, TypeError> {
-        let mut types = Self::load_types(&[id])?;
+    pub fn load_type(&self, id: TypeID) -> Result<Arc<Type>, TypeError> {
+        let mut types = self.load_types(&[id])?;
         assert!(types.len() == 1);
         if let Some(the_type) = types.remove(&id).unwrap() {
             Ok(the_type)
@@ -287,8 +466,12 @@ impl TypeDB {
         }
     }
 
-    pub fn names_of(ids: &[TypeID]) -> Result<HashMap<TypeID, String>, TypeError> {
-        let types = Self::load_types(ids)?;
+    pub fn names_of(&self, ids: &[TypeID]) -> Result<HashMap<TypeID, String>, TypeError> {
+        if let Some(names) = self.cached_names_of(ids) {
+            return Ok(names);
+        }
+
+        let types = self.load_types(ids)?;
         let mut result = HashMap::new();
         for (id, typ) in types {
             if let Some(typ) = typ {
@@ -298,11 +481,11 @@ impl TypeDB {
         Ok(result)
     }
 
-    pub fn ids_of<'a>(names: &[&'a str]) -> Result<HashMap<&'a str, TypeID>, TypeError> {
+    pub fn ids_of<'a>(&self, names: &[&'a str]) -> Result<HashMap<&'a str, TypeID>, TypeError> {
         let mut result = HashMap::new();
         let mut missing = HashSet::new();
         {
-            let cache = NAME_CACHE.read().unwrap();
+            let cache = self.name_cache.read().unwrap();
             for &name in names {
                 if let Some(id) = cache.get(name) {
                     result.insert(name, *id);
@@ -312,8 +495,8 @@ impl TypeDB {
             }
         }
         if !missing.is_empty() {
-            let from_db = CONN.with(|conn| -> Result<_, TypeError> {
-                let placeholders = iter::repeat("?").take(missing.len()).collect::<Vec<&str>>().join(",");
+            let from_db = self.with_conn(|conn| -> Result<_, TypeError> {
+                let placeholders = iter::repeat_n("?", missing.len()).collect::<Vec<&str>>().join(",");
                 let query = format!("SELECT typeID, typeName FROM invTypes WHERE typeName IN ({}) ORDER BY published ASC", placeholders);
 
                 let mut prepared = conn.prepare(&query)?;
@@ -330,6 +513,44 @@ impl TypeDB {
                 Ok(result)
             })?;
 
+            let mut from_db = from_db;
+            let untranslated: Vec<&str> = missing
+                .iter()
+                .filter(|name| !from_db.contains_key(**name))
+                .copied()
+                .collect();
+            if !untranslated.is_empty() && self.has_localized_names {
+                // Names pasted from a non-English client
+                from_db.extend(self.with_conn(|conn| -> Result<_, TypeError> {
+                    let placeholders = iter::repeat_n("?", untranslated.len())
+                        .collect::<Vec<&str>>()
+                        .join(",");
+                    let query = format!(
+                        "
+                        SELECT invTypeNames.typeID, invTypeNames.typeName
+                        FROM invTypeNames JOIN invTypes ON invTypes.typeID = invTypeNames.typeID
+                        WHERE invTypeNames.typeName IN ({})
+                        ORDER BY published ASC
+                    ",
+                        placeholders
+                    );
+
+                    let mut prepared = conn.prepare(&query)?;
+                    let rows = prepared
+                        .query_map(rusqlite::params_from_iter(untranslated.iter()), |row| {
+                            Ok((row.get(0)?, row.get(1)?))
+                        })?;
+
+                    let mut result: HashMap<String, TypeID> = HashMap::new();
+                    for row in rows {
+                        let (id, name) = row?;
+                        result.insert(name, id);
+                    }
+
+                    Ok(result)
+                })?);
+            }
+
             for name in missing {
                 if let Some(type_id) = from_db.get(name) {
                     result.insert(name, *type_id);
@@ -338,7 +559,7 @@ impl TypeDB {
 
             {
                 // Store them in our cache for future use
-                let mut cache = NAME_CACHE.write().unwrap();
+                let mut cache = self.name_cache.write().unwrap();
                 for (name, id) in from_db {
                     cache.insert(name, id);
                 }
@@ -348,13 +569,65 @@ impl TypeDB {
         Ok(result)
     }
 
-    pub fn name_of(id: TypeID) -> Result<String, TypeError> {
-        let the_type = Self::load_type(id)?;
-        Ok(the_type.name.clone())
+    /// Like `names_of`, but in the given site locale where the SDE has a translation.
+    pub fn names_of_locale(
+        &self,
+        ids: &[TypeID],
+        locale: &str,
+    ) -> Result<HashMap<TypeID, String>, TypeError> {
+        let mut result = self.names_of(ids)?;
+        let language = sde_language(locale);
+        if language == "en" || !self.has_localized_names || result.is_empty() {
+            return Ok(result);
+        }
+
+        let found: Vec<TypeID> = result.keys().copied().collect();
+        let translated = self.with_conn(|conn| -> Result<_, rusqlite::Error> {
+            let placeholders = iter::repeat_n("?", found.len())
+                .collect::<Vec<&str>>()
+                .join(",");
+            let query = format!(
+                "SELECT typeID, typeName FROM invTypeNames WHERE language = ? AND typeID IN ({})",
+                placeholders
+            );
+
+            let mut params: Vec<&dyn rusqlite::ToSql> = vec![&language];
+            for id in &found {
+                params.push(id);
+            }
+
+            let mut prepared = conn.prepare(&query)?;
+            let rows =
+                prepared.query_map(params.as_slice(), |row| Ok((row.get(0)?, row.get(1)?)))?;
+            let mut translated: Vec<(TypeID, String)> = Vec::new();
+            for row in rows {
+                translated.push(row?);
+            }
+            Ok(translated)
+        })?;
+
+        for (id, name) in translated {
+            result.insert(id, name);
+        }
+        Ok(result)
     }
 
-    pub fn id_of(name: &str) -> Result<TypeID, TypeError> {
-        let mut ids = Self::ids_of(&[name])?;
+    pub fn name_of_locale(&self, id: TypeID, locale: &str) -> Result<String, TypeError> {
+        match self.names_of_locale(&[id], locale)?.remove(&id) {
+            Some(name) => Ok(name),
+            None => Err(TypeError::NothingMatched),
+        }
+    }
+
+    pub fn name_of(&self, id: TypeID) -> Result<String, TypeError> {
+        match self.names_of(&[id])?.remove(&id) {
+            Some(name) => Ok(name),
+            None => Err(TypeError::NothingMatched),
+        }
+    }
+
+    pub fn id_of(&self, name: &str) -> Result<TypeID, TypeError> {
+        let mut ids = self.ids_of(&[name])?;
         if let Some(id) = ids.remove(name) {
             Ok(id)
         } else {
@@ -362,8 +635,8 @@ impl TypeDB {
         }
     }
 
-    pub fn id_of_fuzzy(name: &str) -> Result<TypeID, TypeError> {
-        CONN.with(|conn| {
+    pub fn id_of_fuzzy(&self, name: &str) -> Result<TypeID, TypeError> {
+        self.with_conn(|conn| {
             let mut prepared = conn.prepare("SELECT typeID FROM invTypes WHERE typeName LIKE ?")?;
             let mut rows = prepared.query([name])?;
             let mut result = None;
@@ -382,15 +655,167 @@ impl TypeDB {
         })
     }
 
-    fn get_max_type_id() -> Result<TypeID, TypeError> {
-        Ok(CONN.with(|conn| {
+    /// Finds the published type whose name is closest to `name`, for "did you mean" hints.
+    /// Returns `None` if nothing is close enough to be a plausible typo.
+    pub fn suggest(&self, name: &str) -> Result<Option<String>, TypeError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Ok(None);
+        }
+
+        let exact = self.with_conn(|conn| {
+            conn.query_row(
+                "SELECT typeName FROM invTypes WHERE typeName = ? COLLATE NOCASE ORDER BY published DESC LIMIT 1",
+                [name],
+                |row| row.get(0),
+            )
+            .optional()
+        })?;
+        if exact.is_some() {
+            return Ok(exact);
+        }
+
+        let needle = name.to_lowercase();
+        let max_distance = std::cmp::max(2, needle.chars().count() / 5);
+        let mut best: Option<(usize, &String)> = None;
+        let names = self.published_names()?;
+        for candidate in names.iter() {
+            // Cheap check first, the length difference is a lower bound on the distance
+            let length_difference =
+                (candidate.len() as isize - needle.len() as isize).unsigned_abs();
+            if length_difference > max_distance {
+                continue;
+            }
+            let distance = levenshtein(&needle, &candidate.to_lowercase());
+            if distance <= max_distance && best.map(|(d, _)| distance < d).unwrap_or(true) {
+                best = Some((distance, candidate));
+            }
+        }
+
+        Ok(best.map(|(_, name)| name.clone()))
+    }
+
+    fn published_names(&self) -> Result<Arc<Vec<String>>, TypeError> {
+        if let Some(names) = self.published_names.read().unwrap().as_ref() {
+            return Ok(names.clone());
+        }
+
+        let names = self.with_conn(|conn| -> Result<Vec<String>, rusqlite::Error> {
+            let mut prepared = conn.prepare("SELECT typeName FROM invTypes WHERE published = 1")?;
+            let rows = prepared.query_map([], |row| row.get(0))?;
+            rows.collect()
+        })?;
+        let names = Arc::new(names);
+        *self.published_names.write().unwrap() = Some(names.clone());
+        Ok(names)
+    }
+
+    /// All published types in the group, ordered by type ID.
+    pub fn group_type_ids(&self, group_id: i32) -> Result<Vec<TypeID>, TypeError> {
+        Ok(
+            self.with_conn(|conn| -> Result<Vec<TypeID>, rusqlite::Error> {
+                let mut prepared = conn.prepare(
+                "SELECT typeID FROM invTypes WHERE groupID = ? AND published = 1 ORDER BY typeID",
+            )?;
+                let rows = prepared.query_map([group_id], |row| row.get(0))?;
+                rows.collect()
+            })?,
+        )
+    }
+
+    pub fn group_id_of(&self, name: &str) -> Result<i32, TypeError> {
+        self.with_conn(|conn| {
+            conn.query_row(
+                "SELECT groupID FROM invGroups WHERE groupName = ?",
+                [name],
+                |row| row.get(0),
+            )
+            .optional()
+        })?
+        .ok_or(TypeError::NothingMatched)
+    }
+
+    /// Market group names repeat across the tree ("Amarr" shows up under every ship class),
+    /// so a group can also be given as a path from any ancestor, like "Battleships > Amarr".
+    pub fn market_group_id_of(&self, path: &str) -> Result<i32, TypeError> {
+        let groups = self.with_conn(
+            |conn| -> Result<HashMap<i32, (Option<i32>, String)>, rusqlite::Error> {
+                let mut prepared = conn.prepare(
+                    "SELECT marketGroupID, parentGroupID, marketGroupName FROM invMarketGroups",
+                )?;
+                let rows =
+                    prepared.query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?;
+                rows.collect()
+            },
+        )?;
+
+        let segments: Vec<&str> = path.split('>').map(str::trim).collect();
+        let (last, parents) = segments.split_last().unwrap();
+        let mut matches = groups.iter().filter(|(&id, (_parent, name))| {
+            if name != last {
+                return false;
+            }
+            // Walk up the tree, every given parent must be the direct parent of the next one
+            let mut current = id;
+            for parent_name in parents.iter().rev() {
+                match groups.get(&current).and_then(|(parent, _)| *parent) {
+                    Some(parent) if groups[&parent].1 == *parent_name => current = parent,
+                    _ => return false,
+                }
+            }
+            true
+        });
+
+        match (matches.next(), matches.next()) {
+            (Some((&id, _)), None) => Ok(id),
+            (None, _) => Err(TypeError::NothingMatched),
+            (Some(_), Some(_)) => Err(TypeError::MultipleMatches),
+        }
+    }
+
+    /// All published types in the market group and everything below it, ordered by type ID.
+    pub fn market_group_type_ids(&self, market_group_id: i32) -> Result<Vec<TypeID>, TypeError> {
+        Ok(self.with_conn(|conn| -> Result<Vec<TypeID>, rusqlite::Error> {
+            let mut prepared = conn.prepare(
+                "WITH RECURSIVE tree(id) AS (
+                    SELECT ?
+                    UNION SELECT marketGroupID FROM invMarketGroups JOIN tree ON parentGroupID = tree.id
+                )
+                SELECT typeID FROM invTypes
+                WHERE marketGroupID IN (SELECT id FROM tree) AND published = 1
+                ORDER BY typeID",
+            )?;
+            let rows = prepared.query_map([market_group_id], |row| row.get(0))?;
+            rows.collect()
+        })?)
+    }
+
+    fn get_max_type_id(&self) -> Result<TypeID, TypeError> {
+        Ok(self.with_conn(|conn| {
             conn.query_row("SELECT MAX(typeID) FROM invTypes", [], |row| row.get(0))
         })?)
     }
 
-    pub fn type_variations(id: TypeID) -> Result<HashMap<TypeID, i64>, TypeError> {
+    fn get_build_number(&self) -> Result<Option<i64>, TypeError> {
+        // Older databases don't have this table
+        Ok(self
+            .with_conn(|conn| {
+                conn.query_row("SELECT buildNumber FROM sdeInfo", [], |row| row.get(0))
+                    .optional()
+            })
+            .or_else(|e| match e {
+                rusqlite::Error::SqliteFailure(_, Some(ref msg))
+                    if msg.contains("no such table") =>
+                {
+                    Ok(None)
+                }
+                e => Err(e),
+            })?)
+    }
+
+    pub fn type_variations(&self, id: TypeID) -> Result<HashMap<TypeID, i64>, TypeError> {
         let parent_type_id =
-            CONN.with(|conn| -> Result<Option<Option<TypeID>>, rusqlite::Error> {
+            self.with_conn(|conn| -> Result<Option<Option<TypeID>>, rusqlite::Error> {
                 conn.query_row(
                     "SELECT parentTypeID FROM invMetaTypes WHERE typeID=?",
                     [id],
@@ -408,7 +833,7 @@ impl TypeDB {
         let mut metas = HashMap::new();
         metas.insert(parent_type_id, 0);
 
-        CONN.with(|conn| -> Result<_, rusqlite::Error> {
+        self.with_conn(|conn| -> Result<_, rusqlite::Error> {
             let mut prepared = conn.prepare(
                 "
                 SELECT invMetaTypes.typeID, COALESCE(valueInt, valueFloat), metaGroupID
@@ -442,9 +867,134 @@ impl TypeDB {
     }
 }
 
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+// The site uses "cn" for Chinese, the SDE uses "zh"
+fn sde_language(locale: &str) -> String {
+    let language = locale
+        .split(['-', '_'])
+        .next()
+        .unwrap_or(locale)
+        .to_lowercase();
+    match language.as_str() {
+        "cn" => "zh".to_string(),
+        _ => language,
+    }
+}
+
+/// Type lookups against the active SDE. The database is opened from `sqlite-shrunk.sqlite`
+/// unless `TypeDB::open` is called first, and can be swapped at runtime the same way.
+pub struct TypeDB {}
+impl TypeDB {
+    pub fn active() -> Result<Arc<SDE>, TypeError> {
+        if let Some(sde) = ACTIVE.read().unwrap().as_ref() {
+            return Ok(sde.clone());
+        }
+
+        let mut active = ACTIVE.write().unwrap();
+        if active.is_none() {
+            *active = Some(Arc::new(SDE::open(DEFAULT_PATH)?));
+        }
+        Ok(active.as_ref().unwrap().clone())
+    }
+
+    /// Opens the SDE at `path` and makes it the active one. Caches start out empty, so
+    /// anything that resolved names to IDs needs to be reloaded by the caller.
+    pub fn open(path: &str) -> Result<Arc<SDE>, TypeError> {
+        Self::open_with_connections(path, DEFAULT_CONNECTIONS)
+    }
+
+    pub fn open_with_connections(path: &str, connections: usize) -> Result<Arc<SDE>, TypeError> {
+        let sde = Arc::new(SDE::open_with_connections(path, connections)?);
+        Self::activate(sde.clone());
+        Ok(sde)
+    }
+
+    /// Makes an already opened SDE the active one, e.g. to put the previous one back.
+    pub fn activate(sde: Arc<SDE>) {
+        *ACTIVE.write().unwrap() = Some(sde);
+    }
+
+    pub fn load_types(ids: &[TypeID]) -> Result<HashMap<TypeID, Option<Arc<Type>>>, TypeError> {
+        Self::active()?.load_types(ids)
+    }
+
+    pub fn load_type(id: TypeID) -> Result<Arc<Type>, TypeError> {
+        Self::active()?.load_type(id)
+    }
+
+    pub fn names_of(ids: &[TypeID]) -> Result<HashMap<TypeID, String>, TypeError> {
+        Self::active()?.names_of(ids)
+    }
+
+    pub fn ids_of<'a>(names: &[&'a str]) -> Result<HashMap<&'a str, TypeID>, TypeError> {
+        Self::active()?.ids_of(names)
+    }
+
+    pub fn name_of(id: TypeID) -> Result<String, TypeError> {
+        Self::active()?.name_of(id)
+    }
+
+    pub fn names_of_locale(
+        ids: &[TypeID],
+        locale: &str,
+    ) -> Result<HashMap<TypeID, String>, TypeError> {
+        Self::active()?.names_of_locale(ids, locale)
+    }
+
+    pub fn name_of_locale(id: TypeID, locale: &str) -> Result<String, TypeError> {
+        Self::active()?.name_of_locale(id, locale)
+    }
+
+    pub fn id_of(name: &str) -> Result<TypeID, TypeError> {
+        Self::active()?.id_of(name)
+    }
+
+    pub fn id_of_fuzzy(name: &str) -> Result<TypeID, TypeError> {
+        Self::active()?.id_of_fuzzy(name)
+    }
+
+    pub fn type_variations(id: TypeID) -> Result<HashMap<TypeID, i64>, TypeError> {
+        Self::active()?.type_variations(id)
+    }
+
+    pub fn suggest(name: &str) -> Result<Option<String>, TypeError> {
+        Self::active()?.suggest(name)
+    }
+
+    pub fn group_type_ids(group_id: i32) -> Result<Vec<TypeID>, TypeError> {
+        Self::active()?.group_type_ids(group_id)
+    }
+
+    pub fn group_id_of(name: &str) -> Result<i32, TypeError> {
+        Self::active()?.group_id_of(name)
+    }
+
+    pub fn market_group_id_of(path: &str) -> Result<i32, TypeError> {
+        Self::active()?.market_group_id_of(path)
+    }
+
+    pub fn market_group_type_ids(market_group_id: i32) -> Result<Vec<TypeID>, TypeError> {
+        Self::active()?.market_group_type_ids(market_group_id)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Attribute, Category, Effect, TypeDB, TypeID};
+    use super::{levenshtein, Attribute, Category, Effect, TypeDB, TypeID, DEFAULT_PATH, SDE};
 
     fn id_of(s: &str) -> TypeID {
         TypeDB::id_of(s).unwrap()
@@ -498,6 +1048,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_preload() {
+        let sde = SDE::open_with_connections(DEFAULT_PATH, 2).unwrap();
+        let ids = [670, id_of("Nightmare"), 0, -1];
+        assert!(sde.cached_names_of(&ids).is_none());
+        let names = sde.names_of(&ids).unwrap();
+
+        sde.preload().unwrap();
+        assert_eq!(sde.cached_names_of(&ids).unwrap(), names);
+        assert_eq!(
+            sde.cached_ids_of(&["Capsule"]).unwrap().get("Capsule"),
+            Some(&670)
+        );
+        assert!(sde.cached_ids_of(&["TDF Titan"]).is_none());
+        assert_eq!(sde.name_of(670).unwrap(), "Capsule");
+    }
+
     #[test]
     fn test_requirements() {
         let t = TypeDB::load_type(id_of("Monitor")).unwrap();
@@ -529,4 +1096,72 @@ mod tests {
             Some(&16)
         );
     }
+
+    #[test]
+    fn test_group_type_ids() {
+        // Marauders
+        let marauders = TypeDB::group_type_ids(900).unwrap();
+        assert!(marauders.contains(&id_of("Paladin")));
+        assert!(marauders.contains(&id_of("Vargur")));
+        assert!(!marauders.contains(&id_of("Nightmare")));
+
+        assert_eq!(TypeDB::group_id_of("Marauder").unwrap(), 900);
+        assert_eq!(TypeDB::load_type(id_of("Paladin")).unwrap().group_id, 900);
+    }
+
+    #[test]
+    fn test_market_groups() {
+        assert!(TypeDB::market_group_id_of("Amarr").is_err());
+        let battleships = TypeDB::market_group_id_of("Battleships").unwrap();
+        let amarr = TypeDB::market_group_id_of("Standard Battleships > Amarr").unwrap();
+        assert_ne!(battleships, amarr);
+
+        let amarr_battleships = TypeDB::market_group_type_ids(amarr).unwrap();
+        assert!(amarr_battleships.contains(&id_of("Apocalypse")));
+        assert!(!amarr_battleships.contains(&id_of("Megathron")));
+        assert!(TypeDB::market_group_type_ids(battleships)
+            .unwrap()
+            .contains(&id_of("Megathron")));
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("abc", ""), 3);
+    }
+
+    #[test]
+    fn test_suggest() {
+        assert_eq!(
+            TypeDB::suggest("large shield extender ii")
+                .unwrap()
+                .as_deref(),
+            Some("Large Shield Extender II")
+        );
+        assert_eq!(
+            TypeDB::suggest("Large Sheild Extnder II")
+                .unwrap()
+                .as_deref(),
+            Some("Large Shield Extender II")
+        );
+        assert_eq!(
+            TypeDB::suggest("Nothing Even Remotely Similar To Any Item").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sde_language() {
+        assert_eq!(super::sde_language("en"), "en");
+        assert_eq!(super::sde_language("cn"), "zh");
+        assert_eq!(super::sde_language("de-DE"), "de");
+    }
+
+    #[test]
+    fn test_localized_names() {
+        assert_eq!(TypeDB::name_of_locale(670, "en").unwrap(), "Capsule");
+        assert_eq!(TypeDB::name_of_locale(670, "de").unwrap(), "Kapsel");
+        assert_eq!(TypeDB::id_of("Kapsel").unwrap(), 670);
+    }
 }
@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{Attribute, Category, Effect, FitError, Fitting, SkillLevel, Type, TypeDB, TypeID};
+
+const CPU_MANAGEMENT: TypeID = 3426;
+const POWER_GRID_MANAGEMENT: TypeID = 3413;
+const WEAPON_UPGRADES: TypeID = 3318;
+const ADVANCED_WEAPON_UPGRADES: TypeID = 11207;
+
+// Skills that lower the CPU or powergrid need of the modules that require them:
+// (skill, CPU reduction per level, powergrid reduction per level)
+const FITTING_SKILLS: &[(TypeID, f64, f64)] = &[
+    (3425, 0.0, 0.05), // Shield Upgrades
+    (3424, 0.05, 0.0), // Energy Grid Upgrades
+    (3432, 0.05, 0.0), // Electronics Upgrades
+];
+
+/// Slot counts, either what a hull provides or what a fit uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SlotLayout {
+    pub high: i64,
+    pub med: i64,
+    pub low: i64,
+    pub rig: i64,
+    pub subsystem: i64,
+    pub turret: i64,
+    pub launcher: i64,
+}
+
+/// CPU (tf), powergrid (MW) and calibration, either provided or used.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FittingResources {
+    pub cpu: f64,
+    pub powergrid: f64,
+    pub calibration: f64,
+}
+
+/// A fit laid out on its hull: what the hull has once subsystems and fitting modules are
+/// applied, and what the modules use. Hull-specific fitting bonuses and implants aren't
+/// modelled, so usage can come out slightly higher than in game for some hulls.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FitLayout {
+    pub slots: SlotLayout,
+    pub used_slots: SlotLayout,
+    /// `None` when the SDE predates fitting resources, in which case only the high, med, low
+    /// and rig slots are checked
+    pub output: Option<FittingResources>,
+    pub used: FittingResources,
+}
+
+impl Type {
+    /// The slots a hull has before subsystems are fitted.
+    pub fn hull_slots(&self) -> SlotLayout {
+        let get = |attribute| self.attributes.get(&attribute).copied().unwrap_or(0.0) as i64;
+        SlotLayout {
+            high: get(Attribute::HiSlots),
+            med: get(Attribute::MedSlots),
+            low: get(Attribute::LowSlots),
+            rig: get(Attribute::RigSlots),
+            subsystem: get(Attribute::MaxSubsystems),
+            turret: get(Attribute::TurretSlotsLeft),
+            launcher: get(Attribute::LauncherSlotsLeft),
+        }
+    }
+
+    pub fn is_subsystem(&self) -> bool {
+        self.effects.contains(&Effect::subsystem())
+    }
+
+    fn attribute(&self, attribute: Attribute) -> Option<f64> {
+        self.attributes.get(&attribute).map(|&value| value as f64)
+    }
+}
+
+impl FitLayout {
+    /// Fails with the first slot or resource the fit uses more of than the hull has.
+    pub fn check(&self) -> Result<(), FitError> {
+        self.check_slots()?;
+        self.check_resources()
+    }
+
+    /// Fails with the first slot or hardpoint the fit uses more of than the hull has. Unlike
+    /// the resources, these are exact.
+    pub fn check_slots(&self) -> Result<(), FitError> {
+        let mut slots = vec![
+            ("high", self.used_slots.high, self.slots.high),
+            ("med", self.used_slots.med, self.slots.med),
+            ("low", self.used_slots.low, self.slots.low),
+            ("rig", self.used_slots.rig, self.slots.rig),
+        ];
+        if self.output.is_some() {
+            slots.push(("subsystem", self.used_slots.subsystem, self.slots.subsystem));
+            slots.push(("turret", self.used_slots.turret, self.slots.turret));
+            slots.push(("launcher", self.used_slots.launcher, self.slots.launcher));
+        }
+        for &(slot, used, available) in &slots {
+            if used > available {
+                return Err(FitError::TooManyModules {
+                    slot,
+                    used,
+                    available,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fails with the first of CPU, powergrid or calibration the fit uses more of than the
+    /// hull has. Only as good as the model, see `FitLayout`.
+    pub fn check_resources(&self) -> Result<(), FitError> {
+        if let Some(output) = &self.output {
+            let resources = [
+                ("CPU", self.used.cpu, output.cpu),
+                ("powergrid", self.used.powergrid, output.powergrid),
+                ("calibration", self.used.calibration, output.calibration),
+            ];
+            for &(resource, used, available) in &resources {
+                // The client shows two decimals, don't fail on anything smaller than that
+                if (used * 100.0).round() > (available * 100.0).round() {
+                    return Err(FitError::Overfitted {
+                        resource,
+                        used,
+                        available,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Fitting {
+    /// Lays the fit out on its hull, with skill levels from `skill_level`.
+    pub fn layout(
+        &self,
+        skill_level: impl Fn(TypeID) -> SkillLevel,
+    ) -> Result<FitLayout, FitError> {
+        let mut ids = vec![self.hull];
+        ids.extend(self.modules.keys());
+        let types = TypeDB::load_types(&ids)?;
+        self.layout_with(&types, skill_level)
+    }
+
+    pub(crate) fn layout_with(
+        &self,
+        types: &HashMap<TypeID, Option<Arc<Type>>>,
+        skill_level: impl Fn(TypeID) -> SkillLevel,
+    ) -> Result<FitLayout, FitError> {
+        let get_type = |id: &TypeID| match types.get(id) {
+            Some(Some(the_type)) => Ok(the_type),
+            _ => Err(FitError::InvalidModule),
+        };
+        let hull = get_type(&self.hull)?;
+        let level = |skill| skill_level(skill).clamp(0, 5) as f64;
+
+        let mut slots = hull.hull_slots();
+        let mut used_slots = SlotLayout::default();
+        let mut output = match (
+            hull.attribute(Attribute::CpuOutput),
+            hull.attribute(Attribute::PowerOutput),
+        ) {
+            (Some(cpu), Some(powergrid)) => Some(FittingResources {
+                cpu,
+                powergrid,
+                calibration: hull.attribute(Attribute::UpgradeCapacity).unwrap_or(0.0),
+            }),
+            _ => None,
+        };
+        let mut used = FittingResources::default();
+        let mut cpu_multiplier = 1.0 + 0.05 * level(CPU_MANAGEMENT);
+        let mut powergrid_multiplier = 1.0 + 0.05 * level(POWER_GRID_MANAGEMENT);
+
+        for (id, &count) in &self.modules {
+            let module = get_type(id)?;
+            if module.category == Category::Drone {
+                continue;
+            }
+            let attribute = |attribute| module.attribute(attribute).unwrap_or(0.0);
+
+            if module.is_subsystem() {
+                used_slots.subsystem += count;
+                slots.high += attribute(Attribute::HiSlotModifier) as i64 * count;
+                slots.med += attribute(Attribute::MedSlotModifier) as i64 * count;
+                slots.low += attribute(Attribute::LowSlotModifier) as i64 * count;
+                slots.turret += attribute(Attribute::TurretHardpointModifier) as i64 * count;
+                slots.launcher += attribute(Attribute::LauncherHardpointModifier) as i64 * count;
+                if let Some(output) = output.as_mut() {
+                    output.cpu += attribute(Attribute::CpuOutput) * count as f64;
+                    output.powergrid += attribute(Attribute::PowerOutput) * count as f64;
+                }
+                continue;
+            }
+
+            match module.slot() {
+                Some("high") => used_slots.high += count,
+                Some("med") => used_slots.med += count,
+                Some("low") => used_slots.low += count,
+                Some("rig") => used_slots.rig += count,
+                _ => (),
+            }
+            let is_weapon = if module.effects.contains(&Effect::turret_fitted()) {
+                used_slots.turret += count;
+                true
+            } else if module.effects.contains(&Effect::launcher_fitted()) {
+                used_slots.launcher += count;
+                true
+            } else {
+                false
+            };
+
+            // Fitting modules: co-processors, reactor control units, auxiliary power cores and rigs
+            for _ in 0..count {
+                if let Some(multiplier) = module.attribute(Attribute::CpuMultiplier) {
+                    cpu_multiplier *= multiplier;
+                }
+                if let Some(bonus) = module.attribute(Attribute::CpuOutputBonus) {
+                    cpu_multiplier *= 1.0 + bonus / 100.0;
+                }
+                if let Some(multiplier) = module.attribute(Attribute::PowerOutputMultiplier) {
+                    powergrid_multiplier *= multiplier;
+                }
+                if let Some(bonus) = module.attribute(Attribute::PowerOutputBonus) {
+                    powergrid_multiplier *= 1.0 + bonus / 100.0;
+                }
+            }
+            if let Some(output) = output.as_mut() {
+                output.powergrid += attribute(Attribute::PowerIncrease) * count as f64;
+            }
+
+            let mut cpu = attribute(Attribute::Cpu);
+            let mut powergrid = attribute(Attribute::Power);
+            if is_weapon {
+                cpu *= 1.0 - 0.05 * level(WEAPON_UPGRADES);
+                powergrid *= 1.0 - 0.02 * level(ADVANCED_WEAPON_UPGRADES);
+            }
+            for &(skill, cpu_reduction, powergrid_reduction) in FITTING_SKILLS {
+                if module.skill_requirements.contains_key(&skill) {
+                    cpu *= 1.0 - cpu_reduction * level(skill);
+                    powergrid *= 1.0 - powergrid_reduction * level(skill);
+                }
+            }
+            used.cpu += cpu * count as f64;
+            used.powergrid += powergrid * count as f64;
+            used.calibration += attribute(Attribute::UpgradeCost) * count as f64;
+        }
+
+        if let Some(output) = output.as_mut() {
+            output.cpu *= cpu_multiplier;
+            output.powergrid *= powergrid_multiplier;
+        }
+
+        Ok(FitLayout {
+            slots,
+            used_slots,
+            output,
+            used,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FitLayout, FittingResources, SlotLayout};
+    use crate::{FitError, Fitting, TypeDB};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_check() {
+        let slots = SlotLayout {
+            high: 8,
+            med: 4,
+            low: 7,
+            rig: 3,
+            turret: 4,
+            ..Default::default()
+        };
+        let resources = FittingResources {
+            cpu: 500.0,
+            powergrid: 20000.0,
+            calibration: 400.0,
+        };
+        let mut layout = FitLayout {
+            slots,
+            used_slots: SlotLayout {
+                high: 6,
+                turret: 4,
+                ..slots
+            },
+            output: Some(resources),
+            used: FittingResources {
+                cpu: 500.004,
+                ..resources
+            },
+        };
+        assert!(layout.check().is_ok());
+
+        layout.used_slots.turret = 5;
+        assert!(matches!(
+            layout.check(),
+            Err(FitError::TooManyModules { slot: "turret", .. })
+        ));
+        layout.used_slots.turret = 4;
+
+        layout.used.cpu = 520.0;
+        assert!(layout.check_slots().is_ok());
+        assert!(matches!(
+            layout.check(),
+            Err(FitError::Overfitted {
+                resource: "CPU",
+                ..
+            })
+        ));
+
+        // Old SDE without fitting resources or hardpoints
+        layout.used_slots.turret = 5;
+        layout.output = None;
+        assert!(layout.check().is_ok());
+    }
+
+    #[test]
+    fn test_layout() {
+        let id = |name| TypeDB::id_of(name).unwrap();
+        let fit = Fitting {
+            hull: id("Nightmare"),
+            modules: BTreeMap::from([(id("Mega Pulse Laser II"), 4), (id("Co-Processor II"), 1)]),
+            cargo: BTreeMap::new(),
+            charges: BTreeMap::new(),
+        };
+        let layout = fit.layout(|_| 5).unwrap();
+        assert_eq!(layout.used_slots.high, 4);
+        assert_eq!(layout.used_slots.turret, 4);
+        assert_eq!(layout.used_slots.low, 1);
+        assert!(layout.check().is_ok());
+
+        let untrained = fit.layout(|_| 0).unwrap();
+        assert!(untrained.used.cpu > layout.used.cpu);
+        assert!(untrained.output.unwrap().cpu < layout.output.unwrap().cpu);
+
+        let mut too_many = fit;
+        too_many.modules.insert(id("Mega Pulse Laser II"), 9);
+        assert!(matches!(
+            too_many.validate(),
+            Err(FitError::TooManyModules { .. })
+        ));
+    }
+}
@@ -1,11 +1,19 @@
+#[cfg(feature = "async")]
+mod async_db;
 mod attribute;
 mod category;
 mod effect;
 mod fitting;
 mod inv_types;
+mod layout;
+mod skill_tree;
 
+#[cfg(feature = "async")]
+pub use async_db::AsyncTypeDB;
 pub use attribute::Attribute;
 pub use category::Category;
 pub use effect::Effect;
-pub use fitting::{FitError, Fitting};
-pub use inv_types::{SkillLevel, Type, TypeDB, TypeError, TypeID};
+pub use fitting::{EftDiagnostic, EftLineError, EftParse, FitError, Fitting};
+pub use inv_types::{SkillLevel, Type, TypeDB, TypeError, TypeID, DEFAULT_CONNECTIONS, SDE};
+pub use layout::{FitLayout, FittingResources, SlotLayout};
+pub use skill_tree::{skill_points, MissingSkill, SkillTree, DEFAULT_SP_PER_MINUTE};
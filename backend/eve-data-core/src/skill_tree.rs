@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::{Attribute, SkillLevel, TypeDB, TypeError, TypeID, SDE};
+
+/// SP per minute for a character with unmapped attributes (20/20) and no implants.
+pub const DEFAULT_SP_PER_MINUTE: f64 = 30.0;
+
+/// Every skill needed to use a set of types, including the prerequisites of those skills.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SkillTree {
+    /// Skill -> highest level needed by anything in the tree
+    pub levels: BTreeMap<TypeID, SkillLevel>,
+    /// Skill -> training time multiplier (rank)
+    pub ranks: BTreeMap<TypeID, f32>,
+    /// Type that was asked about -> the skills it needs directly
+    pub direct: BTreeMap<TypeID, BTreeMap<TypeID, SkillLevel>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissingSkill {
+    pub skill: TypeID,
+    pub have: SkillLevel,
+    pub need: SkillLevel,
+}
+
+// Skill points for each level of a rank 1 skill, 250 * sqrt(32)^(level-1) rounded up
+const RANK_ONE_SKILL_POINTS: [i64; 6] = [0, 250, 1415, 8000, 45255, 256000];
+
+/// Total skill points needed to reach `level` in a skill of the given rank.
+pub fn skill_points(rank: f32, level: SkillLevel) -> i64 {
+    let level = level.clamp(0, 5) as usize;
+    (RANK_ONE_SKILL_POINTS[level] as f64 * rank as f64).round() as i64
+}
+
+impl SkillTree {
+    /// Skills in the tree that are below the needed level, as reported by `have`.
+    pub fn missing(&self, have: impl Fn(TypeID) -> SkillLevel) -> Vec<MissingSkill> {
+        self.levels
+            .iter()
+            .filter_map(|(&skill, &need)| {
+                let have = have(skill);
+                if have < need {
+                    Some(MissingSkill { skill, have, need })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Like `missing`, but only for what `type_id` needs, prerequisites included.
+    pub fn missing_for(
+        &self,
+        type_id: TypeID,
+        have: impl Fn(TypeID) -> SkillLevel,
+    ) -> Vec<MissingSkill> {
+        let mut needed = BTreeMap::new();
+        let mut to_process: Vec<(TypeID, SkillLevel)> = self
+            .direct
+            .get(&type_id)
+            .map(|direct| direct.iter().map(|(&s, &l)| (s, l)).collect())
+            .unwrap_or_default();
+        while let Some((skill, level)) = to_process.pop() {
+            let entry = needed.entry(skill).or_insert(0);
+            if *entry >= level {
+                continue;
+            }
+            *entry = level;
+            if let Some(prerequisites) = self.direct.get(&skill) {
+                to_process.extend(prerequisites.iter().map(|(&s, &l)| (s, l)));
+            }
+        }
+
+        needed
+            .into_iter()
+            .filter_map(|(skill, need)| {
+                let have = have(skill);
+                if have < need {
+                    Some(MissingSkill { skill, have, need })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Skill points still to train, given the levels from `have`.
+    pub fn missing_skill_points(&self, have: impl Fn(TypeID) -> SkillLevel) -> i64 {
+        self.missing(have)
+            .iter()
+            .map(|missing| {
+                let rank = self.ranks.get(&missing.skill).copied().unwrap_or(1.0);
+                skill_points(rank, missing.need) - skill_points(rank, missing.have)
+            })
+            .sum()
+    }
+
+    /// Estimated time to train everything that's missing at the given rate.
+    pub fn training_time(
+        &self,
+        have: impl Fn(TypeID) -> SkillLevel,
+        sp_per_minute: f64,
+    ) -> Duration {
+        let sp = self.missing_skill_points(have) as f64;
+        Duration::from_secs_f64(sp / sp_per_minute * 60.0)
+    }
+}
+
+impl SDE {
+    /// Resolves the full skill requirement tree of the given types. Skills are loaded
+    /// breadth-first so that every level of the tree is a single database round trip.
+    pub fn skill_tree(&self, ids: &[TypeID]) -> Result<SkillTree, TypeError> {
+        let mut tree = SkillTree::default();
+        let mut to_load: Vec<TypeID> = ids.to_vec();
+
+        while !to_load.is_empty() {
+            let loaded = self.load_types(&to_load)?;
+            to_load = Vec::new();
+
+            for (id, the_type) in loaded {
+                let the_type = match the_type {
+                    Some(the_type) => the_type,
+                    None => return Err(TypeError::NothingMatched),
+                };
+                if let Some(&rank) = the_type.attributes.get(&Attribute::TrainingTimeMultiplier) {
+                    tree.ranks.insert(id, rank);
+                }
+
+                let direct: BTreeMap<TypeID, SkillLevel> = the_type
+                    .skill_requirements
+                    .iter()
+                    .map(|(&skill, &level)| (skill, level))
+                    .collect();
+                for (&skill, &level) in &direct {
+                    let entry = tree.levels.entry(skill).or_insert(0);
+                    *entry = (*entry).max(level);
+                    if !tree.direct.contains_key(&skill) && !to_load.contains(&skill) {
+                        to_load.push(skill);
+                    }
+                }
+                tree.direct.insert(id, direct);
+            }
+
+            to_load.retain(|skill| !tree.direct.contains_key(skill));
+        }
+
+        Ok(tree)
+    }
+}
+
+impl TypeDB {
+    pub fn skill_tree(ids: &[TypeID]) -> Result<SkillTree, TypeError> {
+        Self::active()?.skill_tree(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{skill_points, SkillTree, DEFAULT_SP_PER_MINUTE};
+    use crate::TypeDB;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_skill_points() {
+        assert_eq!(skill_points(1.0, 0), 0);
+        assert_eq!(skill_points(1.0, 1), 250);
+        assert_eq!(skill_points(1.0, 2), 1415);
+        assert_eq!(skill_points(1.0, 3), 8000);
+        assert_eq!(skill_points(1.0, 4), 45255);
+        assert_eq!(skill_points(1.0, 5), 256000);
+        assert_eq!(skill_points(8.0, 5), 2048000);
+    }
+
+    #[test]
+    fn test_missing_training_time() {
+        let mut tree = SkillTree::default();
+        tree.levels.insert(1, 5);
+        tree.levels.insert(2, 3);
+        tree.ranks.insert(1, 1.0);
+        tree.ranks.insert(2, 2.0);
+        tree.direct.insert(100, [(1, 5)].iter().copied().collect());
+        tree.direct.insert(1, [(2, 3)].iter().copied().collect());
+
+        let have: HashMap<i32, i8> = [(1, 4), (2, 3)].iter().copied().collect();
+        let have = |skill| have.get(&skill).copied().unwrap_or(0);
+        let missing = tree.missing(have);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(
+            (missing[0].skill, missing[0].have, missing[0].need),
+            (1, 4, 5)
+        );
+        assert_eq!(tree.missing_for(100, have), missing);
+        assert_eq!(tree.missing_skill_points(have), 256000 - 45255);
+        assert_eq!(
+            tree.training_time(have, DEFAULT_SP_PER_MINUTE).as_secs(),
+            (256000 - 45255) * 2
+        );
+
+        assert_eq!(tree.missing_for(100, |_| 0).len(), 2);
+    }
+
+    #[test]
+    fn test_skill_tree() {
+        let nightmare = TypeDB::id_of("Nightmare").unwrap();
+        let tree = TypeDB::skill_tree(&[nightmare]).unwrap();
+
+        // Direct requirement of the hull, and one of its prerequisites
+        let amarr_battleship = TypeDB::id_of("Amarr Battleship").unwrap();
+        let amarr_cruiser = TypeDB::id_of("Amarr Cruiser").unwrap();
+        assert!(tree
+            .direct
+            .get(&nightmare)
+            .unwrap()
+            .contains_key(&amarr_battleship));
+        assert!(tree.levels.contains_key(&amarr_cruiser));
+        assert!(tree.ranks.get(&amarr_battleship).copied().unwrap_or(0.0) > 0.0);
+        assert!(tree.training_time(|_| 0, DEFAULT_SP_PER_MINUTE).as_secs() > 0);
+        assert!(tree.missing(|_| 5).is_empty());
+    }
+}
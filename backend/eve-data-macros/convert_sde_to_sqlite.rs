@@ -1,9 +1,9 @@
 // Standalone script to convert official CCP SDE JSON files to SQLite database
 // This replaces the fuzzwork SQLite download with direct SDE conversion
 
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, OptionalExtension, params};
 use serde_json::Value;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
@@ -14,31 +14,84 @@ const REQUIRED_ATTRIBUTE_IDS: &[i32] = &[
     984, 985, 986, 987,  // resists
     182, 183, 184, 1285, 1289, 1290,  // skill req
     277, 278, 279, 1286, 1287, 1288,  // skill req level
+    12, 13, 14, 1137,  // low, med, high, rig slots
+    1374, 1375, 1376,  // subsystem high, med, low slot modifiers
+    101, 102, 1367, 1368, 1369,  // launcher, turret hardpoints, subsystem slots and modifiers
+    48, 11, 1132,  // cpu, powergrid, calibration output
+    50, 30, 1153,  // cpu, powergrid, calibration need
+    202, 424, 145, 313, 549,  // fitting module bonuses
 ];
 
-const REQUIRED_EFFECT_IDS: &[i32] = &[11, 12, 13, 2663];
+const REQUIRED_EFFECT_IDS: &[i32] = &[11, 12, 13, 2663, 3772, 42, 40];
+
+// Tables and columns eve-data-core reads, checked before the new database replaces the old one
+const EXPECTED_SCHEMA: &[(&str, &[&str])] = &[
+    ("invTypes", &["typeID", "typeName", "groupID", "marketGroupID", "published"]),
+    ("invTypeNames", &["typeID", "language", "typeName"]),
+    ("invGroups", &["groupID", "categoryID", "groupName"]),
+    ("invMarketGroups", &["marketGroupID", "parentGroupID", "marketGroupName"]),
+    ("invMetaTypes", &["typeID", "parentTypeID", "metaGroupID"]),
+    ("dgmTypeAttributes", &["typeID", "attributeID", "valueInt", "valueFloat"]),
+    ("dgmTypeEffects", &["typeID", "effectID"]),
+    ("sdeInfo", &["buildNumber", "releaseDate"]),
+];
+
+// Data files that refer to types by name, relative to the data directory
+const NAME_REFERENCE_FILES: &[&str] = &["modules.yaml", "categories.yaml", "skills.yaml"];
+
+// Diff sections get long after big expansions, only print this many lines of each
+const MAX_REPORT_LINES: usize = 50;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = std::env::args().collect();
-    let sde_dir = if args.len() > 1 {
-        &args[1]
-    } else {
-        eprintln!("Usage: convert_sde_to_sqlite <sde_extracted_directory>");
-        eprintln!("Example: convert_sde_to_sqlite /tmp/sde-2024.11");
-        std::process::exit(1);
+    let mut args = std::env::args().skip(1);
+    let mut sde_dir = None;
+    let mut data_dir = "./data".to_string();
+    let mut force = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--force" => force = true,
+            "--data" => data_dir = args.next().ok_or("--data needs a directory")?,
+            _ => sde_dir = Some(arg),
+        }
+    }
+    let sde_dir = match sde_dir {
+        Some(sde_dir) => sde_dir,
+        None => {
+            eprintln!("Usage: convert_sde_to_sqlite <sde_extracted_directory> [--data <data_dir>] [--force]");
+            eprintln!("Example: convert_sde_to_sqlite /tmp/sde-2024.11 --data ./data");
+            std::process::exit(1);
+        }
     };
 
     let output_file = "sqlite-shrunk.sqlite";
-    
-    println!("Converting SDE from {} to {}", sde_dir, output_file);
-    
-    // Remove existing database
-    if Path::new(output_file).exists() {
-        fs::remove_file(output_file)?;
+    let new_file = "sqlite-shrunk.sqlite.new";
+    let previous_file = "sqlite-shrunk.sqlite.previous";
+    let has_old = Path::new(output_file).exists();
+
+    // Nothing to do if we already converted this build, unless we now keep more of it
+    if has_old && !force {
+        let new_build = read_build_number(Path::new(&sde_dir))?;
+        let old = Connection::open(output_file)?;
+        let old_build = old
+            .query_row("SELECT buildNumber FROM sdeInfo", [], |row| row.get::<_, i64>(0))
+            .optional()
+            .unwrap_or(None);
+        let up_to_date = validate_schema(&old).is_ok();
+        if let Some(build) = new_build.filter(|_| new_build == old_build && up_to_date) {
+            println!("{} is already at SDE build {}, use --force to convert anyway", output_file, build);
+            return Ok(());
+        }
+    }
+
+    println!("Converting SDE from {} to {}", sde_dir, new_file);
+
+    // Build next to the existing database, which stays in place until the new one checks out
+    if Path::new(new_file).exists() {
+        fs::remove_file(new_file)?;
     }
 
     // Create SQLite database
-    let conn = Connection::open(output_file)?;
+    let conn = Connection::open(new_file)?;
     
     // Create tables
     conn.execute(
@@ -46,15 +99,36 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             typeID INTEGER PRIMARY KEY,
             typeName TEXT NOT NULL,
             groupID INTEGER NOT NULL,
+            marketGroupID INTEGER,
             published INTEGER NOT NULL
         )",
         [],
     )?;
 
+    conn.execute(
+        "CREATE TABLE invTypeNames (
+            typeID INTEGER NOT NULL,
+            language TEXT NOT NULL,
+            typeName TEXT NOT NULL,
+            PRIMARY KEY (typeID, language)
+        )",
+        [],
+    )?;
+
     conn.execute(
         "CREATE TABLE invGroups (
             groupID INTEGER PRIMARY KEY,
-            categoryID INTEGER NOT NULL
+            categoryID INTEGER NOT NULL,
+            groupName TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE invMarketGroups (
+            marketGroupID INTEGER PRIMARY KEY,
+            parentGroupID INTEGER,
+            marketGroupName TEXT NOT NULL
         )",
         [],
     )?;
@@ -88,16 +162,30 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         [],
     )?;
 
+    conn.execute(
+        "CREATE TABLE sdeInfo (
+            buildNumber INTEGER NOT NULL,
+            releaseDate TEXT
+        )",
+        [],
+    )?;
+
     // Process SDE files
     // Files are directly in sde_dir, not in an fsd/ subdirectory
     let sde_path = PathBuf::from(sde_dir);
     
+    println!("Processing sdeInfo...");
+    process_sde_info(&conn, &sde_path)?;
+
     println!("Processing invTypes...");
     process_types(&conn, &sde_path)?;
     
     println!("Processing invGroups...");
     process_groups(&conn, &sde_path)?;
     
+    println!("Processing invMarketGroups...");
+    process_market_groups(&conn, &sde_path)?;
+
     println!("Processing invMetaTypes...");
     process_meta_types(&conn, &sde_path)?;
     
@@ -108,16 +196,344 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Creating indexes...");
     conn.execute("CREATE INDEX invTypes_name ON invTypes (typeName)", [])?;
     conn.execute("CREATE INDEX invTypes_typeID ON invTypes (typeID)", [])?;
+    conn.execute("CREATE INDEX invTypeNames_name ON invTypeNames (typeName)", [])?;
     conn.execute("CREATE INDEX invGroups_groupID ON invGroups (groupID)", [])?;
+    conn.execute("CREATE INDEX invGroups_name ON invGroups (groupName)", [])?;
+    conn.execute("CREATE INDEX invTypes_marketGroupID ON invTypes (marketGroupID)", [])?;
+    conn.execute("CREATE INDEX invMarketGroups_parentGroupID ON invMarketGroups (parentGroupID)", [])?;
     conn.execute("CREATE INDEX invMetaTypes_typeID ON invMetaTypes (typeID)", [])?;
     conn.execute("CREATE INDEX invMetaTypes_parentTypeID ON invMetaTypes (parentTypeID)", [])?;
     conn.execute("CREATE INDEX dgmTypeAttributes_typeID ON dgmTypeAttributes (typeID)", [])?;
     conn.execute("CREATE INDEX dgmTypeEffects_typeID ON dgmTypeEffects (typeID)", [])?;
 
+    println!("Validating schema...");
+    validate_schema(&conn)?;
+
+    if has_old {
+        conn.execute("ATTACH DATABASE ?1 AS old", [output_file])?;
+        println!();
+        print_diff_report(&conn)?;
+        println!();
+        check_references(&conn, Path::new(&data_dir))?;
+        conn.execute("DETACH DATABASE old", [])?;
+    }
+    drop(conn);
+
+    if has_old {
+        fs::rename(output_file, previous_file)?;
+        println!("Previous database kept as: {}", previous_file);
+    }
+    fs::rename(new_file, output_file)?;
+
     println!("✓ Conversion complete! Database saved to: {}", output_file);
     Ok(())
 }
 
+fn read_build_number(sde_dir: &Path) -> Result<Option<i64>, Box<dyn std::error::Error>> {
+    let file_path = sde_dir.join("_sde.jsonl");
+    if !file_path.exists() {
+        return Ok(None);
+    }
+
+    let reader = BufReader::new(fs::File::open(&file_path)?);
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let json: Value = serde_json::from_str(&line)?;
+        return Ok(json["buildNumber"].as_i64());
+    }
+    Ok(None)
+}
+
+fn validate_schema(conn: &Connection) -> Result<(), Box<dyn std::error::Error>> {
+    for (table, columns) in EXPECTED_SCHEMA {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let found: HashSet<String> = stmt
+            .query_map([], |row| row.get(1))?
+            .collect::<Result<_, _>>()?;
+        for column in columns.iter() {
+            if !found.contains(*column) {
+                return Err(format!("Table {} is missing column {}", table, column).into());
+            }
+        }
+    }
+
+    // An empty table means the SDE layout changed under us and we read nothing
+    for table in ["invTypes", "invGroups", "dgmTypeAttributes", "dgmTypeEffects"].iter() {
+        let count: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))?;
+        if count == 0 {
+            return Err(format!("Table {} is empty", table).into());
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT DISTINCT attributeID FROM dgmTypeAttributes")?;
+    let attributes: HashSet<i32> = stmt.query_map([], |row| row.get(0))?.collect::<Result<_, _>>()?;
+    let missing: Vec<String> = REQUIRED_ATTRIBUTE_IDS
+        .iter()
+        .filter(|id| !attributes.contains(id))
+        .map(|id| id.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("No values for required attributes: {}", missing.join(", ")).into());
+    }
+
+    let mut stmt = conn.prepare("SELECT DISTINCT effectID FROM dgmTypeEffects")?;
+    let effects: HashSet<i32> = stmt.query_map([], |row| row.get(0))?.collect::<Result<_, _>>()?;
+    let missing: Vec<String> = REQUIRED_EFFECT_IDS
+        .iter()
+        .filter(|id| !effects.contains(id))
+        .map(|id| id.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("No types with required effects: {}", missing.join(", ")).into());
+    }
+
+    Ok(())
+}
+
+fn print_section(title: &str, lines: Vec<String>) {
+    println!("{} ({})", title, lines.len());
+    for line in lines.iter().take(MAX_REPORT_LINES) {
+        println!("  {}", line);
+    }
+    if lines.len() > MAX_REPORT_LINES {
+        println!("  ... and {} more", lines.len() - MAX_REPORT_LINES);
+    }
+}
+
+fn query_lines(conn: &Connection, query: &str, format: impl Fn(&rusqlite::Row) -> rusqlite::Result<String>) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare(query)?;
+    let lines = stmt.query_map([], |row| format(row))?.collect::<Result<_, _>>()?;
+    Ok(lines)
+}
+
+// Expects the previous database to be attached as "old"
+fn print_diff_report(conn: &Connection) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Changes since the previous database:");
+
+    let added: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM main.invTypes n LEFT JOIN old.invTypes o ON o.typeID = n.typeID WHERE o.typeID IS NULL",
+        [],
+        |row| row.get(0),
+    )?;
+    println!("Added types ({})", added);
+
+    print_section("Removed types", query_lines(conn,
+        "SELECT o.typeID, o.typeName FROM old.invTypes o
+         LEFT JOIN main.invTypes n ON n.typeID = o.typeID
+         WHERE n.typeID IS NULL ORDER BY o.typeID",
+        |row| Ok(format!("{} {}", row.get::<_, i32>(0)?, row.get::<_, String>(1)?)),
+    )?);
+
+    print_section("Renamed types", query_lines(conn,
+        "SELECT o.typeID, o.typeName, n.typeName FROM old.invTypes o
+         JOIN main.invTypes n ON n.typeID = o.typeID
+         WHERE o.typeName != n.typeName ORDER BY o.typeID",
+        |row| Ok(format!("{} {} -> {}", row.get::<_, i32>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)),
+    )?);
+
+    print_section("Unpublished types", query_lines(conn,
+        "SELECT n.typeID, n.typeName FROM old.invTypes o
+         JOIN main.invTypes n ON n.typeID = o.typeID
+         WHERE o.published = 1 AND n.published = 0 ORDER BY n.typeID",
+        |row| Ok(format!("{} {}", row.get::<_, i32>(0)?, row.get::<_, String>(1)?)),
+    )?);
+
+    // SQLite has no FULL OUTER JOIN, so changed/added/removed values are three queries
+    let mut attribute_changes = query_lines(conn,
+        "SELECT n.typeID, t.typeName, n.attributeID, o.valueFloat, n.valueFloat FROM old.dgmTypeAttributes o
+         JOIN main.dgmTypeAttributes n ON n.typeID = o.typeID AND n.attributeID = o.attributeID
+         JOIN main.invTypes t ON t.typeID = n.typeID
+         WHERE o.valueFloat IS NOT n.valueFloat ORDER BY n.typeID, n.attributeID",
+        |row| Ok(format!(
+            "{} {}: attribute {} {} -> {}",
+            row.get::<_, i32>(0)?, row.get::<_, String>(1)?, row.get::<_, i32>(2)?,
+            row.get::<_, Option<f64>>(3)?.unwrap_or_default(), row.get::<_, Option<f64>>(4)?.unwrap_or_default(),
+        )),
+    )?;
+    attribute_changes.extend(query_lines(conn,
+        "SELECT n.typeID, t.typeName, n.attributeID, n.valueFloat FROM main.dgmTypeAttributes n
+         JOIN old.invTypes ot ON ot.typeID = n.typeID
+         JOIN main.invTypes t ON t.typeID = n.typeID
+         LEFT JOIN old.dgmTypeAttributes o ON o.typeID = n.typeID AND o.attributeID = n.attributeID
+         WHERE o.typeID IS NULL ORDER BY n.typeID, n.attributeID",
+        |row| Ok(format!(
+            "{} {}: attribute {} added ({})",
+            row.get::<_, i32>(0)?, row.get::<_, String>(1)?, row.get::<_, i32>(2)?,
+            row.get::<_, Option<f64>>(3)?.unwrap_or_default(),
+        )),
+    )?);
+    attribute_changes.extend(query_lines(conn,
+        "SELECT o.typeID, t.typeName, o.attributeID, o.valueFloat FROM old.dgmTypeAttributes o
+         JOIN main.invTypes t ON t.typeID = o.typeID
+         LEFT JOIN main.dgmTypeAttributes n ON n.typeID = o.typeID AND n.attributeID = o.attributeID
+         WHERE n.typeID IS NULL ORDER BY o.typeID, o.attributeID",
+        |row| Ok(format!(
+            "{} {}: attribute {} removed (was {})",
+            row.get::<_, i32>(0)?, row.get::<_, String>(1)?, row.get::<_, i32>(2)?,
+            row.get::<_, Option<f64>>(3)?.unwrap_or_default(),
+        )),
+    )?);
+    print_section("Changed attributes", attribute_changes);
+
+    print_section("Changed meta groups", query_lines(conn,
+        "SELECT n.typeID, t.typeName, o.metaGroupID, n.metaGroupID, o.parentTypeID, n.parentTypeID FROM old.invMetaTypes o
+         JOIN main.invMetaTypes n ON n.typeID = o.typeID
+         JOIN main.invTypes t ON t.typeID = n.typeID
+         WHERE o.metaGroupID IS NOT n.metaGroupID OR o.parentTypeID IS NOT n.parentTypeID
+         ORDER BY n.typeID",
+        |row| Ok(format!(
+            "{} {}: meta group {:?} -> {:?}, parent {:?} -> {:?}",
+            row.get::<_, i32>(0)?, row.get::<_, String>(1)?,
+            row.get::<_, Option<i32>>(2)?, row.get::<_, Option<i32>>(3)?,
+            row.get::<_, Option<i32>>(4)?, row.get::<_, Option<i32>>(5)?,
+        )),
+    )?);
+
+    Ok(())
+}
+
+fn collect_yaml_strings(value: &serde_yaml::Value, into: &mut BTreeSet<String>) {
+    match value {
+        serde_yaml::Value::String(s) => {
+            into.insert(s.clone());
+        }
+        serde_yaml::Value::Sequence(items) => {
+            for item in items {
+                collect_yaml_strings(item, into);
+            }
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                collect_yaml_strings(key, into);
+                collect_yaml_strings(value, into);
+            }
+        }
+        _ => (),
+    }
+}
+
+// Expects the previous database to be attached as "old". A string in the data files counts as
+// a type reference if it was a type name in the previous database.
+fn check_references(conn: &Connection, data_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Checking references in {}:", data_dir.display());
+    let mut problems = 0;
+
+    let mut old_name = conn.prepare("SELECT typeID FROM old.invTypes WHERE typeName = ?1")?;
+    let mut new_name = conn.prepare("SELECT typeName FROM main.invTypes WHERE typeID = ?1")?;
+    let mut name_exists = conn.prepare("SELECT 1 FROM main.invTypes WHERE typeName = ?1 AND published = 1")?;
+
+    for file_name in NAME_REFERENCE_FILES {
+        let path = data_dir.join(file_name);
+        if !path.exists() {
+            println!("  {} not found, skipping", path.display());
+            continue;
+        }
+
+        let value: serde_yaml::Value = serde_yaml::from_str(&fs::read_to_string(&path)?)?;
+        let mut strings = BTreeSet::new();
+        collect_yaml_strings(&value, &mut strings);
+
+        for name in strings {
+            let old_id: Option<i32> = old_name.query_row([&name], |row| row.get(0)).optional()?;
+            let old_id = match old_id {
+                Some(old_id) => old_id,
+                None => continue,
+            };
+            if name_exists.exists([&name])? {
+                continue;
+            }
+
+            problems += 1;
+            let renamed: Option<String> = new_name.query_row([old_id], |row| row.get(0)).optional()?;
+            match renamed {
+                Some(renamed) if renamed != name => println!("  {}: '{}' was renamed to '{}'", file_name, name, renamed),
+                Some(_) => println!("  {}: '{}' is no longer published", file_name, name),
+                None => println!("  {}: '{}' no longer exists", file_name, name),
+            }
+        }
+    }
+
+    // Doctrine fits are DNA plus an optional charges attribute, so these are type IDs rather than names
+    let fits_path = data_dir.join("fits.dat");
+    if fits_path.exists() {
+        let fits = fs::read_to_string(&fits_path)?;
+        let mut type_exists = conn.prepare("SELECT 1 FROM main.invTypes WHERE typeID = ?1 AND published = 1")?;
+        let mut checked = HashSet::new();
+        for link in fits.split("fitting:").skip(1) {
+            let mut ids = Vec::new();
+
+            // "type;count", or "type_;count" for cargo
+            let dna = link.split('"').next().unwrap_or("");
+            for piece in dna.split(':') {
+                ids.extend(piece.split(';').next());
+            }
+
+            // charges="module;charge;count:..."
+            if let Some(charges) = link.split('>').next().and_then(|tag| tag.split("charges=\"").nth(1)) {
+                let charges = charges.split('"').next().unwrap_or("");
+                for piece in charges.split(':') {
+                    ids.extend(piece.split(';').nth(1));
+                }
+            }
+
+            for id in ids {
+                let type_id: i32 = match id.trim_end_matches('_').parse() {
+                    Ok(type_id) => type_id,
+                    Err(_) => continue,
+                };
+                if !checked.insert(type_id) || type_exists.exists([type_id])? {
+                    continue;
+                }
+                problems += 1;
+                println!("  fits.dat: type {} no longer exists or is unpublished", type_id);
+            }
+        }
+    } else {
+        println!("  {} not found, skipping", fits_path.display());
+    }
+
+    if problems == 0 {
+        println!("  All references still resolve");
+    } else {
+        println!("  {} references need updating", problems);
+    }
+    Ok(())
+}
+
+fn process_sde_info(conn: &Connection, sde_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let file_path = sde_dir.join("_sde.jsonl");
+    if !file_path.exists() {
+        println!("  _sde.jsonl not found, build number will be unknown");
+        return Ok(());
+    }
+
+    let file = fs::File::open(&file_path)?;
+    let reader = BufReader::new(file);
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let json: Value = serde_json::from_str(&line)?;
+        let build_number = json["buildNumber"]
+            .as_i64()
+            .ok_or("Missing buildNumber in _sde entry")?;
+        let release_date = json.get("releaseDate").and_then(|v| v.as_str());
+
+        conn.execute(
+            "INSERT INTO sdeInfo (buildNumber, releaseDate) VALUES (?1, ?2)",
+            params![build_number, release_date],
+        )?;
+        println!("  SDE build {}", build_number);
+    }
+
+    Ok(())
+}
+
 fn process_types(conn: &Connection, sde_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
     let file_path = sde_dir.join("types.jsonl");
     if !file_path.exists() {
@@ -127,7 +543,10 @@ fn process_types(conn: &Connection, sde_dir: &Path) -> Result<(), Box<dyn std::e
     let file = fs::File::open(&file_path)?;
     let reader = BufReader::new(file);
     let mut stmt = conn.prepare(
-        "INSERT INTO invTypes (typeID, typeName, groupID, published) VALUES (?1, ?2, ?3, ?4)"
+        "INSERT INTO invTypes (typeID, typeName, groupID, marketGroupID, published) VALUES (?1, ?2, ?3, ?4, ?5)"
+    )?;
+    let mut name_stmt = conn.prepare(
+        "INSERT INTO invTypeNames (typeID, language, typeName) VALUES (?1, ?2, ?3)"
     )?;
 
     let mut count = 0;
@@ -153,13 +572,29 @@ fn process_types(conn: &Connection, sde_dir: &Path) -> Result<(), Box<dyn std::e
             .as_i64()
             .ok_or("Missing groupID in type entry")? as i32;
         
+        let market_group_id: Option<i32> = json.get("marketGroupID")
+            .and_then(|v| v.as_i64())
+            .map(|v| v as i32);
+
         let published = json.get("published")
             .and_then(|v| v.as_bool())
             .unwrap_or(false) as i32;
 
-        stmt.execute(params![type_id, type_name, group_id, published])?;
+        stmt.execute(params![type_id, type_name, group_id, market_group_id, published])?;
         count += 1;
 
+        // English lives in invTypes, everything else goes into invTypeNames
+        if let Some(names) = name_obj.as_object() {
+            for (language, localized) in names {
+                if language == "en" {
+                    continue;
+                }
+                if let Some(localized) = localized.as_str() {
+                    name_stmt.execute(params![type_id, language, localized])?;
+                }
+            }
+        }
+
         if count % 10000 == 0 {
             println!("  Processed {} types...", count);
         }
@@ -178,7 +613,7 @@ fn process_groups(conn: &Connection, sde_dir: &Path) -> Result<(), Box<dyn std::
     let file = fs::File::open(&file_path)?;
     let reader = BufReader::new(file);
     let mut stmt = conn.prepare(
-        "INSERT INTO invGroups (groupID, categoryID) VALUES (?1, ?2)"
+        "INSERT INTO invGroups (groupID, categoryID, groupName) VALUES (?1, ?2, ?3)"
     )?;
 
     let mut count = 0;
@@ -197,7 +632,11 @@ fn process_groups(conn: &Connection, sde_dir: &Path) -> Result<(), Box<dyn std::
             .as_i64()
             .ok_or("Missing categoryID in group entry")? as i32;
 
-        stmt.execute(params![group_id, category_id])?;
+        let group_name = json["name"]["en"]
+            .as_str()
+            .ok_or("Missing name.en in group entry")?;
+
+        stmt.execute(params![group_id, category_id, group_name])?;
         count += 1;
     }
 
@@ -205,6 +644,47 @@ fn process_groups(conn: &Connection, sde_dir: &Path) -> Result<(), Box<dyn std::
     Ok(())
 }
 
+fn process_market_groups(conn: &Connection, sde_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let file_path = sde_dir.join("marketGroups.jsonl");
+    if !file_path.exists() {
+        println!("  marketGroups.jsonl not found, skipping market groups...");
+        return Ok(());
+    }
+
+    let file = fs::File::open(&file_path)?;
+    let reader = BufReader::new(file);
+    let mut stmt = conn.prepare(
+        "INSERT INTO invMarketGroups (marketGroupID, parentGroupID, marketGroupName) VALUES (?1, ?2, ?3)"
+    )?;
+
+    let mut count = 0;
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let json: Value = serde_json::from_str(&line)?;
+
+        let market_group_id: i32 = json["_key"].as_i64()
+            .ok_or("Missing _key in market group entry")? as i32;
+
+        let parent_group_id: Option<i32> = json.get("parentGroupID")
+            .and_then(|v| v.as_i64())
+            .map(|v| v as i32);
+
+        let name = json["name"]["en"]
+            .as_str()
+            .ok_or("Missing name.en in market group entry")?;
+
+        stmt.execute(params![market_group_id, parent_group_id, name])?;
+        count += 1;
+    }
+
+    println!("  Inserted {} market groups", count);
+    Ok(())
+}
+
 fn process_meta_types(conn: &Connection, sde_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
     // Meta types are stored in types.jsonl
     // We need to extract entries that have either:
@@ -3,7 +3,7 @@ extern crate proc_macro;
 use eve_data_core::{TypeDB, TypeError};
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, Error, LitStr};
+use syn::{parse_macro_input, punctuated::Punctuated, Error, LitStr, Token};
 
 #[proc_macro]
 pub fn type_id(input: TokenStream) -> TokenStream {
@@ -22,3 +22,33 @@ pub fn type_id(input: TokenStream) -> TokenStream {
     }
     .into()
 }
+
+/// Resolves a list of type names at compile time into a `&'static [TypeID]`, in the given order.
+#[proc_macro]
+pub fn type_ids(input: TokenStream) -> TokenStream {
+    let parser = Punctuated::<LitStr, Token![,]>::parse_terminated;
+    let type_names = parse_macro_input!(input with parser);
+
+    let mut ids = Vec::new();
+    let mut errors = Vec::new();
+    for type_name in &type_names {
+        match TypeDB::id_of_fuzzy(&type_name.value()) {
+            Ok(the_type) => ids.push(the_type),
+            Err(TypeError::NothingMatched) => errors.push(
+                Error::new(type_name.span(), "Could not find type in SDE").to_compile_error(),
+            ),
+            e => {
+                e.unwrap();
+                panic!()
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return quote! { #(#errors)* }.into();
+    }
+    quote! {
+        &[#(#ids),*]
+    }
+    .into()
+}
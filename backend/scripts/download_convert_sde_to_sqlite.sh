@@ -124,7 +124,8 @@ if [ ! -f "$BACKEND_DIR/target/release/convert_sde_to_sqlite" ]; then
 fi
 
 # Pass SDE_ROOT directly - files are in the root, not in fsd/ subdirectory
-"$BACKEND_DIR/target/release/convert_sde_to_sqlite" "$SDE_ROOT"
+# Extra arguments (e.g. --force) are passed through to the converter
+"$BACKEND_DIR/target/release/convert_sde_to_sqlite" "$SDE_ROOT" --data "$BACKEND_DIR/data" "$@"
 
 if [ ! -f "sqlite-shrunk.sqlite" ]; then
     echo "Error: Conversion failed - sqlite-shrunk.sqlite not created"
@@ -0,0 +1,8 @@
+-- Encrypted tokens are longer than the plaintext ones. Existing rows are encrypted by the
+-- backend at startup, with the keys from app.token_encryption_keys. Until a key is added to
+-- the config, tokens stay unencrypted and a warning is logged at startup.
+ALTER TABLE `access_token` MODIFY `access_token` varchar(4096) NOT NULL;
+ALTER TABLE `refresh_token` MODIFY `refresh_token` varchar(1024) NOT NULL;
+ALTER TABLE `srp_service_account`
+  MODIFY `access_token` varchar(4096) NOT NULL,
+  MODIFY `refresh_token` varchar(1024) NOT NULL;
@@ -0,0 +1,26 @@
+CREATE TABLE `fleet_session` (
+  `id` bigint NOT NULL AUTO_INCREMENT,
+  `fleet_id` bigint NOT NULL,
+  `started_at` bigint NOT NULL,
+  `ended_at` bigint DEFAULT NULL,
+  `waitlist_id` bigint DEFAULT NULL,
+  `peak_size` int NOT NULL DEFAULT '0',
+  `constellation_id` bigint DEFAULT NULL,
+  `constellation_name` varchar(255) DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  KEY `fleet_id` (`fleet_id`),
+  KEY `started_at` (`started_at`),
+  CONSTRAINT `fleet_session_ibfk_1` FOREIGN KEY (`waitlist_id`) REFERENCES `waitlist` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `fleet_session_boss` (
+  `id` bigint NOT NULL AUTO_INCREMENT,
+  `session_id` bigint NOT NULL,
+  `character_id` bigint NOT NULL,
+  `started_at` bigint NOT NULL,
+  `ended_at` bigint DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  KEY `session_id` (`session_id`),
+  CONSTRAINT `fleet_session_boss_ibfk_1` FOREIGN KEY (`session_id`) REFERENCES `fleet_session` (`id`),
+  CONSTRAINT `fleet_session_boss_ibfk_2` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,15 @@
+CREATE TABLE `fleet_role_assignment` (
+  `id` bigint NOT NULL AUTO_INCREMENT,
+  `session_id` bigint NOT NULL,
+  `role` varchar(16) NOT NULL,
+  `character_id` bigint NOT NULL,
+  `assigned_by` bigint DEFAULT NULL,
+  `assigned_at` bigint NOT NULL,
+  `removed_at` bigint DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  KEY `session_id` (`session_id`),
+  KEY `character_id` (`character_id`),
+  CONSTRAINT `fleet_role_assignment_ibfk_1` FOREIGN KEY (`session_id`) REFERENCES `fleet_session` (`id`),
+  CONSTRAINT `fleet_role_assignment_ibfk_2` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `fleet_role_assignment_ibfk_3` FOREIGN KEY (`assigned_by`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
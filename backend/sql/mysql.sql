@@ -22,7 +22,7 @@ CREATE TABLE `character` (
 
 CREATE TABLE `access_token` (
   `character_id` bigint NOT NULL,
-  `access_token` varchar(2048) NOT NULL,
+  `access_token` varchar(4096) NOT NULL,
   `expires` bigint NOT NULL,
   `scopes` varchar(1024) NOT NULL,
   PRIMARY KEY (`character_id`),
@@ -31,7 +31,7 @@ CREATE TABLE `access_token` (
 
 CREATE TABLE `refresh_token` (
   `character_id` bigint NOT NULL,
-  `refresh_token` varchar(255) NOT NULL,
+  `refresh_token` varchar(1024) NOT NULL,
   `scopes` varchar(1024) NOT NULL,
   PRIMARY KEY (`character_id`),
   CONSTRAINT `refresh_token_ibfk_1` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`)
@@ -337,8 +337,8 @@ CREATE TABLE `srp_service_account` (
   `character_name` varchar(255) NOT NULL,
   `corporation_id` bigint NOT NULL,
   `wallet_id` int NOT NULL DEFAULT '1000',
-  `access_token` varchar(2048) NOT NULL,
-  `refresh_token` varchar(255) NOT NULL,
+  `access_token` varchar(4096) NOT NULL,
+  `refresh_token` varchar(1024) NOT NULL,
   `expires` bigint NOT NULL,
   `scopes` varchar(1024) NOT NULL,
   `is_active` tinyint NOT NULL DEFAULT '1',
@@ -350,4 +350,47 @@ CREATE TABLE `srp_service_account` (
   KEY `corporation_id` (`corporation_id`),
   KEY `is_active` (`is_active`),
   CONSTRAINT `srp_service_account_ibfk_1` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`)
-) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
\ No newline at end of file
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `fleet_session` (
+  `id` bigint NOT NULL AUTO_INCREMENT,
+  `fleet_id` bigint NOT NULL,
+  `started_at` bigint NOT NULL,
+  `ended_at` bigint DEFAULT NULL,
+  `waitlist_id` bigint DEFAULT NULL,
+  `peak_size` int NOT NULL DEFAULT '0',
+  `constellation_id` bigint DEFAULT NULL,
+  `constellation_name` varchar(255) DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  KEY `fleet_id` (`fleet_id`),
+  KEY `started_at` (`started_at`),
+  CONSTRAINT `fleet_session_ibfk_1` FOREIGN KEY (`waitlist_id`) REFERENCES `waitlist` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `fleet_session_boss` (
+  `id` bigint NOT NULL AUTO_INCREMENT,
+  `session_id` bigint NOT NULL,
+  `character_id` bigint NOT NULL,
+  `started_at` bigint NOT NULL,
+  `ended_at` bigint DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  KEY `session_id` (`session_id`),
+  CONSTRAINT `fleet_session_boss_ibfk_1` FOREIGN KEY (`session_id`) REFERENCES `fleet_session` (`id`),
+  CONSTRAINT `fleet_session_boss_ibfk_2` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE `fleet_role_assignment` (
+  `id` bigint NOT NULL AUTO_INCREMENT,
+  `session_id` bigint NOT NULL,
+  `role` varchar(16) NOT NULL,
+  `character_id` bigint NOT NULL,
+  `assigned_by` bigint DEFAULT NULL,
+  `assigned_at` bigint NOT NULL,
+  `removed_at` bigint DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  KEY `session_id` (`session_id`),
+  KEY `character_id` (`character_id`),
+  CONSTRAINT `fleet_role_assignment_ibfk_1` FOREIGN KEY (`session_id`) REFERENCES `fleet_session` (`id`),
+  CONSTRAINT `fleet_role_assignment_ibfk_2` FOREIGN KEY (`character_id`) REFERENCES `character` (`id`),
+  CONSTRAINT `fleet_role_assignment_ibfk_3` FOREIGN KEY (`assigned_by`) REFERENCES `character` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -16,18 +16,10 @@ pub fn new(db: Arc<crate::DB>, config: Config) -> Application {
     Application {
         affiliation_service: crate::core::affiliation::AffiliationService::new(
             db.clone(),
-            crate::core::esi::ESIClient::new(
-                db.clone(),
-                config.esi.client_id.clone(),
-                config.esi.client_secret.clone(),
-            ),
+            crate::core::esi::ESIClient::new(db.clone(), &config),
         ),
         ban_service: crate::core::ban::BanService::new(db.clone()),
-        esi_client: crate::core::esi::ESIClient::new(
-            db.clone(),
-            config.esi.client_id.clone(),
-            config.esi.client_secret.clone(),
-        ),
+        esi_client: crate::core::esi::ESIClient::new(db.clone(), &config),
         sse_client: crate::core::sse::SSEClient::new(
             config.sse.url.clone(),
             &hex::decode(&config.sse.secret).unwrap(),
@@ -14,11 +14,32 @@ pub struct ESIConfig {
     pub client_id: String,
     pub client_secret: String,
     pub url: String,
+    #[serde(default = "default_esi_base_url")]
+    pub esi_base_url: String,
+    #[serde(default = "default_sso_base_url")]
+    pub sso_base_url: String,
+}
+
+fn default_esi_base_url() -> String {
+    "https://esi.evetech.net".to_string()
+}
+
+fn default_sso_base_url() -> String {
+    "https://login.eveonline.com".to_string()
 }
 
 #[derive(Deserialize, Clone)]
 pub struct AppConfig {
     pub token_secret: String,
+    // Tokens are stored unencrypted until a key is configured
+    #[serde(default)]
+    pub token_encryption_keys: Vec<TokenKeyConfig>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct TokenKeyConfig {
+    pub id: String,
+    pub key: String,
 }
 
 #[derive(Deserialize, Clone)]
@@ -64,6 +85,22 @@ pub struct DiscordConfig {
     pub srp_ping_role_id: String,
 }
 
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct SDEConfig {
+    pub path: String,
+    pub connections: usize,
+}
+
+impl Default for SDEConfig {
+    fn default() -> Self {
+        SDEConfig {
+            path: "sqlite-shrunk.sqlite".to_string(),
+            connections: eve_data_core::DEFAULT_CONNECTIONS,
+        }
+    }
+}
+
 #[derive(Deserialize, Clone)]
 pub struct Config {
     pub database: DatabaseConfig,
@@ -76,4 +113,6 @@ pub struct Config {
     pub incursion_updater: IncursionUpdaterConfig,
     pub janice: JaniceConfig,
     pub discord: DiscordConfig,
+    #[serde(default)]
+    pub sde: SDEConfig,
 }
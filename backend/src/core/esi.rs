@@ -1,6 +1,13 @@
 use serde::{Deserialize, Serialize};
 use std::{collections::BTreeSet, sync::Arc};
 
+use reqwest::header::IF_NONE_MATCH;
+
+use super::esi_budget::{self, ERROR_BUDGET, MAX_RETRIES};
+use super::esi_cache::{CacheKey, Lookup, ESI_CACHE};
+use super::sse::{Event, SSEClient};
+use super::token_crypto::{TokenCipher, TokenCryptoError};
+
 #[derive(Debug, Deserialize)]
 pub struct Incursion {
     pub constellation_id: i64,
@@ -100,21 +107,24 @@ pub struct KillmailData {
     pub war_id: Option<i64>,
 }
 
-#[derive(Debug)]
-pub struct ESIResponse<T> {
-    pub data: T,
-    pub etag: Option<String>,
-}
-
 struct ESIRawClient {
     http: reqwest::Client,
     client_id: String,
     client_secret: String,
+    esi_base_url: String,
+    sso_base_url: String,
 }
 
 pub struct ESIClient {
     db: Arc<crate::DB>,
     raw: ESIRawClient,
+    sse: SSEClient,
+    cipher: TokenCipher,
+}
+
+#[derive(Debug, Serialize)]
+struct TokenRevokedMessage {
+    message: String,
 }
 
 pub struct EsiErrorReason {
@@ -181,9 +191,13 @@ pub enum ESIError {
     NoToken,
     #[error("missing ESI scope")]
     MissingScope,
+    #[error("ESI returned invalid JSON")]
+    InvalidJSON(#[from] serde_json::Error),
+    #[error("stored token could not be decrypted")]
+    TokenDecryption(#[from] TokenCryptoError),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(non_camel_case_types)]
 pub enum ESIScope {
     PublicData,
@@ -194,6 +208,8 @@ pub enum ESIScope {
     Clones_ReadImplants_v1,
     Search_v1,
     Wallet_ReadCorporationWallets_v1,
+    Fittings_ReadFittings_v1,
+    Fittings_WriteFittings_v1,
 }
 
 impl ESIScope {
@@ -208,6 +224,8 @@ impl ESIScope {
             Clones_ReadImplants_v1 => "esi-clones.read_implants.v1",
             Search_v1 => "esi-search.search_structures.v1",
             Wallet_ReadCorporationWallets_v1 => "esi-wallet.read_corporation_wallets.v1",
+            Fittings_ReadFittings_v1 => "esi-fittings.read_fittings.v1",
+            Fittings_WriteFittings_v1 => "esi-fittings.write_fittings.v1",
         }
     }
 }
@@ -222,14 +240,60 @@ impl From<reqwest::Error> for ESIError {
 }
 
 impl ESIRawClient {
-    pub fn new(client_id: String, client_secret: String) -> ESIRawClient {
+    pub fn new(config: &crate::config::ESIConfig) -> ESIRawClient {
         ESIRawClient {
             http: reqwest::Client::builder()
                 .user_agent("Waitlist (https://github.com/TvdW/tdf-waitlist)")
                 .build()
                 .unwrap(),
-            client_id,
-            client_secret,
+            client_id: config.client_id.clone(),
+            client_secret: config.client_secret.clone(),
+            esi_base_url: config.esi_base_url.trim_end_matches('/').to_string(),
+            sso_base_url: config.sso_base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn esi_url(&self, path: &str) -> String {
+        format!("{}{}", self.esi_base_url, path)
+    }
+
+    /// Sends a request within the shared error budget.
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, ESIError> {
+        ERROR_BUDGET.wait().await;
+        let response = request.send().await?;
+        ERROR_BUDGET.update(response.status(), response.headers());
+        Ok(response)
+    }
+
+    /// Like `send`, but tries again on 5xx responses and connection failures. Only for
+    /// requests that are safe to repeat, which for ESI means GETs.
+    async fn send_idempotent(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, ESIError> {
+        let mut attempt = 0;
+        loop {
+            let this_try = request.try_clone().expect("GET requests have no streaming body");
+            let failure = match self.send(this_try).await {
+                Ok(response) if response.status().is_server_error() && attempt < MAX_RETRIES => {
+                    response.status().to_string()
+                }
+                Err(ESIError::HTTPError(e))
+                    if (e.is_connect() || e.is_timeout()) && attempt < MAX_RETRIES =>
+                {
+                    e.to_string()
+                }
+                result => return result,
+            };
+
+            ERROR_BUDGET.record_retry();
+            let delay = esi_budget::retry_delay(attempt);
+            warn!("ESI request failed ({}), retrying in {:?}", failure, delay);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
         }
     }
 
@@ -262,11 +326,12 @@ impl ESIRawClient {
             scope: scope_str,
         };
         Ok(self
-            .http
-            .post("https://login.eveonline.com/v2/oauth/token")
-            .basic_auth(&self.client_id, Some(&self.client_secret))
-            .form(&request)
-            .send()
+            .send(
+                self.http
+                    .post(&format!("{}/v2/oauth/token", self.sso_base_url))
+                    .basic_auth(&self.client_id, Some(&self.client_secret))
+                    .form(&request),
+            )
             .await?
             .error_for_status()?
             .json::<OAuthTokenResponse>()
@@ -288,7 +353,7 @@ impl ESIRawClient {
         }
 
         let result: VerifyResponse = self
-            .get("https://login.eveonline.com/oauth/verify", access_token)
+            .get(&format!("{}/oauth/verify", self.sso_base_url), access_token)
             .await?
             .json()
             .await?;
@@ -317,10 +382,7 @@ impl ESIRawClient {
 
     pub async fn get(&self, url: &str, access_token: &str) -> Result<reqwest::Response, ESIError> {
         let response = self
-            .http
-            .get(url)
-            .bearer_auth(access_token)
-            .send()
+            .send_idempotent(self.http.get(url).bearer_auth(access_token))
             .await?;
 
         if let Err(err) = response.error_for_status_ref() {
@@ -332,31 +394,50 @@ impl ESIRawClient {
         Ok(response)
     }
 
-    pub async fn get_with_etag(&self, url: &str, access_token: &str, etag: Option<&str>) -> Result<reqwest::Response, ESIError> {
-        let mut request = self.http.get(url).bearer_auth(access_token);
-        
-        if let Some(etag) = etag {
-            request = request.header("If-None-Match", format!("\"{}\"", etag));
-        }
-        
-        let response = request.send().await?;
-
-        // Don't treat 304 Not Modified as an error
-        if response.status() == 304 {
-            return Ok(response);
-        }
-
-        if let Err(err) = response.error_for_status_ref() {
-            let status = err.status().unwrap().as_u16();
-            let response_body = response.text().await.unwrap_or_else(|_| "No response body".to_string());
-            return Err(ESIError::WithMessage(status, response_body));
+    /// A GET answered from `ESI_CACHE` while it's fresh, and revalidated with its ETag after.
+    /// Errors come back as `WithMessage`, like `get`.
+    async fn get_cached(
+        &self,
+        url: &str,
+        access_token: Option<&str>,
+        character_id: Option<i64>,
+    ) -> Result<Arc<String>, ESIError> {
+        let key = CacheKey::new(url, character_id);
+        let mut etag = match ESI_CACHE.lookup(&key) {
+            Lookup::Fresh(body) => return Ok(body),
+            Lookup::Stale(etag) => etag,
         };
 
-        Ok(response)
-    }
+        loop {
+            let mut request = self.http.get(url);
+            if let Some(access_token) = access_token {
+                request = request.bearer_auth(access_token);
+            }
+            if let Some(etag) = &etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            let response = self.send_idempotent(request).await?;
+
+            if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+                match ESI_CACHE.revalidated(&key, response.headers()) {
+                    Some(body) => return Ok(body),
+                    // Evicted since we looked, so ask for the whole thing
+                    None if etag.is_some() => {
+                        etag = None;
+                        continue;
+                    }
+                    None => return Err(ESIError::Status(304)),
+                }
+            }
 
-    pub async fn get_unauthenticated(&self, url: &str) -> Result<reqwest::Response, ESIError> {
-        Ok(self.http.get(url).send().await?.error_for_status()?)
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = response.text().await?;
+            if !status.is_success() {
+                return Err(ESIError::WithMessage(status.as_u16(), body));
+            }
+            return Ok(ESI_CACHE.store(key, &headers, body));
+        }
     }
 
     pub async fn delete(
@@ -365,10 +446,7 @@ impl ESIRawClient {
         access_token: &str,
     ) -> Result<reqwest::Response, ESIError> {
         Ok(self
-            .http
-            .delete(url)
-            .bearer_auth(access_token)
-            .send()
+            .send(self.http.delete(url).bearer_auth(access_token))
             .await?
             .error_for_status()?)
     }
@@ -380,11 +458,7 @@ impl ESIRawClient {
         access_token: &str,
     ) -> Result<reqwest::Response, ESIError> {
         let response = self
-            .http
-            .post(url)
-            .bearer_auth(access_token)
-            .json(input)
-            .send()
+            .send(self.http.post(url).bearer_auth(access_token).json(input))
             .await?;
 
         if let Err(err) = response.error_for_status_ref() {
@@ -404,12 +478,7 @@ impl ESIRawClient {
         url: &str,
         input: &E,
     ) -> Result<reqwest::Response, ESIError> {
-        let response = self
-            .http
-            .post(url)
-            .json(input)
-            .send()
-            .await?;
+        let response = self.send(self.http.post(url).json(input)).await?;
 
         if let Err(err) = response.error_for_status_ref() {
             let response_body = response.text().await?;
@@ -430,11 +499,7 @@ impl ESIRawClient {
         access_token: &str,
     ) -> Result<reqwest::Response, ESIError> {
         let response = self
-            .http
-            .put(url)
-            .bearer_auth(access_token)
-            .json(input)
-            .send()
+            .send(self.http.put(url).bearer_auth(access_token).json(input))
             .await?;
 
         if let Err(err) = response.error_for_status_ref() {
@@ -451,13 +516,23 @@ impl ESIRawClient {
 }
 
 impl ESIClient {
-    pub fn new(database: Arc<crate::DB>, client_id: String, client_secret: String) -> ESIClient {
+    pub fn new(database: Arc<crate::DB>, config: &crate::config::Config) -> ESIClient {
         ESIClient {
             db: database,
-            raw: ESIRawClient::new(client_id, client_secret),
+            raw: ESIRawClient::new(&config.esi),
+            sse: SSEClient::new(
+                config.sse.url.clone(),
+                &hex::decode(&config.sse.secret).unwrap(),
+            ),
+            cipher: TokenCipher::new(&config.app.token_encryption_keys),
         }
     }
 
+    /// Encrypts and decrypts tokens as they are stored in the database.
+    pub fn token_cipher(&self) -> &TokenCipher {
+        &self.cipher
+    }
+
     pub async fn process_authorization_code(&self, code: &str) -> Result<i64, ESIError> {
         let mut result = self
             .raw
@@ -511,11 +586,13 @@ impl ESIClient {
         }
 
         let expiry_timestamp = auth.access_token_expiry.timestamp();
+        let access_token = self.cipher.encrypt(&auth.access_token);
+        let refresh_token = self.cipher.encrypt(&auth.refresh_token);
         let scopes = join_scopes(&auth.scopes);
         sqlx::query!(
             "REPLACE INTO access_token (character_id, access_token, expires, scopes) VALUES (?, ?, ?, ?)",
             auth.character_id,
-            auth.access_token,
+            access_token,
             expiry_timestamp,
             scopes,
         )
@@ -525,7 +602,7 @@ impl ESIClient {
         sqlx::query!(
             "REPLACE INTO refresh_token (character_id, refresh_token, scopes) VALUES (?, ?, ?)",
             auth.character_id,
-            auth.refresh_token,
+            refresh_token,
             scopes,
         )
         .execute(&mut tx)
@@ -548,7 +625,8 @@ impl ESIClient {
         .await?
         {
             if record.expires >= chrono::Utc::now().timestamp() {
-                return Ok((record.access_token, split_scopes(&record.scopes)));
+                let access_token = self.cipher.decrypt(&record.access_token)?;
+                return Ok((access_token, split_scopes(&record.scopes)));
             }
         }
 
@@ -568,7 +646,7 @@ impl ESIClient {
             .raw
             .process_auth(
                 "refresh_token",
-                &refresh.refresh_token,
+                &self.cipher.decrypt(&refresh.refresh_token)?,
                 Some(&refresh_scopes),
             )
             .await
@@ -594,6 +672,9 @@ impl ESIClient {
                 .await?;
                 tx.commit().await?;
 
+                if let Err(e) = self.notify_token_revoked(character_id).await {
+                    error!("Could not tell {} their token was revoked: {}", character_id, e);
+                }
                 return Err(ESIError::NoToken);
             }
             Err(e) => return Err(e),
@@ -603,6 +684,41 @@ impl ESIClient {
         Ok((refreshed.access_token, refreshed.scopes))
     }
 
+    /// Tells the pilot, on their main and on any account the character is an alt of, that
+    /// they'll have to log in with the character again.
+    async fn notify_token_revoked(
+        &self,
+        character_id: i64,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let name = sqlx::query!("SELECT name FROM `character` WHERE id=?", character_id)
+            .fetch_one(self.db.as_ref())
+            .await?
+            .name;
+        let mut topics = vec![format!("account;{}", character_id)];
+        for alt in sqlx::query!(
+            "SELECT account_id FROM alt_character WHERE alt_id=?",
+            character_id
+        )
+        .fetch_all(self.db.as_ref())
+        .await?
+        {
+            topics.push(format!("account;{}", alt.account_id));
+        }
+
+        let message = TokenRevokedMessage {
+            message: format!(
+                "ESI access for {} was revoked, log in with it again to keep using it",
+                name
+            ),
+        };
+        let events = topics
+            .iter()
+            .map(|topic| Event::new_json(topic, "message", &message))
+            .collect();
+        self.sse.submit(events).await?;
+        Ok(())
+    }
+
     async fn access_token(&self, character_id: i64, scope: ESIScope) -> Result<String, ESIError> {
         let (token, scopes) = self.access_token_raw(character_id).await?;
 
@@ -620,51 +736,37 @@ impl ESIClient {
         scope: ESIScope,
     ) -> Result<D, ESIError> {
         let access_token = self.access_token(character_id, scope).await?;
-        let url = format!("https://esi.evetech.net{}", path);
+        let url = self.raw.esi_url(path);
         Ok(self.raw.get(&url, &access_token).await?.json().await?)
     }
 
-    pub async fn get_with_etag<D: serde::de::DeserializeOwned>(
+    /// `get` through the response cache, for data that ESI caches on its side anyway.
+    pub async fn get_cached<D: serde::de::DeserializeOwned>(
         &self,
         path: &str,
         character_id: i64,
         scope: ESIScope,
-        etag: Option<&str>,
-    ) -> Result<ESIResponse<D>, ESIError> {
+    ) -> Result<D, ESIError> {
         let access_token = self.access_token(character_id, scope).await?;
-        let url = format!("https://esi.evetech.net{}", path);
-        let response = self.raw.get_with_etag(&url, &access_token, etag).await?;
-        
-        let etag = response.headers()
-            .get("etag")
-            .and_then(|h| h.to_str().ok())
-            .map(|s| s.trim_matches('"').to_string());
-        
-        // Handle 304 Not Modified response
-        if response.status() == 304 {
-            // Return empty data for 304 responses
-            let empty_data: D = serde_json::from_str("[]").unwrap_or_else(|_| {
-                // If we can't parse "[]", try to create a default value
-                // This is a fallback for non-array types
-                serde_json::from_str("{}").unwrap_or_else(|_| {
-                    // If all else fails, this will panic, but it shouldn't happen
-                    // for the types we're using (Vec<WalletJournalEntry>)
-                    panic!("Cannot create empty value for type")
-                })
-            });
-            return Ok(ESIResponse { data: empty_data, etag });
-        }
-        
-        let data = response.json().await?;
-        Ok(ESIResponse { data, etag })
+        let url = self.raw.esi_url(path);
+        let body = self
+            .raw
+            .get_cached(&url, Some(&access_token), Some(character_id))
+            .await?;
+        Ok(serde_json::from_str(&body)?)
     }
 
     pub async fn get_unauthenticated<D: serde::de::DeserializeOwned>(
         &self,
         path: &str,
     ) -> Result<D, ESIError> {
-        let url = format!("https://esi.evetech.net{}", path);
-        Ok(self.raw.get_unauthenticated(&url).await?.json().await?)
+        let url = self.raw.esi_url(path);
+        let body = match self.raw.get_cached(&url, None, None).await {
+            Ok(body) => body,
+            Err(ESIError::WithMessage(status, _)) => return Err(ESIError::Status(status)),
+            Err(e) => return Err(e),
+        };
+        Ok(serde_json::from_str(&body)?)
     }
 
     pub async fn get_incursions(&self) -> Result<Vec<Incursion>, ESIError> {
@@ -708,7 +810,7 @@ impl ESIClient {
             return Ok(std::collections::HashMap::new());
         }
 
-        let url = format!("https://esi.evetech.net/v2/universe/names/");
+        let url = self.raw.esi_url("/v2/universe/names/");
         
         match self.raw.post_unauthenticated(&url, ids).await {
             Ok(response) => {
@@ -745,7 +847,7 @@ impl ESIClient {
         scope: ESIScope,
     ) -> Result<(), ESIError> {
         let access_token = self.access_token(character_id, scope).await?;
-        let url = format!("https://esi.evetech.net{}", path);
+        let url = self.raw.esi_url(path);
         self.raw.delete(&url, &access_token).await?;
         Ok(())
     }
@@ -758,11 +860,24 @@ impl ESIClient {
         scope: ESIScope,
     ) -> Result<(), ESIError> {
         let access_token = self.access_token(character_id, scope).await?;
-        let url = format!("https://esi.evetech.net{}", path);
+        let url = self.raw.esi_url(path);
         self.raw.post::<E>(&url, input, &access_token).await?;
         Ok(())
     }
 
+    /// `post` for endpoints that answer with something, like the ID of what was created.
+    pub async fn post_json<E: Serialize + ?Sized, D: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        input: &E,
+        character_id: i64,
+        scope: ESIScope,
+    ) -> Result<D, ESIError> {
+        let access_token = self.access_token(character_id, scope).await?;
+        let url = self.raw.esi_url(path);
+        Ok(self.raw.post::<E>(&url, input, &access_token).await?.json().await?)
+    }
+
     pub async fn put<E: Serialize + ?Sized>(
         &self,
         path: &str,
@@ -771,12 +886,36 @@ impl ESIClient {
         scope: ESIScope,
     ) -> Result<(), ESIError> {
         let access_token = self.access_token(character_id, scope).await?;
-        let url = format!("https://esi.evetech.net{}", path);
+        let url = self.raw.esi_url(path);
         self.raw.put::<E>(&url, input, &access_token).await?;
         Ok(())
     }
 }
 
+pub mod character_fleet {
+    use crate::core::esi::ESIScope;
+
+    use super::{ESIClient, ESIError};
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    pub struct ESICharacterFleet {
+        pub fleet_id: i64,
+        pub fleet_boss_id: i64,
+    }
+
+    /// The fleet the character is in, which any member can read with their own token.
+    pub async fn get(client: &ESIClient, character_id: i64) -> Result<ESICharacterFleet, ESIError> {
+        Ok(client
+            .get_cached(
+                &format!("/v1/characters/{}/fleet", character_id),
+                character_id,
+                ESIScope::Fleets_ReadFleet_v1,
+            )
+            .await?)
+    }
+}
+
 pub mod fleet_members {
     use eve_data_core::TypeID;
 
@@ -800,7 +939,7 @@ pub mod fleet_members {
         boss_id: i64,
     ) -> Result<Vec<ESIFleetMember>, ESIError> {
         Ok(client
-            .get(
+            .get_cached(
                 &format!("/v1/fleets/{}/members", fleet_id),
                 boss_id,
                 ESIScope::Fleets_ReadFleet_v1,
@@ -860,7 +999,246 @@ pub mod fleet_info {
     }
 }
 
-fn split_scopes(input: &str) -> BTreeSet<String> {
+pub mod fleet_wings {
+    use crate::core::esi::ESIScope;
+
+    use super::{ESIClient, ESIError};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize)]
+    struct NewWing {
+        wing_id: i64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct NewSquad {
+        squad_id: i64,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct Rename<'a> {
+        name: &'a str,
+    }
+
+    /// Creates a wing and names it, returning its ID.
+    pub async fn create_wing(
+        client: &ESIClient,
+        fleet_id: i64,
+        boss_id: i64,
+        name: &str,
+    ) -> Result<i64, ESIError> {
+        let wing: NewWing = client
+            .post_json(
+                &format!("/v1/fleets/{}/wings/", fleet_id),
+                &serde_json::json!({}),
+                boss_id,
+                ESIScope::Fleets_WriteFleet_v1,
+            )
+            .await?;
+        client
+            .put(
+                &format!("/v1/fleets/{}/wings/{}/", fleet_id, wing.wing_id),
+                &Rename { name },
+                boss_id,
+                ESIScope::Fleets_WriteFleet_v1,
+            )
+            .await?;
+        Ok(wing.wing_id)
+    }
+
+    /// Creates a squad in the wing and names it, returning its ID.
+    pub async fn create_squad(
+        client: &ESIClient,
+        fleet_id: i64,
+        boss_id: i64,
+        wing_id: i64,
+        name: &str,
+    ) -> Result<i64, ESIError> {
+        let squad: NewSquad = client
+            .post_json(
+                &format!("/v1/fleets/{}/wings/{}/squads/", fleet_id, wing_id),
+                &serde_json::json!({}),
+                boss_id,
+                ESIScope::Fleets_WriteFleet_v1,
+            )
+            .await?;
+        rename_squad(client, fleet_id, boss_id, squad.squad_id, name).await?;
+        Ok(squad.squad_id)
+    }
+
+    pub async fn delete_wing(
+        client: &ESIClient,
+        fleet_id: i64,
+        boss_id: i64,
+        wing_id: i64,
+    ) -> Result<(), ESIError> {
+        client
+            .delete(
+                &format!("/v1/fleets/{}/wings/{}/", fleet_id, wing_id),
+                boss_id,
+                ESIScope::Fleets_WriteFleet_v1,
+            )
+            .await
+    }
+
+    pub async fn rename_squad(
+        client: &ESIClient,
+        fleet_id: i64,
+        boss_id: i64,
+        squad_id: i64,
+        name: &str,
+    ) -> Result<(), ESIError> {
+        client
+            .put(
+                &format!("/v1/fleets/{}/squads/{}/", fleet_id, squad_id),
+                &Rename { name },
+                boss_id,
+                ESIScope::Fleets_WriteFleet_v1,
+            )
+            .await
+    }
+}
+
+pub mod fittings {
+    use std::collections::BTreeMap;
+
+    use eve_data_core::{FitError, Fitting, TypeDB, TypeID};
+
+    use crate::core::esi::ESIScope;
+
+    use super::{ESIClient, ESIError};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, Serialize)]
+    pub struct ESIFittingItem {
+        pub flag: String,
+        pub quantity: i64,
+        pub type_id: TypeID,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct ESIFitting {
+        pub fitting_id: i64,
+        pub name: String,
+        pub description: String,
+        pub ship_type_id: TypeID,
+        pub items: Vec<ESIFittingItem>,
+    }
+
+    impl ESIFitting {
+        pub fn to_fitting(&self) -> Fitting {
+            let mut modules = BTreeMap::new();
+            let mut cargo = BTreeMap::new();
+            for item in &self.items {
+                // Everything that isn't in the cargo hold is fitted, drones included
+                let dest = match item.flag.as_str() {
+                    "Cargo" => &mut cargo,
+                    _ => &mut modules,
+                };
+                *dest.entry(item.type_id).or_insert(0) += item.quantity;
+            }
+            // Saved fittings don't remember what was loaded, charges only show up as cargo
+            Fitting {
+                hull: self.ship_type_id,
+                modules,
+                cargo,
+                charges: BTreeMap::new(),
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct ESINewFitting {
+        pub name: String,
+        pub description: String,
+        pub ship_type_id: TypeID,
+        pub items: Vec<ESIFittingItem>,
+    }
+
+    impl ESINewFitting {
+        pub fn from_fitting(name: &str, description: &str, fit: &Fitting) -> Result<Self, FitError> {
+            let mut ids: Vec<TypeID> = fit.modules.keys().copied().collect();
+            ids.extend(fit.cargo.keys());
+            let types = TypeDB::load_types(&ids)?;
+
+            let mut items = Vec::new();
+            let mut slot_index = BTreeMap::new();
+            for (type_id, &count) in &fit.modules {
+                let module = match types.get(type_id) {
+                    Some(Some(module)) => module,
+                    _ => return Err(FitError::InvalidModule),
+                };
+                let flag_prefix = match module.slot() {
+                    Some("drone") => {
+                        items.push(ESIFittingItem {
+                            flag: "DroneBay".to_string(),
+                            quantity: count,
+                            type_id: *type_id,
+                        });
+                        continue;
+                    }
+                    Some("high") => "HiSlot",
+                    Some("med") => "MedSlot",
+                    Some("low") => "LoSlot",
+                    Some("rig") => "RigSlot",
+                    _ => "SubSystemSlot",
+                };
+                // Every fitted module takes its own slot
+                for _ in 0..count {
+                    let index = slot_index.entry(flag_prefix).or_insert(0);
+                    items.push(ESIFittingItem {
+                        flag: format!("{}{}", flag_prefix, index),
+                        quantity: 1,
+                        type_id: *type_id,
+                    });
+                    *index += 1;
+                }
+            }
+            for (&type_id, &quantity) in &fit.cargo {
+                items.push(ESIFittingItem {
+                    flag: "Cargo".to_string(),
+                    quantity,
+                    type_id,
+                });
+            }
+
+            Ok(ESINewFitting {
+                // ESI rejects names over 50 characters
+                name: name.chars().take(50).collect(),
+                description: description.chars().take(500).collect(),
+                ship_type_id: fit.hull,
+                items,
+            })
+        }
+    }
+
+    pub async fn get(client: &ESIClient, character_id: i64) -> Result<Vec<ESIFitting>, ESIError> {
+        Ok(client
+            .get(
+                &format!("/v2/characters/{}/fittings/", character_id),
+                character_id,
+                ESIScope::Fittings_ReadFittings_v1,
+            )
+            .await?)
+    }
+
+    pub async fn create(
+        client: &ESIClient,
+        character_id: i64,
+        fitting: &ESINewFitting,
+    ) -> Result<(), ESIError> {
+        client
+            .post(
+                &format!("/v2/characters/{}/fittings/", character_id),
+                fitting,
+                character_id,
+                ESIScope::Fittings_WriteFittings_v1,
+            )
+            .await
+    }
+}
+
+pub fn split_scopes(input: &str) -> BTreeSet<String> {
     input
         .split(' ')
         .filter(|s| !s.is_empty())
@@ -0,0 +1,177 @@
+//! ESI only allows so many error responses per window before it blocks the whole IP, and
+//! every ESIClient in the process (routes and background updaters alike) spends the same
+//! budget. The raw client reports every response here and waits here before sending.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use serde::Serialize;
+
+/// With this many errors or fewer left in the window, requests wait for the window to reset
+pub const LOW_WATERMARK: i64 = 10;
+/// How often an idempotent GET is tried again after a 5xx or a connection failure
+pub const MAX_RETRIES: u32 = 3;
+const RETRY_BASE: Duration = Duration::from_millis(250);
+// ESI sends 420 once the budget is gone; the window is a minute if it doesn't say otherwise
+const DEFAULT_WINDOW: Duration = Duration::from_secs(60);
+
+lazy_static::lazy_static! {
+    pub static ref ERROR_BUDGET: ErrorBudget = ErrorBudget::default();
+}
+
+#[derive(Debug, Default)]
+struct BudgetState {
+    remain: Option<i64>,
+    reset_at: Option<Instant>,
+    errors: u64,
+    retries: u64,
+    backoffs: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct ErrorBudget {
+    state: Mutex<BudgetState>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorBudgetStatus {
+    /// Errors ESI will still accept in this window, as of the last response
+    pub remain: Option<i64>,
+    pub reset_in_seconds: Option<u64>,
+    pub backing_off: bool,
+    pub low_watermark: i64,
+    /// Totals since startup
+    pub errors: u64,
+    pub retries: u64,
+    pub backoffs: u64,
+}
+
+fn jitter(max: Duration) -> Duration {
+    max.mul_f64(rand::thread_rng().gen_range(0.0..1.0))
+}
+
+/// Exponential backoff with full jitter on top, so retries from different tasks spread out.
+pub fn retry_delay(attempt: u32) -> Duration {
+    let delay = RETRY_BASE * 2u32.pow(attempt);
+    delay + jitter(delay)
+}
+
+impl ErrorBudget {
+    pub fn update(&self, status: reqwest::StatusCode, headers: &reqwest::header::HeaderMap) {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<i64>().ok())
+        };
+        let now = Instant::now();
+
+        let mut state = self.state.lock().unwrap();
+        if status.is_client_error() || status.is_server_error() {
+            state.errors += 1;
+        }
+        if let Some(remain) = header("x-esi-error-limit-remain") {
+            state.remain = Some(remain);
+        }
+        if let Some(reset) = header("x-esi-error-limit-reset") {
+            state.reset_at = Some(now + Duration::from_secs(reset.max(0) as u64));
+        }
+        if status.as_u16() == 420 {
+            state.remain = Some(0);
+            if state.reset_at.map_or(true, |reset_at| reset_at <= now) {
+                state.reset_at = Some(now + DEFAULT_WINDOW);
+            }
+        }
+    }
+
+    pub fn record_retry(&self) {
+        self.state.lock().unwrap().retries += 1;
+    }
+
+    fn backoff(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+        match (state.remain, state.reset_at) {
+            (Some(remain), Some(reset_at)) if remain <= LOW_WATERMARK => reset_at
+                .checked_duration_since(Instant::now())
+                .filter(|wait| !wait.is_zero()),
+            _ => None,
+        }
+    }
+
+    /// Waits out the rest of the window if the budget is nearly spent.
+    pub async fn wait(&self) {
+        if let Some(wait) = self.backoff() {
+            self.state.lock().unwrap().backoffs += 1;
+            warn!("ESI error budget is low, holding requests for {:?}", wait);
+            // Everyone who waited shouldn't hit ESI in the same instant
+            tokio::time::sleep(wait + jitter(Duration::from_secs(1))).await;
+        }
+    }
+
+    pub fn status(&self) -> ErrorBudgetStatus {
+        let backoff = self.backoff();
+        let state = self.state.lock().unwrap();
+        ErrorBudgetStatus {
+            remain: state.remain,
+            reset_in_seconds: state
+                .reset_at
+                .and_then(|reset_at| reset_at.checked_duration_since(Instant::now()))
+                .map(|wait| wait.as_secs()),
+            backing_off: backoff.is_some(),
+            low_watermark: LOW_WATERMARK,
+            errors: state.errors,
+            retries: state.retries,
+            backoffs: state.backoffs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{retry_delay, ErrorBudget, LOW_WATERMARK};
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use reqwest::StatusCode;
+    use std::time::Duration;
+
+    fn headers(remain: i64, reset: i64) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-ESI-Error-Limit-Remain", HeaderValue::from(remain));
+        headers.insert("X-ESI-Error-Limit-Reset", HeaderValue::from(reset));
+        headers
+    }
+
+    #[test]
+    fn test_budget() {
+        let budget = ErrorBudget::default();
+        assert!(!budget.status().backing_off);
+
+        budget.update(StatusCode::OK, &headers(100, 30));
+        assert_eq!(budget.status().remain, Some(100));
+        assert!(!budget.status().backing_off);
+
+        budget.update(StatusCode::BAD_GATEWAY, &headers(LOW_WATERMARK, 30));
+        let status = budget.status();
+        assert!(status.backing_off);
+        assert_eq!(status.errors, 1);
+
+        // The window is over, even if nothing told us the new budget yet
+        budget.update(StatusCode::OK, &headers(LOW_WATERMARK, 0));
+        assert!(!budget.status().backing_off);
+
+        // Out of budget without headers still backs off
+        budget.update(StatusCode::from_u16(420).unwrap(), &HeaderMap::new());
+        let status = budget.status();
+        assert_eq!(status.remain, Some(0));
+        assert!(status.backing_off);
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        for attempt in 0..3 {
+            let base = Duration::from_millis(250) * 2u32.pow(attempt);
+            let delay = retry_delay(attempt);
+            assert!(delay >= base && delay <= base * 2);
+        }
+    }
+}
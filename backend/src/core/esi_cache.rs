@@ -0,0 +1,201 @@
+//! ESI says how long a response stays valid (`Expires`) and how to ask whether it changed
+//! afterwards (`ETag`). GETs through the raw client keep their bodies here, per URL and per
+//! character whose token fetched them, so repeat lookups don't cost a request until ESI
+//! would have something new to say.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::header::{HeaderMap, DATE, ETAG, EXPIRES};
+
+// Past this, expired entries without an ETag are dropped, and if that isn't enough, everything
+const MAX_ENTRIES: usize = 20_000;
+
+lazy_static::lazy_static! {
+    pub static ref ESI_CACHE: ESICache = ESICache::default();
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    url: String,
+    character_id: Option<i64>,
+}
+
+impl CacheKey {
+    pub fn new(url: &str, character_id: Option<i64>) -> CacheKey {
+        CacheKey {
+            url: url.to_string(),
+            character_id,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    body: Arc<String>,
+    etag: Option<String>,
+    expires_at: Instant,
+}
+
+pub enum Lookup {
+    /// Still within `Expires`, no need to ask ESI
+    Fresh(Arc<String>),
+    /// Ask ESI, with `If-None-Match` if there's an ETag
+    Stale(Option<String>),
+}
+
+#[derive(Debug, Default)]
+pub struct ESICache {
+    entries: Mutex<HashMap<CacheKey, CacheEntry>>,
+}
+
+/// How much longer the response stays valid, measured against ESI's own clock.
+fn time_to_live(headers: &HeaderMap) -> Duration {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| chrono::DateTime::parse_from_rfc2822(value).ok())
+    };
+    let expires = match header(EXPIRES) {
+        Some(expires) => expires,
+        None => return Duration::ZERO,
+    };
+    let now = header(DATE).unwrap_or_else(|| chrono::Utc::now().into());
+    (expires - now).to_std().unwrap_or(Duration::ZERO)
+}
+
+fn etag(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+impl ESICache {
+    pub fn lookup(&self, key: &CacheKey) -> Lookup {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => Lookup::Fresh(entry.body.clone()),
+            Some(entry) => Lookup::Stale(entry.etag.clone()),
+            None => Lookup::Stale(None),
+        }
+    }
+
+    /// Keeps a 200 response, if there's anything that makes it worth keeping.
+    pub fn store(&self, key: CacheKey, headers: &HeaderMap, body: String) -> Arc<String> {
+        let body = Arc::new(body);
+        let ttl = time_to_live(headers);
+        let etag = etag(headers);
+        if ttl.is_zero() && etag.is_none() {
+            return body;
+        }
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, entry| entry.expires_at > now || entry.etag.is_some());
+            if entries.len() >= MAX_ENTRIES {
+                entries.clear();
+            }
+        }
+        entries.insert(
+            key,
+            CacheEntry {
+                body: body.clone(),
+                etag,
+                expires_at: now + ttl,
+            },
+        );
+        body
+    }
+
+    /// ESI answered 304: the cached body is good for another `Expires`. `None` if the entry
+    /// was dropped in the meantime.
+    pub fn revalidated(&self, key: &CacheKey, headers: &HeaderMap) -> Option<Arc<String>> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get_mut(key)?;
+        entry.expires_at = Instant::now() + time_to_live(headers);
+        if let Some(etag) = etag(headers) {
+            entry.etag = Some(etag);
+        }
+        Some(entry.body.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{time_to_live, CacheKey, ESICache, Lookup};
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use std::time::Duration;
+
+    fn headers(date: &str, expires: &str, etag: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("Date", HeaderValue::from_str(date).unwrap());
+        headers.insert("Expires", HeaderValue::from_str(expires).unwrap());
+        if let Some(etag) = etag {
+            headers.insert("ETag", HeaderValue::from_str(etag).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_time_to_live() {
+        let fresh = headers(
+            "Mon, 19 Oct 2026 12:00:00 GMT",
+            "Mon, 19 Oct 2026 12:05:00 GMT",
+            None,
+        );
+        assert_eq!(time_to_live(&fresh), Duration::from_secs(300));
+
+        let expired = headers(
+            "Mon, 19 Oct 2026 12:05:01 GMT",
+            "Mon, 19 Oct 2026 12:05:00 GMT",
+            None,
+        );
+        assert_eq!(time_to_live(&expired), Duration::ZERO);
+        assert_eq!(time_to_live(&HeaderMap::new()), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_cache() {
+        let cache = ESICache::default();
+        let key = CacheKey::new("https://esi/v1/thing/", Some(1));
+        let other_character = CacheKey::new("https://esi/v1/thing/", Some(2));
+
+        // Nothing to keep: no ETag, already expired
+        let stale = headers(
+            "Mon, 19 Oct 2026 12:00:00 GMT",
+            "Mon, 19 Oct 2026 12:00:00 GMT",
+            None,
+        );
+        cache.store(key.clone(), &stale, "[1]".to_string());
+        assert!(matches!(cache.lookup(&key), Lookup::Stale(None)));
+
+        let fresh = headers(
+            "Mon, 19 Oct 2026 12:00:00 GMT",
+            "Mon, 19 Oct 2026 12:05:00 GMT",
+            Some("\"abc\""),
+        );
+        cache.store(key.clone(), &fresh, "[2]".to_string());
+        assert!(matches!(cache.lookup(&key), Lookup::Fresh(body) if *body == "[2]"));
+        assert!(matches!(
+            cache.lookup(&other_character),
+            Lookup::Stale(None)
+        ));
+
+        // Expired, but can be revalidated
+        let expired = headers(
+            "Mon, 19 Oct 2026 12:05:00 GMT",
+            "Mon, 19 Oct 2026 12:05:00 GMT",
+            Some("\"abc\""),
+        );
+        cache.store(key.clone(), &expired, "[2]".to_string());
+        assert!(matches!(cache.lookup(&key), Lookup::Stale(Some(etag)) if etag == "\"abc\""));
+        let body = cache.revalidated(&key, &fresh).unwrap();
+        assert_eq!(*body, "[2]");
+        assert!(matches!(cache.lookup(&key), Lookup::Fresh(_)));
+        assert!(cache.revalidated(&other_character, &fresh).is_none());
+    }
+}
@@ -1,5 +1,5 @@
 use crate::core::esi::{self, ESIScope};
-use crate::data::character;
+use crate::data::{character, fleet_roles, fleet_session, motd::Motd, motd_roles};
 use crate::{config::Config, util::madness::Madness};
 use eve_data_core::TypeID;
 use serde::{Deserialize, Serialize};
@@ -18,11 +18,7 @@ pub struct FleetUpdater {
 impl FleetUpdater {
     pub fn new(db: Arc<crate::DB>, config: Config) -> FleetUpdater {
         FleetUpdater {
-            esi_client: esi::ESIClient::new(
-                db.clone(),
-                config.esi.client_id.clone(),
-                config.esi.client_secret.clone(),
-            ),
+            esi_client: esi::ESIClient::new(db.clone(), &config),
             sse_client: sse::SSEClient::new(
                 config.sse.url.clone(),
                 &hex::decode(&config.sse.secret).unwrap(),
@@ -56,7 +52,7 @@ impl FleetUpdater {
         &self.db
     }
 
-    async fn run_once(&self) -> Result<(), Madness> {
+    pub(crate) async fn run_once(&self) -> Result<(), Madness> {
         // XXX Get global update lock
 
         let fleets = sqlx::query!("SELECT id FROM fleet")
@@ -74,30 +70,52 @@ impl FleetUpdater {
         let fleet = sqlx::query!("SELECT * FROM fleet WHERE id = ?", fleet_id)
             .fetch_one(self.get_db())
             .await?;
-        let members_raw =
-            match esi::fleet_members::get(&self.esi_client, fleet_id, fleet.boss_id).await {
-                Ok(m) => m,
-                Err(
-                    esi::ESIError::WithMessage(403, _)
-                    | esi::ESIError::WithMessage(404, _)
-                    | esi::ESIError::NoToken
-                    | esi::ESIError::MissingScope,
-                ) => {
-                    // 403/404 => Delete the fleet, move on
-                    let mut tx = self.get_db().begin().await?;
-                    sqlx::query!("DELETE FROM fleet_squad WHERE fleet_id=?", fleet_id)
-                        .execute(&mut tx)
-                        .await?;
-                    sqlx::query!("DELETE FROM fleet WHERE id=?", fleet_id)
-                        .execute(&mut tx)
-                        .await?;
-                    tx.commit().await?;
-                    return Ok(());
+        let mut boss_id = fleet.boss_id;
+        let members_raw = match esi::fleet_members::get(&self.esi_client, fleet_id, boss_id).await {
+            Ok(m) => m,
+            Err(esi::ESIError::WithMessage(403, _) | esi::ESIError::MissingScope) => {
+                // Only the boss can see the members, so the fleet may have been handed over
+                if let Some((new_boss, members)) = self.find_new_boss(fleet_id, boss_id).await? {
+                    self.hand_over(fleet_id, boss_id, new_boss).await?;
+                    boss_id = new_boss;
+                    members
+                } else {
+                    return self.delete_fleet(fleet_id).await;
                 }
-                Err(e) => return Err(Madness::from(e)),
-            };
+            }
+            Err(esi::ESIError::WithMessage(404, _) | esi::ESIError::NoToken) => {
+                // 404 => The fleet is gone, delete it and move on
+                return self.delete_fleet(fleet_id).await;
+            }
+            Err(e) => return Err(Madness::from(e)),
+        };
+
+        // The old boss can still see the members for a moment after handing the fleet over,
+        // but the fleet already knows who the new boss is
+        if let Ok(info) = esi::character_fleet::get(&self.esi_client, boss_id).await {
+            if info.fleet_id == fleet_id
+                && info.fleet_boss_id != boss_id
+                && esi::fleet_members::get(&self.esi_client, fleet_id, info.fleet_boss_id)
+                    .await
+                    .is_ok()
+            {
+                self.hand_over(fleet_id, boss_id, info.fleet_boss_id)
+                    .await?;
+                boss_id = info.fleet_boss_id;
+            }
+        }
         let members: HashMap<_, _> = members_raw.iter().map(|m| (m.character_id, m)).collect();
         let member_ids: Vec<i64> = members.iter().map(|(&id, _mem)| id).collect();
+        let session_id = {
+            let mut tx = self.get_db().begin().await?;
+            let now = chrono::Utc::now().timestamp();
+            let session_id =
+                fleet_session::update(&mut tx, fleet_id, boss_id, members.len(), now).await?;
+            tx.commit().await?;
+            session_id
+        };
+        self.remove_departed_roles(fleet_id, boss_id, session_id, &member_ids)
+            .await?;
 
         {
             // Update characters to make sure we have each in the database
@@ -113,7 +131,7 @@ impl FleetUpdater {
                         .esi_client
                         .get(
                             &format!("/v5/characters/{}/", id),
-                            fleet.boss_id,
+                            boss_id,
                             ESIScope::PublicData,
                         )
                         .await?;
@@ -192,7 +210,7 @@ impl FleetUpdater {
 
             // Detect newly joined members
             for (&id, member) in &have_in_fleet {
-                let is_boss = (fleet.boss_id == id) as i8;
+                let is_boss = (boss_id == id) as i8;
 
                 let mut should_insert = false;
                 if let Some(stored) = stored_in_fleet.get(&id) {
@@ -252,6 +270,169 @@ impl FleetUpdater {
         Ok(())
     }
 
+    /// Takes pilots who left off their roles, and off the MOTD. Only the lines of those roles
+    /// are rewritten, after picking up pilots added to the MOTD by hand. Nothing is stored
+    /// until the MOTD is written, so a failed write is tried again on the next run.
+    async fn remove_departed_roles(
+        &self,
+        fleet_id: i64,
+        boss_id: i64,
+        session_id: i64,
+        member_ids: &[i64],
+    ) -> Result<(), Madness> {
+        let now = chrono::Utc::now().timestamp();
+        let mut changes = fleet_roles::Changes::load(self.get_db(), session_id, now).await?;
+        let departed = changes
+            .assignments()
+            .iter()
+            .any(|assignment| !member_ids.contains(&assignment.character_id));
+        if !departed {
+            return Ok(());
+        }
+
+        let roles = motd_roles::roles();
+        let info = match esi::fleet_info::get(&self.esi_client, fleet_id, boss_id).await {
+            Ok(info) => info,
+            Err(e) => {
+                warn!("Could not read the MOTD of fleet {}: {:?}", fleet_id, e);
+                return Ok(());
+            }
+        };
+        let mut motd = Motd::parse(&info.motd, &roles);
+        let in_fleet: HashMap<i64, String> = character::lookup(self.get_db(), member_ids)
+            .await?
+            .into_iter()
+            .map(|(id, character)| (id, character.name))
+            .collect();
+
+        changes.import(&motd, &roles, &in_fleet);
+        changes.remove_missing(member_ids);
+        changes.project(&mut motd);
+
+        // Only stored once the MOTD is written, so a failed write is tried again next time
+        let rendered = motd.render();
+        if rendered != info.motd {
+            if let Err(e) =
+                esi::fleet_info::update(&self.esi_client, fleet_id, boss_id, rendered).await
+            {
+                warn!("Could not update the MOTD of fleet {}: {:?}", fleet_id, e);
+                return Ok(());
+            }
+        }
+        changes.store(self.get_db()).await?;
+
+        Ok(())
+    }
+
+    /// Looks for the pilot the fleet was handed to among those last seen in it. Only the boss
+    /// can read the member list, so whoever can is it.
+    async fn delete_fleet(&self, fleet_id: i64) -> Result<(), Madness> {
+        let mut tx = self.get_db().begin().await?;
+        sqlx::query!("DELETE FROM fleet_squad WHERE fleet_id=?", fleet_id)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query!("DELETE FROM fleet WHERE id=?", fleet_id)
+            .execute(&mut tx)
+            .await?;
+        fleet_session::end(&mut tx, fleet_id, chrono::Utc::now().timestamp()).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn find_new_boss(
+        &self,
+        fleet_id: i64,
+        old_boss_id: i64,
+    ) -> Result<Option<(i64, Vec<esi::fleet_members::ESIFleetMember>)>, Madness> {
+        let candidates = sqlx::query!(
+            "SELECT DISTINCT fleet_activity.character_id FROM fleet_activity JOIN refresh_token ON fleet_activity.character_id=refresh_token.character_id WHERE fleet_id=? AND has_left=0 AND fleet_activity.character_id != ? AND refresh_token.scopes LIKE ?",
+            fleet_id,
+            old_boss_id,
+            format!("%{}%", ESIScope::Fleets_ReadFleet_v1.as_str())
+        )
+        .fetch_all(self.get_db())
+        .await?;
+
+        // Anyone still in the fleet can tell us who the boss is, so ask the old boss first and
+        // only check the members list with the boss it names
+        let askers = std::iter::once(old_boss_id).chain(candidates.iter().map(|c| c.character_id));
+        for asker in askers {
+            let info = match esi::character_fleet::get(&self.esi_client, asker).await {
+                Ok(info) if info.fleet_id == fleet_id => info,
+                _ => continue,
+            };
+            if info.fleet_boss_id == old_boss_id {
+                return Ok(None);
+            }
+            return match esi::fleet_members::get(&self.esi_client, fleet_id, info.fleet_boss_id)
+                .await
+            {
+                Ok(members) => Ok(Some((info.fleet_boss_id, members))),
+                Err(_) => Ok(None),
+            };
+        }
+        Ok(None)
+    }
+
+    async fn hand_over(
+        &self,
+        fleet_id: i64,
+        old_boss_id: i64,
+        new_boss_id: i64,
+    ) -> Result<(), Madness> {
+        let mut tx = self.get_db().begin().await?;
+        sqlx::query!(
+            "UPDATE fleet SET boss_id=? WHERE id=?",
+            new_boss_id,
+            fleet_id
+        )
+        .execute(&mut tx)
+        .await?;
+        fleet_session::start(
+            &mut tx,
+            fleet_id,
+            new_boss_id,
+            chrono::Utc::now().timestamp(),
+        )
+        .await?;
+        tx.commit().await?;
+
+        #[derive(Debug, Serialize)]
+        struct Message {
+            message: String,
+        }
+        let names = character::lookup(self.get_db(), &[old_boss_id, new_boss_id]).await?;
+        let name = |id: i64| {
+            names
+                .get(&id)
+                .map(|character| character.name.clone())
+                .unwrap_or_else(|| id.to_string())
+        };
+        let message = format!(
+            "{} handed fleet boss to {}",
+            name(old_boss_id),
+            name(new_boss_id)
+        );
+        info!("Fleet {}: {}", fleet_id, message);
+        // The handover is done, a missed notification shouldn't hold up the other fleets
+        if let Err(e) = self
+            .sse_client
+            .submit(vec![sse::Event::new_json(
+                "fleet_comp",
+                "message",
+                &Message { message },
+            )])
+            .await
+        {
+            warn!(
+                "Could not announce the handover of fleet {}: {:?}",
+                fleet_id, e
+            );
+        }
+
+        Ok(())
+    }
+
     async fn notify_sse(
         &self,
         fleet_id: i64,
@@ -3,8 +3,11 @@ pub mod auth;
 pub mod ban;
 pub mod discord;
 pub mod esi;
+pub mod esi_budget;
+pub mod esi_cache;
 pub mod fleet_updater;
 pub mod incursion_updater;
 pub mod skill_updater;
 pub mod srp_updater;
 pub mod sse;
+pub mod token_crypto;
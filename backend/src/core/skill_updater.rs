@@ -13,11 +13,7 @@ pub struct SkillUpdater {
 impl SkillUpdater {
     pub fn new(db: Arc<crate::DB>, config: Config) -> SkillUpdater {
         SkillUpdater {
-            esi_client: esi::ESIClient::new(
-                db.clone(),
-                config.esi.client_id.clone(),
-                config.esi.client_secret.clone(),
-            ),
+            esi_client: esi::ESIClient::new(db.clone(), &config),
             db,
             config,
         }
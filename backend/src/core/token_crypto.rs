@@ -0,0 +1,254 @@
+//! ESI tokens are stored encrypted. Every token gets its own random data key, and only that
+//! data key is encrypted with a key from the config (the key-encryption key). Rotating the
+//! config key then means re-wrapping a few bytes per row instead of re-encrypting tokens.
+//!
+//! Stored form: `enc1:<key id>:<wrapped data key>:<encrypted token>`, both parts as branca
+//! tokens. Anything without the prefix is a token from before encryption and is read as is.
+//! Without a configured key, tokens keep being stored that way.
+
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use crate::config::TokenKeyConfig;
+
+const PREFIX: &str = "enc1";
+
+#[derive(thiserror::Error, Debug)]
+pub enum TokenCryptoError {
+    #[error("token was encrypted with unknown key {0}")]
+    UnknownKey(String),
+    #[error("malformed encrypted token")]
+    Malformed,
+    #[error("could not decrypt token")]
+    Branca(#[from] branca::errors::Error),
+}
+
+pub struct TokenCipher {
+    current: Option<String>,
+    keys: HashMap<String, Vec<u8>>,
+}
+
+struct Sealed<'a> {
+    key_id: &'a str,
+    wrapped_key: &'a str,
+    ciphertext: &'a str,
+}
+
+fn parse(stored: &str) -> Option<Result<Sealed<'_>, TokenCryptoError>> {
+    let rest = stored.strip_prefix(PREFIX)?.strip_prefix(':')?;
+    let mut parts = rest.splitn(3, ':');
+    Some(match (parts.next(), parts.next(), parts.next()) {
+        (Some(key_id), Some(wrapped_key), Some(ciphertext)) => Ok(Sealed {
+            key_id,
+            wrapped_key,
+            ciphertext,
+        }),
+        _ => Err(TokenCryptoError::Malformed),
+    })
+}
+
+impl TokenCipher {
+    /// The first key encrypts, the others are only there to read older rows.
+    pub fn new(keys: &[TokenKeyConfig]) -> TokenCipher {
+        let current = keys.first().map(|key| key.id.clone());
+        let keys = keys
+            .iter()
+            .map(|key| {
+                assert!(
+                    !key.id.is_empty() && !key.id.contains(':'),
+                    "Token encryption key IDs can't be empty or contain ':'"
+                );
+                let secret = hex::decode(&key.key).unwrap();
+                assert_eq!(secret.len(), 32, "Token encryption keys are 32 bytes");
+                (key.id.clone(), secret)
+            })
+            .collect();
+        TokenCipher { current, keys }
+    }
+
+    fn key(&self, key_id: &str) -> Result<&[u8], TokenCryptoError> {
+        self.keys
+            .get(key_id)
+            .map(Vec::as_slice)
+            .ok_or_else(|| TokenCryptoError::UnknownKey(key_id.to_string()))
+    }
+
+    fn wrap(&self, current: &str, data_key: &[u8]) -> String {
+        let wrapped = branca::encode(data_key, &self.keys[current], 0).unwrap();
+        format!("{}:{}:{}", PREFIX, current, wrapped)
+    }
+
+    pub fn encrypt(&self, token: &str) -> String {
+        let current = match &self.current {
+            Some(current) => current,
+            None => return token.to_string(),
+        };
+        let data_key: [u8; 32] = rand::thread_rng().gen();
+        let ciphertext = branca::encode(token.as_bytes(), &data_key, 0).unwrap();
+        format!("{}:{}", self.wrap(current, &data_key), ciphertext)
+    }
+
+    pub fn decrypt(&self, stored: &str) -> Result<String, TokenCryptoError> {
+        let sealed = match parse(stored) {
+            Some(sealed) => sealed?,
+            None => return Ok(stored.to_string()),
+        };
+        let data_key = branca::decode(sealed.wrapped_key, self.key(sealed.key_id)?, 0)?;
+        let token = branca::decode(sealed.ciphertext, &data_key, 0)?;
+        String::from_utf8(token).map_err(|_| TokenCryptoError::Malformed)
+    }
+
+    /// The stored form under the current key, or `None` if it already is. Tokens from before
+    /// encryption get encrypted, older keys only get their data key re-wrapped.
+    pub fn rewrap(&self, stored: &str) -> Result<Option<String>, TokenCryptoError> {
+        let current = match &self.current {
+            Some(current) => current,
+            None => return Ok(None),
+        };
+        let sealed = match parse(stored) {
+            Some(sealed) => sealed?,
+            None => return Ok(Some(self.encrypt(stored))),
+        };
+        if sealed.key_id == current {
+            return Ok(None);
+        }
+        let data_key = branca::decode(sealed.wrapped_key, self.key(sealed.key_id)?, 0)?;
+        Ok(Some(format!(
+            "{}:{}",
+            self.wrap(current, &data_key),
+            sealed.ciphertext
+        )))
+    }
+}
+
+/// Brings every stored token under the current key. Runs at startup, so that switching on
+/// encryption or rotating the key only takes a config change and a restart.
+pub async fn migrate(db: &crate::DB, cipher: &TokenCipher) -> Result<(), sqlx::Error> {
+    let current = match &cipher.current {
+        Some(current) => current,
+        None => {
+            warn!("No app.token_encryption_keys configured, ESI tokens are stored unencrypted");
+            return Ok(());
+        }
+    };
+    let mut updated = 0;
+
+    for row in sqlx::query!("SELECT character_id, access_token FROM access_token")
+        .fetch_all(db)
+        .await?
+    {
+        match cipher.rewrap(&row.access_token) {
+            Ok(Some(access_token)) => {
+                sqlx::query!(
+                    "UPDATE access_token SET access_token=? WHERE character_id=?",
+                    access_token,
+                    row.character_id
+                )
+                .execute(db)
+                .await?;
+                updated += 1;
+            }
+            Ok(None) => (),
+            Err(e) => error!("Access token of {}: {}", row.character_id, e),
+        }
+    }
+
+    for row in sqlx::query!("SELECT character_id, refresh_token FROM refresh_token")
+        .fetch_all(db)
+        .await?
+    {
+        match cipher.rewrap(&row.refresh_token) {
+            Ok(Some(refresh_token)) => {
+                sqlx::query!(
+                    "UPDATE refresh_token SET refresh_token=? WHERE character_id=?",
+                    refresh_token,
+                    row.character_id
+                )
+                .execute(db)
+                .await?;
+                updated += 1;
+            }
+            Ok(None) => (),
+            Err(e) => error!("Refresh token of {}: {}", row.character_id, e),
+        }
+    }
+
+    for row in
+        sqlx::query!("SELECT character_id, access_token, refresh_token FROM srp_service_account")
+            .fetch_all(db)
+            .await?
+    {
+        match (
+            cipher.rewrap(&row.access_token),
+            cipher.rewrap(&row.refresh_token),
+        ) {
+            (Ok(None), Ok(None)) => (),
+            (Ok(access_token), Ok(refresh_token)) => {
+                sqlx::query!(
+                    "UPDATE srp_service_account SET access_token=?, refresh_token=? WHERE character_id=?",
+                    access_token.unwrap_or(row.access_token),
+                    refresh_token.unwrap_or(row.refresh_token),
+                    row.character_id
+                )
+                .execute(db)
+                .await?;
+                updated += 1;
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                error!("SRP service account {}: {}", row.character_id, e)
+            }
+        }
+    }
+
+    if updated > 0 {
+        info!("Encrypted {} stored tokens with key {}", updated, current);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TokenCipher;
+    use crate::config::TokenKeyConfig;
+
+    fn key(id: &str, byte: &str) -> TokenKeyConfig {
+        TokenKeyConfig {
+            id: id.to_string(),
+            key: byte.repeat(32),
+        }
+    }
+
+    #[test]
+    fn test_encrypt_and_rotate() {
+        let old = TokenCipher::new(&[key("1", "01")]);
+        let stored = old.encrypt("eyJ.access.token");
+        assert!(stored.starts_with("enc1:1:"));
+        assert!(!stored.contains("eyJ"));
+        assert_eq!(old.decrypt(&stored).unwrap(), "eyJ.access.token");
+        assert_eq!(old.rewrap(&stored).unwrap(), None);
+
+        // Rows from before encryption
+        assert_eq!(old.decrypt("plain").unwrap(), "plain");
+        let encrypted = old.rewrap("plain").unwrap().unwrap();
+        assert_eq!(old.decrypt(&encrypted).unwrap(), "plain");
+
+        let new = TokenCipher::new(&[key("2", "02"), key("1", "01")]);
+        assert_eq!(new.decrypt(&stored).unwrap(), "eyJ.access.token");
+        let rotated = new.rewrap(&stored).unwrap().unwrap();
+        assert!(rotated.starts_with("enc1:2:"));
+        // Only the data key changed
+        assert_eq!(rotated.rsplit(':').next(), stored.rsplit(':').next());
+        assert_eq!(new.decrypt(&rotated).unwrap(), "eyJ.access.token");
+
+        let only_new = TokenCipher::new(&[key("2", "02")]);
+        assert!(only_new.decrypt(&stored).is_err());
+        assert!(only_new.decrypt("enc1:2:garbage").is_err());
+
+        // No key configured yet
+        let none = TokenCipher::new(&[]);
+        assert_eq!(none.encrypt("plain"), "plain");
+        assert_eq!(none.rewrap("plain").unwrap(), None);
+        assert_eq!(none.decrypt("plain").unwrap(), "plain");
+    }
+}
@@ -1,20 +1,56 @@
+use std::collections::HashSet;
 use std::sync::{Arc, RwLock};
 
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::{data::yamlhelper, util::types::WaitlistCategory};
+use crate::{
+    data::{typeselector::TypeSelector, yamlhelper},
+    util::types::WaitlistCategory,
+};
 use crate::util::madness::Madness;
 
 use eve_data_core::{Fitting, TypeDB, TypeError, TypeID};
 
+#[derive(thiserror::Error, Debug)]
+pub enum CategoryError {
+    #[error("type error: {0}")]
+    TypeError(#[from] TypeError),
+    #[error("invalid doctrine pattern: {0}")]
+    InvalidPattern(#[from] regex::Error),
+    #[error("rule {0} has no conditions")]
+    EmptyRule(usize),
+    #[error("rule {0} uses unknown category '{1}'")]
+    UnknownCategory(usize, String),
+}
+
 struct CategoryData {
     categories: Vec<WaitlistCategory>,
-    rules: Vec<(TypeID, String)>,
+    rules: Vec<Rule>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
     struct CategoryRule {
-        item: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        item: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        hull: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        modules: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        hull_group: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        market_group: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        doctrine: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        alt: Option<bool>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        badge: Option<String>,
+        #[serde(default)]
+        priority: i32,
         category: String,
     }
 
@@ -24,21 +60,216 @@ struct CategoryData {
         rules: Vec<CategoryRule>,
     }
 
+// A rule from categories.yaml with its names resolved. Every condition that is set must match.
+struct Rule {
+    index: usize,
+    priority: i32,
+    description: String,
+    item: Option<TypeID>,
+    hull: Option<TypeID>,
+    modules: Vec<TypeID>,
+    // Hull or any fitted module in the set
+    any_of: Vec<HashSet<TypeID>>,
+    hull_of: Option<HashSet<TypeID>>,
+    doctrine: Option<Regex>,
+    alt: Option<bool>,
+    badge: Option<String>,
+    category: String,
+}
+
+impl Rule {
+    fn matches(&self, input: &CategoryInput) -> bool {
+        if let Some(item) = self.item {
+            if input.fit.hull != item && !input.fit.modules.contains_key(&item) {
+                return false;
+            }
+        }
+        if let Some(hull) = self.hull {
+            if input.fit.hull != hull {
+                return false;
+            }
+        }
+        if !self.modules.iter().all(|id| input.fit.modules.contains_key(id)) {
+            return false;
+        }
+        for types in &self.any_of {
+            if !types.contains(&input.fit.hull)
+                && !input.fit.modules.keys().any(|id| types.contains(id))
+            {
+                return false;
+            }
+        }
+        if let Some(hulls) = &self.hull_of {
+            if !hulls.contains(&input.fit.hull) {
+                return false;
+            }
+        }
+        if let Some(doctrine) = &self.doctrine {
+            match input.doctrine {
+                Some(name) if doctrine.is_match(name) => (),
+                _ => return false,
+            }
+        }
+        if let Some(alt) = self.alt {
+            if input.is_alt != alt {
+                return false;
+            }
+        }
+        if let Some(badge) = &self.badge {
+            if !input.badges.contains(badge) {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Only rules that match on a single type and nothing else
+    fn simple_type(&self) -> Option<TypeID> {
+        if !self.modules.is_empty()
+            || !self.any_of.is_empty()
+            || self.hull_of.is_some()
+            || self.doctrine.is_some()
+            || self.alt.is_some()
+            || self.badge.is_some()
+        {
+            return None;
+        }
+        match (self.item, self.hull) {
+            (Some(id), None) | (None, Some(id)) => Some(id),
+            _ => None,
+        }
+    }
+
+    // The hulls the rule puts in its category, for rules that look at the hull
+    fn hulls(&self) -> Vec<TypeID> {
+        if let Some(hull) = self.hull {
+            return vec![hull];
+        }
+        if let Some(hulls) = &self.hull_of {
+            return hulls.iter().copied().collect();
+        }
+        self.simple_type().into_iter().collect()
+    }
+}
+
+/// Everything a category rule can look at.
+pub struct CategoryInput<'a> {
+    pub fit: &'a Fitting,
+    pub doctrine: Option<&'a str>,
+    pub is_alt: bool,
+    pub badges: &'a [String],
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuleMatch {
+    pub index: usize,
+    pub priority: i32,
+    pub description: String,
+    pub category: String,
+}
+
 lazy_static::lazy_static! {
     static ref CATEGORY_DATA: Arc<RwLock<CategoryData>> = Arc::new(RwLock::new(build_category_data().unwrap()));
 }
 
-fn build_category_data() -> Result<CategoryData, TypeError> {
+fn describe_rule(rule: &CategoryRule) -> String {
+    let mut conditions = Vec::new();
+    if let Some(item) = &rule.item {
+        conditions.push(format!("item={}", item));
+    }
+    if let Some(hull) = &rule.hull {
+        conditions.push(format!("hull={}", hull));
+    }
+    for module in &rule.modules {
+        conditions.push(format!("module={}", module));
+    }
+    if let Some(group) = &rule.group {
+        conditions.push(format!("group={}", group));
+    }
+    if let Some(group) = &rule.hull_group {
+        conditions.push(format!("hull_group={}", group));
+    }
+    if let Some(group) = &rule.market_group {
+        conditions.push(format!("market_group={}", group));
+    }
+    if let Some(doctrine) = &rule.doctrine {
+        conditions.push(format!("doctrine~{}", doctrine));
+    }
+    if let Some(alt) = rule.alt {
+        conditions.push(format!("alt={}", alt));
+    }
+    if let Some(badge) = &rule.badge {
+        conditions.push(format!("badge={}", badge));
+    }
+    conditions.join(" AND ")
+}
+
+fn build_category_data() -> Result<CategoryData, CategoryError> {
     let file: CategoryFile = yamlhelper::from_file("./data/categories.yaml");
 
     let rules = {
         let mut rules = Vec::new();
 
-        for rule in file.rules {
-            let item = TypeDB::id_of(&rule.item)?;
-            rules.push((item, rule.category));
+        for (index, rule) in file.rules.into_iter().enumerate() {
+            let description = describe_rule(&rule);
+            if description.is_empty() {
+                return Err(CategoryError::EmptyRule(index));
+            }
+            if !file.categories.iter().any(|c| c.id == rule.category) {
+                return Err(CategoryError::UnknownCategory(index, rule.category));
+            }
+
+            let item = match &rule.item {
+                Some(name) => Some(TypeDB::id_of(name)?),
+                None => None,
+            };
+            let hull = match &rule.hull {
+                Some(name) => Some(TypeDB::id_of(name)?),
+                None => None,
+            };
+            let mut modules = Vec::new();
+            for name in &rule.modules {
+                modules.push(TypeDB::id_of(name)?);
+            }
+            let mut any_of = Vec::new();
+            if let Some(group) = &rule.group {
+                let selector = TypeSelector::Group { group: group.clone() };
+                any_of.push(selector.resolve()?.into_iter().collect());
+            }
+            if let Some(market_group) = &rule.market_group {
+                let selector = TypeSelector::MarketGroup { market_group: market_group.clone() };
+                any_of.push(selector.resolve()?.into_iter().collect());
+            }
+            let hull_of = match &rule.hull_group {
+                Some(group) => {
+                    let selector = TypeSelector::Group { group: group.clone() };
+                    Some(selector.resolve()?.into_iter().collect())
+                }
+                None => None,
+            };
+            let doctrine = match &rule.doctrine {
+                Some(pattern) => Some(Regex::new(pattern)?),
+                None => None,
+            };
+
+            rules.push(Rule {
+                index,
+                priority: rule.priority,
+                description,
+                item,
+                hull,
+                modules,
+                any_of,
+                hull_of,
+                doctrine,
+                alt: rule.alt,
+                badge: rule.badge,
+                category: rule.category,
+            });
         }
 
+        // Higher priority wins, ties are broken by the order in the file
+        rules.sort_by_key(|rule| (-rule.priority, rule.index));
         rules
     };
     Ok(CategoryData {
@@ -51,24 +282,43 @@ pub fn categories() -> Vec<WaitlistCategory> {
     CATEGORY_DATA.read().unwrap().categories.clone()
 }
 
-pub fn rules() -> Vec<(TypeID, String)> {
-    CATEGORY_DATA.read().unwrap().rules.clone()
+/// Every hull some rule puts in the category, including rules with other conditions.
+pub fn hulls_in(category: &str) -> Vec<TypeID> {
+    let mut hulls: Vec<TypeID> = CATEGORY_DATA
+        .read()
+        .unwrap()
+        .rules
+        .iter()
+        .filter(|rule| rule.category == category)
+        .flat_map(|rule| rule.hulls())
+        .collect();
+    hulls.sort_unstable();
+    hulls.dedup();
+    hulls
 }
 
-pub fn reload_category_data() -> Result<(), TypeError> {
+pub fn reload_category_data() -> Result<(), CategoryError> {
     let new_data = build_category_data()?;
     *CATEGORY_DATA.write().unwrap() = new_data;
     Ok(())
 }
 
-pub fn categorize(fit: &Fitting) -> Option<String> {
+pub fn explain(input: &CategoryInput) -> Option<RuleMatch> {
     let category_data = CATEGORY_DATA.read().unwrap();
-    for (type_id, category) in &category_data.rules {
-        if fit.hull == *type_id || fit.modules.contains_key(type_id) {
-            return Some(category.clone());
-        }
-    }
-    None
+    category_data
+        .rules
+        .iter()
+        .find(|rule| rule.matches(input))
+        .map(|rule| RuleMatch {
+            index: rule.index,
+            priority: rule.priority,
+            description: rule.description.clone(),
+            category: rule.category.clone(),
+        })
+}
+
+pub fn categorize(input: &CategoryInput) -> Option<String> {
+    explain(input).map(|rule| rule.category)
 }
 
 use std::fs;
@@ -155,7 +405,27 @@ pub fn create_backup() -> Result<(), Madness> {
 
 #[cfg(test)]
 mod tests {
-    use super::categories;
+    use super::{categories, CategoryInput, Rule};
+    use eve_data_core::Fitting;
+    use regex::Regex;
+    use std::collections::BTreeMap;
+
+    fn rule(category: &str) -> Rule {
+        Rule {
+            index: 0,
+            priority: 0,
+            description: String::new(),
+            item: None,
+            hull: None,
+            modules: Vec::new(),
+            any_of: Vec::new(),
+            hull_of: None,
+            doctrine: None,
+            alt: None,
+            badge: None,
+            category: category.to_string(),
+        }
+    }
 
     #[test]
     fn test_data_load() {
@@ -164,4 +434,63 @@ mod tests {
         assert!(!cats[0].id.is_empty());
         assert!(!cats[0].name.is_empty());
     }
+
+    #[test]
+    fn test_rule_conditions() {
+        let mut modules = BTreeMap::new();
+        modules.insert(2, 1);
+        let fit = Fitting {
+            hull: 1,
+            modules,
+            cargo: BTreeMap::new(),
+            charges: BTreeMap::new(),
+        };
+        let badges = vec!["LOGI".to_string()];
+        let input = CategoryInput {
+            fit: &fit,
+            doctrine: Some("VINDICATOR ELITE"),
+            is_alt: false,
+            badges: &badges,
+        };
+
+        let mut hull_and_module = rule("dps");
+        hull_and_module.hull = Some(1);
+        hull_and_module.modules = vec![2];
+        assert!(hull_and_module.matches(&input));
+        hull_and_module.modules = vec![2, 3];
+        assert!(!hull_and_module.matches(&input));
+
+        let mut doctrine = rule("dps");
+        doctrine.doctrine = Some(Regex::new("ELITE").unwrap());
+        assert!(doctrine.matches(&input));
+        doctrine.doctrine = Some(Regex::new("STARTER").unwrap());
+        assert!(!doctrine.matches(&input));
+
+        let mut alt = rule("alt");
+        alt.alt = Some(true);
+        assert!(!alt.matches(&input));
+
+        let mut badge = rule("logi");
+        badge.badge = Some("LOGI".to_string());
+        assert!(badge.matches(&input));
+
+        let mut item = rule("bastion");
+        item.item = Some(2);
+        assert!(item.matches(&input));
+        assert_eq!(item.simple_type(), Some(2));
+        assert_eq!(hull_and_module.simple_type(), None);
+
+        let mut group = rule("logi");
+        group.any_of = vec![[2, 5].iter().copied().collect()];
+        assert!(group.matches(&input));
+        group.hull_of = Some([2, 5].iter().copied().collect());
+        assert!(!group.matches(&input));
+        assert_eq!(group.simple_type(), None);
+        let mut hulls = group.hulls();
+        hulls.sort_unstable();
+        assert_eq!(hulls, vec![2, 5]);
+        assert_eq!(hull_and_module.hulls(), vec![1]);
+        assert_eq!(item.hulls(), vec![2]);
+        assert!(doctrine.hulls().is_empty());
+    }
 }
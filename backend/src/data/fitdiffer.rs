@@ -19,6 +19,8 @@ pub struct DiffResult {
     pub module_upgraded: BTreeMap<TypeID, BTreeMap<TypeID, i64>>,
     pub module_downgraded: BTreeMap<TypeID, BTreeMap<TypeID, i64>>,
     pub cargo_missing: BTreeMap<TypeID, i64>,
+    /// Module -> charge -> how many of those modules should have it loaded but don't
+    pub charge_missing: BTreeMap<TypeID, BTreeMap<TypeID, i64>>,
 }
 
 pub struct FitDiffer {}
@@ -102,10 +104,53 @@ impl FitDiffer {
         }
     }
 
+    fn charge_diff(
+        expect: &Fitting,
+        actual: &Fitting,
+        variator: &Variator,
+    ) -> BTreeMap<TypeID, BTreeMap<TypeID, i64>> {
+        // Doctrines only care about loaded charges when they list them, so this only looks
+        // for what's expected and never reports extras
+        let mut available = actual.charges.clone();
+        let mut missing = BTreeMap::new();
+
+        for (&module, charges) in &expect.charges {
+            let or_else = [Variation {
+                from: module,
+                to: module,
+                meta_diff: 0,
+            }];
+
+            for (&charge, &count) in charges {
+                let mut remaining = count;
+                // Any variation of the module counts, as long as it has the right charge in it
+                for variation in variator.get(module).unwrap_or(&or_else) {
+                    if let Some(loaded) = available
+                        .get_mut(&variation.to)
+                        .and_then(|loaded| loaded.get_mut(&charge))
+                    {
+                        let sub = min(remaining, *loaded);
+                        remaining -= sub;
+                        *loaded -= sub;
+                    }
+                }
+                if remaining > 0 {
+                    missing
+                        .entry(module)
+                        .or_insert_with(BTreeMap::new)
+                        .insert(charge, remaining);
+                }
+            }
+        }
+
+        missing
+    }
+
     pub fn diff(expect: &Fitting, actual: &Fitting) -> DiffResult {
         let variator = crate::data::variations::get();
         let variator_guard = variator.read().unwrap();
         let mut modules = Self::section_diff(&expect.modules, &actual.modules, &*variator_guard);
+        let charge_missing = Self::charge_diff(expect, actual, &*variator_guard);
         let cargo_changer = crate::data::variations::drug_handling().unwrap_or(BTreeMap::new());
         let mut mexcargo = expect.cargo.clone();
         let amvariations =
@@ -173,6 +218,7 @@ impl FitDiffer {
             module_downgraded: modules.downgraded,
             module_upgraded: modules.upgraded,
             cargo_missing,
+            charge_missing,
         }
     }
 }
@@ -342,5 +388,52 @@ Sensor Booster II
                 .unwrap(),
             1
         );
+        assert!(diff.charge_missing.is_empty());
+    }
+
+    #[test]
+    fn test_diff_charges() {
+        let expect = Fitting::from_dna("17736:3057;4::")
+            .unwrap()
+            .with_charges("3057;12816;4")
+            .unwrap();
+
+        let actual = Fitting::from_dna("17736:3057;4::").unwrap();
+        let diff = FitDiffer::diff(&expect, &actual);
+        assert!(diff.module_missing.is_empty());
+        assert_eq!(
+            *diff
+                .charge_missing
+                .get(&type_id!("Mega Pulse Laser II"))
+                .unwrap()
+                .get(&type_id!("Conflagration L"))
+                .unwrap(),
+            4
+        );
+
+        let actual = Fitting::from_dna("17736:3057;4::")
+            .unwrap()
+            .with_charges("3057;12816;3")
+            .unwrap();
+        let diff = FitDiffer::diff(&expect, &actual);
+        assert_eq!(
+            *diff
+                .charge_missing
+                .get(&type_id!("Mega Pulse Laser II"))
+                .unwrap()
+                .get(&type_id!("Conflagration L"))
+                .unwrap(),
+            1
+        );
+
+        let actual = Fitting::from_dna("17736:3057;4::")
+            .unwrap()
+            .with_charges("3057;12816;4")
+            .unwrap();
+        assert!(FitDiffer::diff(&expect, &actual).charge_missing.is_empty());
+
+        // Charges the doctrine doesn't ask for are fine
+        let diff = FitDiffer::diff(&Fitting::from_dna("17736:3057;4::").unwrap(), &actual);
+        assert!(diff.charge_missing.is_empty());
     }
 }
@@ -20,12 +20,18 @@ fn load_fits() -> FitData {
     let mut fits = BTreeMap::new();
 
     let fit_data = std::fs::read_to_string("./data/fits.dat").expect("Could not load fits.dat");
-    let fit_regex = Regex::new(r#"<a href="fitting:([0-9:;_]+)">([^<]+)</a>"#).unwrap();
+    // Loaded charges don't fit in DNA, they go in a `charges="module;charge;count:..."` attribute
+    let fit_regex =
+        Regex::new(r#"<a href="fitting:([0-9:;_]+)"(?: charges="([0-9:;]*)")?>([^<]+)</a>"#)
+            .unwrap();
 
     for fit_match in fit_regex.captures_iter(&fit_data) {
         let dna = fit_match.get(1).unwrap().as_str();
-        let fit_name = fit_match.get(2).unwrap().as_str();
-        let parsed = Fitting::from_dna(dna).unwrap();
+        let charges = fit_match.get(2).map(|m| m.as_str()).unwrap_or("");
+        let fit_name = fit_match.get(3).unwrap().as_str();
+        let parsed = Fitting::from_dna(dna)
+            .and_then(|fit| fit.with_charges(charges))
+            .unwrap();
         fits.entry(parsed.hull)
             .or_insert_with(Vec::new)
             .push(DoctrineFit {
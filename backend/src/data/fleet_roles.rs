@@ -0,0 +1,351 @@
+//! Who holds which MOTD role, per fleet session. This is the record the fleet MOTD is written
+//! from, so assignments survive the MOTD being edited by hand and stay behind as history once
+//! the fleet is gone. Pilots added to the MOTD by hand are picked up before it is rewritten,
+//! with a `NULL` `assigned_by`.
+//!
+//! Changes are worked out in `Changes` first, so the MOTD can be written to ESI without a
+//! transaction open, and only stored once that worked.
+
+use std::collections::HashMap;
+
+use super::{motd::Motd, motd_roles::MotdRole};
+
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub role: String,
+    pub character_id: i64,
+    pub name: String,
+}
+
+/// The session of a tracked fleet, if it has one.
+pub async fn session_id(db: &crate::DB, fleet_id: i64) -> Result<Option<i64>, sqlx::Error> {
+    Ok(sqlx::query!(
+        "SELECT id FROM fleet_session WHERE fleet_id=? AND ended_at IS NULL",
+        fleet_id
+    )
+    .fetch_optional(db)
+    .await?
+    .map(|session| session.id))
+}
+
+/// Whether anyone has ever held a role in the session. Until then the MOTD is all there is.
+pub async fn any(db: &crate::DB, session_id: i64) -> Result<bool, sqlx::Error> {
+    Ok(sqlx::query!(
+        "SELECT id FROM fleet_role_assignment WHERE session_id=? LIMIT 1",
+        session_id
+    )
+    .fetch_optional(db)
+    .await?
+    .is_some())
+}
+
+/// The roles held right now, in the order they were handed out.
+pub async fn current(db: &crate::DB, session_id: i64) -> Result<Vec<Assignment>, sqlx::Error> {
+    Ok(sqlx::query!(
+        "
+            SELECT role, character_id, `character`.name
+            FROM fleet_role_assignment JOIN `character` ON character_id=`character`.id
+            WHERE session_id=? AND removed_at IS NULL
+            ORDER BY assigned_at, fleet_role_assignment.id
+        ",
+        session_id
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|row| Assignment {
+        role: row.role,
+        character_id: row.character_id,
+        name: row.name,
+    })
+    .collect())
+}
+
+/// Puts the pilot on the role, unless they already hold it.
+async fn assign(
+    db: &mut crate::DBTX<'_>,
+    session_id: i64,
+    role: &str,
+    character_id: i64,
+    assigned_by: Option<i64>,
+    now: i64,
+) -> Result<(), sqlx::Error> {
+    let existing = sqlx::query!(
+        "SELECT id FROM fleet_role_assignment WHERE session_id=? AND role=? AND character_id=? AND removed_at IS NULL",
+        session_id,
+        role,
+        character_id
+    )
+    .fetch_optional(&mut *db)
+    .await?;
+    if existing.is_some() {
+        return Ok(());
+    }
+
+    sqlx::query!(
+        "INSERT INTO fleet_role_assignment (session_id, role, character_id, assigned_by, assigned_at) VALUES (?, ?, ?, ?, ?)",
+        session_id,
+        role,
+        character_id,
+        assigned_by,
+        now
+    )
+    .execute(&mut *db)
+    .await?;
+
+    Ok(())
+}
+
+/// Changes to who holds which role, made against the assignments as they were loaded.
+pub struct Changes {
+    session_id: i64,
+    now: i64,
+    held: Vec<Assignment>,
+    added: Vec<(Assignment, Option<i64>)>,
+    removed: Vec<(String, i64)>,
+    changed: Vec<String>,
+}
+
+impl Changes {
+    pub async fn load(db: &crate::DB, session_id: i64, now: i64) -> Result<Changes, sqlx::Error> {
+        Ok(Changes {
+            session_id,
+            now,
+            held: current(db, session_id).await?,
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        })
+    }
+
+    /// The roles held once the changes are stored, in the order they were handed out.
+    pub fn assignments(&self) -> &[Assignment] {
+        &self.held
+    }
+
+    fn holds(&self, role: &str, character_id: i64) -> bool {
+        self.held
+            .iter()
+            .any(|held| held.role == role && held.character_id == character_id)
+    }
+
+    fn mark_changed(&mut self, role: &str) {
+        if !self.changed.iter().any(|changed| changed == role) {
+            self.changed.push(role.to_string());
+        }
+    }
+
+    fn add(&mut self, role: &str, character_id: i64, name: &str, assigned_by: Option<i64>) {
+        let assignment = Assignment {
+            role: role.to_string(),
+            character_id,
+            name: name.to_string(),
+        };
+        self.held.push(assignment.clone());
+        self.added.push((assignment, assigned_by));
+    }
+
+    fn remove(&mut self, keep: impl Fn(&Assignment) -> bool) {
+        let (held, removed): (Vec<_>, Vec<_>) = self.held.drain(..).partition(|held| keep(held));
+        self.held = held;
+        for assignment in removed {
+            self.mark_changed(&assignment.role);
+            self.removed
+                .push((assignment.role, assignment.character_id));
+        }
+    }
+
+    /// Puts the pilot on the role. Returns `false` if they already held it. The role's line is
+    /// rewritten either way.
+    pub fn assign(
+        &mut self,
+        role: &str,
+        character_id: i64,
+        name: &str,
+        assigned_by: Option<i64>,
+    ) -> bool {
+        self.mark_changed(role);
+        if self.holds(role, character_id) {
+            return false;
+        }
+        self.add(role, character_id, name, assigned_by);
+        true
+    }
+
+    /// Makes the given pilots the ones on the role. Pilots who keep the role keep their
+    /// assignment, so the time they held it isn't reset.
+    pub fn set(&mut self, role: &str, characters: &[(i64, String)], assigned_by: i64) {
+        self.remove(|held| {
+            held.role != role
+                || characters
+                    .iter()
+                    .any(|(id, _name)| *id == held.character_id)
+        });
+        for (character_id, name) in characters {
+            self.assign(role, *character_id, name, Some(assigned_by));
+        }
+    }
+
+    /// Takes pilots who have left the fleet off their roles.
+    pub fn remove_missing(&mut self, in_fleet: &[i64]) {
+        self.remove(|held| in_fleet.contains(&held.character_id));
+    }
+
+    /// Assigns pilots that were put on a role by editing the MOTD, as long as they are in the
+    /// fleet. `in_fleet` has the names of the fleet's members, to match names typed in by hand.
+    pub fn import(&mut self, motd: &Motd, roles: &[MotdRole], in_fleet: &HashMap<i64, String>) {
+        for role in roles {
+            for assignee in motd.assignments(&role.id) {
+                let found = match assignee.character_id {
+                    Some(id) => in_fleet.get_key_value(&id),
+                    None => {
+                        let name = assignee.name.to_lowercase();
+                        in_fleet
+                            .iter()
+                            .find(|(_id, member)| member.to_lowercase() == name)
+                    }
+                };
+                if let Some((&character_id, name)) = found {
+                    if !self.holds(&role.id, character_id) {
+                        self.add(&role.id, character_id, name, None);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes the pilots of every changed role into the MOTD, replacing whoever it had on them.
+    pub fn project(&self, motd: &mut Motd) {
+        for role in &self.changed {
+            let characters: Vec<(i64, String)> = self
+                .held
+                .iter()
+                .filter(|assignment| &assignment.role == role)
+                .map(|assignment| (assignment.character_id, assignment.name.clone()))
+                .collect();
+            motd.set_assignments(role, &characters);
+        }
+    }
+
+    /// Stores the changes. Assignments that were changed in the meantime are left as they are.
+    pub async fn store(&self, db: &crate::DB) -> Result<(), sqlx::Error> {
+        let mut tx = db.begin().await?;
+        for (role, character_id) in &self.removed {
+            sqlx::query!(
+                "UPDATE fleet_role_assignment SET removed_at=? WHERE session_id=? AND role=? AND character_id=? AND removed_at IS NULL",
+                self.now,
+                self.session_id,
+                role,
+                character_id
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+        for (assignment, assigned_by) in &self.added {
+            assign(
+                &mut tx,
+                self.session_id,
+                &assignment.role,
+                assignment.character_id,
+                *assigned_by,
+                self.now,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Assignment, Changes};
+    use crate::data::{motd::Motd, motd_roles::MotdRole};
+    use std::collections::HashMap;
+
+    fn roles() -> Vec<MotdRole> {
+        ["DDD", "MS"]
+            .iter()
+            .map(|id| MotdRole {
+                id: id.to_string(),
+                name: id.to_string(),
+                hulls: Vec::new(),
+            })
+            .collect()
+    }
+
+    fn changes(held: &[(&str, i64, &str)]) -> Changes {
+        Changes {
+            session_id: 1,
+            now: 0,
+            held: held
+                .iter()
+                .map(|&(role, character_id, name)| Assignment {
+                    role: role.to_string(),
+                    character_id,
+                    name: name.to_string(),
+                })
+                .collect(),
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_changes() {
+        let mut changes = changes(&[("DDD", 1, "One"), ("DDD", 2, "Two"), ("MS", 2, "Two")]);
+        changes.remove_missing(&[1, 3]);
+        assert_eq!(
+            changes.removed,
+            vec![("DDD".to_string(), 2), ("MS".to_string(), 2)]
+        );
+        assert_eq!(changes.changed, vec!["DDD".to_string(), "MS".to_string()]);
+
+        assert!(changes.assign("MS", 3, "Three", Some(1)));
+        assert!(!changes.assign("MS", 3, "Three", Some(1)));
+        assert_eq!(changes.added.len(), 1);
+
+        changes.set("DDD", &[(3, "Three".to_string())], 1);
+        let held: Vec<_> = changes
+            .assignments()
+            .iter()
+            .map(|a| (a.role.as_str(), a.character_id))
+            .collect();
+        assert_eq!(held, vec![("MS", 3), ("DDD", 3)]);
+    }
+
+    #[test]
+    fn test_import_and_project() {
+        let roles = roles();
+        let motd_text = "DDD: Typed Two, Left Fleet<br>MS: <a href=\"showinfo:1377//1\">One</a>";
+        let mut motd = Motd::parse(motd_text, &roles);
+        let in_fleet: HashMap<i64, String> =
+            vec![(1, "One".to_string()), (2, "typed two".to_string())]
+                .into_iter()
+                .collect();
+
+        let mut changes = changes(&[]);
+        changes.import(&motd, &roles, &in_fleet);
+        // Picked up as they are, so there is nothing to rewrite yet
+        let added: Vec<_> = changes
+            .added
+            .iter()
+            .map(|(a, assigned_by)| (a.role.as_str(), a.character_id, *assigned_by))
+            .collect();
+        assert_eq!(added, vec![("DDD", 2, None), ("MS", 1, None)]);
+        changes.project(&mut motd);
+        assert_eq!(motd.render(), motd_text);
+
+        changes.assign("MS", 2, "typed two", Some(1));
+        changes.project(&mut motd);
+        let ms: Vec<_> = motd
+            .assignments("MS")
+            .into_iter()
+            .map(|a| a.character_id)
+            .collect();
+        assert_eq!(ms, vec![Some(1), Some(2)]);
+        assert_eq!(motd.assignments("DDD").len(), 2);
+    }
+}
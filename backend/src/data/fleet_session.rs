@@ -0,0 +1,151 @@
+//! A `fleet` row only lives as long as the fleet is tracked. A `fleet_session` is the record
+//! of that fleet that stays behind: when it ran, who had boss, which waitlist fed it and how
+//! big it got. Member data stays in `fleet_activity`, matched on fleet ID and time.
+
+/// Opens a session for the fleet, or hands the open one to a new boss.
+pub async fn start(
+    db: &mut crate::DBTX<'_>,
+    fleet_id: i64,
+    boss_id: i64,
+    now: i64,
+) -> Result<i64, sqlx::Error> {
+    if let Some(session) = sqlx::query!(
+        "SELECT id FROM fleet_session WHERE fleet_id=? AND ended_at IS NULL",
+        fleet_id
+    )
+    .fetch_optional(&mut *db)
+    .await?
+    {
+        set_boss(db, session.id, boss_id, now).await?;
+        return Ok(session.id);
+    }
+
+    let focus = sqlx::query!(
+        "SELECT current_focus_constellation_id, current_focus_constellation_name FROM incursion_focus WHERE focus_active=1 ORDER BY id DESC LIMIT 1"
+    )
+    .fetch_optional(&mut *db)
+    .await?;
+    let (constellation_id, constellation_name) = match focus {
+        Some(focus) => (
+            focus.current_focus_constellation_id,
+            focus.current_focus_constellation_name,
+        ),
+        None => (None, None),
+    };
+
+    let result = sqlx::query!(
+        "INSERT INTO fleet_session (fleet_id, started_at, peak_size, constellation_id, constellation_name) VALUES (?, ?, 0, ?, ?)",
+        fleet_id,
+        now,
+        constellation_id,
+        constellation_name
+    )
+    .execute(&mut *db)
+    .await?;
+    let session_id = crate::last_insert_id!(result);
+    set_boss(db, session_id, boss_id, now).await?;
+
+    Ok(session_id)
+}
+
+async fn set_boss(
+    db: &mut crate::DBTX<'_>,
+    session_id: i64,
+    boss_id: i64,
+    now: i64,
+) -> Result<(), sqlx::Error> {
+    let current = sqlx::query!(
+        "SELECT character_id FROM fleet_session_boss WHERE session_id=? AND ended_at IS NULL",
+        session_id
+    )
+    .fetch_optional(&mut *db)
+    .await?;
+    if current.map(|current| current.character_id) == Some(boss_id) {
+        return Ok(());
+    }
+
+    sqlx::query!(
+        "UPDATE fleet_session_boss SET ended_at=? WHERE session_id=? AND ended_at IS NULL",
+        now,
+        session_id
+    )
+    .execute(&mut *db)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO fleet_session_boss (session_id, character_id, started_at) VALUES (?, ?, ?)",
+        session_id,
+        boss_id,
+        now
+    )
+    .execute(&mut *db)
+    .await?;
+
+    Ok(())
+}
+
+/// Closes the fleet's session, for when the fleet stops being tracked.
+pub async fn end(db: &mut crate::DBTX<'_>, fleet_id: i64, now: i64) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE fleet_session_boss SET ended_at=? WHERE ended_at IS NULL AND session_id IN (SELECT id FROM fleet_session WHERE fleet_id=? AND ended_at IS NULL)",
+        now,
+        fleet_id
+    )
+    .execute(&mut *db)
+    .await?;
+    sqlx::query!(
+        "UPDATE fleet_role_assignment SET removed_at=? WHERE removed_at IS NULL AND session_id IN (SELECT id FROM fleet_session WHERE fleet_id=? AND ended_at IS NULL)",
+        now,
+        fleet_id
+    )
+    .execute(&mut *db)
+    .await?;
+    sqlx::query!(
+        "UPDATE fleet_session SET ended_at=? WHERE fleet_id=? AND ended_at IS NULL",
+        now,
+        fleet_id
+    )
+    .execute(&mut *db)
+    .await?;
+
+    Ok(())
+}
+
+/// Keeps the session in step with what the fleet updater sees. Fleets registered before
+/// sessions existed get one here. Returns the session's ID.
+pub async fn update(
+    db: &mut crate::DBTX<'_>,
+    fleet_id: i64,
+    boss_id: i64,
+    size: usize,
+    now: i64,
+) -> Result<i64, sqlx::Error> {
+    let session_id = start(db, fleet_id, boss_id, now).await?;
+    let size = size as i32;
+    sqlx::query!(
+        "UPDATE fleet_session SET peak_size=? WHERE id=? AND peak_size < ?",
+        size,
+        session_id,
+        size
+    )
+    .execute(&mut *db)
+    .await?;
+
+    Ok(session_id)
+}
+
+/// Remembers the waitlist the fleet was first invited from.
+pub async fn record_waitlist(
+    db: &crate::DB,
+    fleet_id: i64,
+    waitlist_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE fleet_session SET waitlist_id=? WHERE fleet_id=? AND ended_at IS NULL AND waitlist_id IS NULL",
+        waitlist_id,
+        fleet_id
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
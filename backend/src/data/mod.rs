@@ -2,8 +2,12 @@ pub mod categories;
 pub mod guide_assets;
 pub mod guides;
 pub mod locales;
+pub mod motd;
+pub mod motd_roles;
 pub mod character;
 pub mod fitdiffer;
+pub mod fleet_roles;
+pub mod fleet_session;
 pub mod fits;
 pub mod incursion;
 pub mod implants;
@@ -12,6 +16,7 @@ pub mod skills;
 pub mod srp;
 pub mod srp_notify;
 pub mod tags;
+pub mod typeselector;
 pub mod variations;
 pub mod yamlhelper;
 
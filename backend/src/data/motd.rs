@@ -0,0 +1,592 @@
+//! A fleet MOTD is EVE's rich text: plain text mixed with `<font>`, `<b>`, `<br>`, `<loc>` and
+//! `<a href="showinfo:…">` tags. `Motd` keeps it as a list of nodes that renders back to
+//! exactly what was parsed, and knows where the role lines (`DDD: <a …>Pilot</a>`) are, so
+//! that the pilots on them can be read and replaced without touching anything else.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use regex::Regex;
+use serde::Serialize;
+
+use super::motd_roles::MotdRole;
+
+// Every race has its own character type, the client doesn't care which one a link uses
+const CHARACTER_TYPES: Range<i64> = 1373..1387;
+const DEFAULT_CHARACTER_TYPE: i64 = 1377;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    Text(String),
+    Open {
+        tag: String,
+        attributes: Vec<(String, String)>,
+    },
+    Close {
+        tag: String,
+    },
+    /// A tag that wouldn't render back the way it was written, kept as is
+    Raw(String),
+    /// The `ROLE:` a role's pilots follow
+    Label {
+        role: String,
+        text: String,
+    },
+}
+
+impl Node {
+    fn render(&self, out: &mut String) {
+        match self {
+            Node::Text(text) | Node::Raw(text) | Node::Label { text, .. } => out.push_str(text),
+            Node::Open { tag, attributes } => {
+                out.push('<');
+                out.push_str(tag);
+                for (name, value) in attributes {
+                    out.push_str(&format!(" {}=\"{}\"", name, value));
+                }
+                out.push('>');
+            }
+            Node::Close { tag } => {
+                out.push_str("</");
+                out.push_str(tag);
+                out.push('>');
+            }
+        }
+    }
+
+    fn is_line_break(&self) -> bool {
+        match self {
+            Node::Open { tag, .. } => tag.eq_ignore_ascii_case("br"),
+            Node::Text(text) => text == "\n",
+            _ => false,
+        }
+    }
+
+    fn is_blank(&self) -> bool {
+        match self {
+            Node::Text(text) => text.trim().is_empty(),
+            _ => false,
+        }
+    }
+
+    /// The character a `showinfo` link points at.
+    fn character_link(&self) -> Option<i64> {
+        let href = match self {
+            Node::Open { tag, attributes } if tag.eq_ignore_ascii_case("a") => attributes
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("href"))
+                .map(|(_, value)| value)?,
+            _ => return None,
+        };
+        let (type_id, id) = href.strip_prefix("showinfo:")?.split_once("//")?;
+        if !CHARACTER_TYPES.contains(&type_id.parse::<i64>().ok()?) {
+            return None;
+        }
+        id.parse().ok()
+    }
+}
+
+fn parse_tag(raw: &str) -> Node {
+    lazy_static::lazy_static! {
+        static ref TAG: Regex = Regex::new(r#"^<(/?)([A-Za-z]+)((?: [A-Za-z-]+="[^"]*")*)>$"#).unwrap();
+        static ref ATTRIBUTE: Regex = Regex::new(r#" ([A-Za-z-]+)="([^"]*)""#).unwrap();
+    }
+
+    let node = match TAG.captures(raw) {
+        Some(captures) if captures[1].is_empty() => Node::Open {
+            tag: captures[2].to_string(),
+            attributes: ATTRIBUTE
+                .captures_iter(&captures[3])
+                .map(|attribute| (attribute[1].to_string(), attribute[2].to_string()))
+                .collect(),
+        },
+        Some(captures) if captures[3].is_empty() => Node::Close {
+            tag: captures[2].to_string(),
+        },
+        _ => return Node::Raw(raw.to_string()),
+    };
+
+    let mut rendered = String::new();
+    node.render(&mut rendered);
+    if rendered == raw {
+        node
+    } else {
+        Node::Raw(raw.to_string())
+    }
+}
+
+// Newlines get a node of their own, they end role lines just like `<br>`
+fn push_text(nodes: &mut Vec<Node>, text: &str) {
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            nodes.push(Node::Text("\n".to_string()));
+        }
+        if !line.is_empty() {
+            nodes.push(Node::Text(line.to_string()));
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end + 1,
+            None => break,
+        };
+        push_text(&mut nodes, &rest[..start]);
+        nodes.push(parse_tag(&rest[start..end]));
+        rest = &rest[end..];
+    }
+    push_text(&mut nodes, rest);
+    nodes
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Assignee {
+    pub name: String,
+    /// `None` for names typed in without a link
+    pub character_id: Option<i64>,
+}
+
+struct Section {
+    label: usize,
+    /// Nodes from the first pilot to the last one, `None` if there aren't any
+    names: Option<Range<usize>>,
+    /// Linked pilots and the nodes of their link
+    links: Vec<(Assignee, Range<usize>)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Motd {
+    nodes: Vec<Node>,
+}
+
+impl Motd {
+    /// Parses the MOTD, recognizing the first `ROLE:` of each role as where its pilots are.
+    pub fn parse(text: &str, roles: &[MotdRole]) -> Motd {
+        let mut nodes = tokenize(text);
+        if roles.is_empty() {
+            return Motd { nodes };
+        }
+
+        let pattern = roles
+            .iter()
+            .map(|role| regex::escape(&role.id))
+            .collect::<Vec<_>>()
+            .join("|");
+        let label = Regex::new(&format!(r"(?i)\b({}):", pattern)).unwrap();
+
+        let mut seen = Vec::new();
+        let mut i = 0;
+        while i < nodes.len() {
+            let text = match &nodes[i] {
+                Node::Text(text) => text.clone(),
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let found = label.captures_iter(&text).find_map(|captures| {
+                let role = roles
+                    .iter()
+                    .find(|role| role.id.eq_ignore_ascii_case(&captures[1]))?;
+                if seen.contains(&role.id) {
+                    return None;
+                }
+                Some((role.id.clone(), captures.get(0).unwrap().range()))
+            });
+            let (role, range) = match found {
+                Some(found) => found,
+                None => {
+                    i += 1;
+                    continue;
+                }
+            };
+
+            let mut replacement = Vec::new();
+            if range.start > 0 {
+                replacement.push(Node::Text(text[..range.start].to_string()));
+            }
+            replacement.push(Node::Label {
+                role: role.clone(),
+                text: text[range.clone()].to_string(),
+            });
+            if range.end < text.len() {
+                replacement.push(Node::Text(text[range.end..].to_string()));
+            }
+            seen.push(role);
+            let count = replacement.len();
+            nodes.splice(i..=i, replacement);
+            // Text after the label is looked at again, it can hold more labels
+            i += match range.end < text.len() {
+                true => count - 1,
+                false => count,
+            };
+        }
+
+        Motd { nodes }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for node in &self.nodes {
+            node.render(&mut out);
+        }
+        out
+    }
+
+    fn label(&self, role: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| match node {
+            Node::Label { role: label, .. } => label == role,
+            _ => false,
+        })
+    }
+
+    /// A role's pilots follow its label up to the end of the line, or the next label.
+    fn section(&self, role: &str) -> Option<Section> {
+        let label = self.label(role)?;
+        let mut text: Option<Range<usize>> = None;
+        let mut links = Vec::new();
+        let mut link: Option<usize> = None;
+
+        for i in label + 1..self.nodes.len() {
+            let node = &self.nodes[i];
+            if matches!(node, Node::Label { .. }) || node.is_line_break() {
+                break;
+            }
+
+            if let Some(start) = link {
+                if matches!(node, Node::Close { tag } if tag.eq_ignore_ascii_case("a")) {
+                    let name: String = self.nodes[start + 1..i]
+                        .iter()
+                        .filter_map(|node| match node {
+                            Node::Text(text) => Some(text.as_str()),
+                            _ => None,
+                        })
+                        .collect();
+                    let assignee = Assignee {
+                        name: unescape(name.trim()),
+                        character_id: self.nodes[start].character_link(),
+                    };
+                    links.push((assignee, start..i + 1));
+                    link = None;
+                }
+            } else if node.character_link().is_some() {
+                link = Some(i);
+            } else if matches!(node, Node::Text(_)) && !node.is_blank() {
+                text = Some(text.map_or(i, |text| text.start)..i + 1);
+            }
+        }
+
+        // With links, text around them is the FC's and stays
+        let names = match (links.first(), links.last()) {
+            (Some((_, first)), Some((_, last))) => Some(first.start..last.end),
+            _ => text,
+        };
+        Some(Section {
+            label,
+            names,
+            links,
+        })
+    }
+
+    /// The pilots on a role, empty if the MOTD doesn't have the role.
+    pub fn assignments(&self, role: &str) -> Vec<Assignee> {
+        let section = match self.section(role) {
+            Some(section) => section,
+            None => return Vec::new(),
+        };
+        if !section.links.is_empty() {
+            return section
+                .links
+                .into_iter()
+                .map(|(assignee, _)| assignee)
+                .collect();
+        }
+
+        // Names typed in by hand
+        let text: String = match section.names {
+            Some(names) => self.nodes[names]
+                .iter()
+                .filter_map(|node| match node {
+                    Node::Text(text) => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            None => return Vec::new(),
+        };
+        text.split(&[',', ';', '|', '/'][..])
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| Assignee {
+                name: unescape(name),
+                character_id: None,
+            })
+            .collect()
+    }
+
+    /// The pilots on every role that is in the MOTD.
+    pub fn all_assignments(&self) -> BTreeMap<String, Vec<Assignee>> {
+        self.nodes
+            .iter()
+            .filter_map(|node| match node {
+                Node::Label { role, .. } => Some((role.clone(), self.assignments(role))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Replaces the pilots on a role. Links of pilots that stay are kept as they were, new
+    /// ones are separated the way the existing ones were. A role that isn't in the MOTD yet
+    /// gets a line at the end.
+    pub fn set_assignments(&mut self, role: &str, characters: &[(i64, String)]) {
+        let section = match self.section(role) {
+            Some(section) => section,
+            None => {
+                if characters.is_empty() {
+                    return;
+                }
+                self.nodes.push(Node::Open {
+                    tag: "br".to_string(),
+                    attributes: Vec::new(),
+                });
+                self.nodes.push(Node::Label {
+                    role: role.to_string(),
+                    text: format!("{}:", role),
+                });
+                self.nodes.push(Node::Text(" ".to_string()));
+                let at = self.nodes.len();
+                let section = Section {
+                    label: at - 2,
+                    names: None,
+                    links: Vec::new(),
+                };
+                let replacement = self.links(&section, characters);
+                self.nodes.splice(at..at, replacement);
+                return;
+            }
+        };
+
+        let replacement = self.links(&section, characters);
+        match section.names.clone() {
+            Some(names) => {
+                // Keep the whitespace around hand typed names
+                let mut replacement = replacement;
+                if let Node::Text(first) = &self.nodes[names.start] {
+                    let leading = &first[..first.len() - first.trim_start().len()];
+                    if !leading.is_empty() {
+                        replacement.insert(0, Node::Text(leading.to_string()));
+                    }
+                }
+                if let Node::Text(last) = &self.nodes[names.end - 1] {
+                    let trailing = &last[last.trim_end().len()..];
+                    if !trailing.is_empty() {
+                        replacement.push(Node::Text(trailing.to_string()));
+                    }
+                }
+                self.nodes.splice(names, replacement);
+            }
+            None => {
+                if characters.is_empty() {
+                    return;
+                }
+                let mut at = section.label + 1;
+                if matches!(self.nodes.get(at), Some(node) if node.is_blank())
+                    && !self.nodes[at].is_line_break()
+                {
+                    at += 1;
+                } else {
+                    self.nodes.insert(at, Node::Text(" ".to_string()));
+                    at += 1;
+                }
+                self.nodes.splice(at..at, replacement);
+            }
+        }
+    }
+
+    fn links(&self, section: &Section, characters: &[(i64, String)]) -> Vec<Node> {
+        let separator = match section.links.as_slice() {
+            [(_, first), (_, second), ..] => self.nodes[first.end..second.start].to_vec(),
+            _ => vec![Node::Text(" ".to_string())],
+        };
+
+        let mut nodes = Vec::new();
+        for (i, (id, name)) in characters.iter().enumerate() {
+            if i > 0 {
+                nodes.extend(separator.iter().cloned());
+            }
+            match section
+                .links
+                .iter()
+                .find(|(assignee, _)| assignee.character_id == Some(*id))
+            {
+                Some((_, link)) => nodes.extend(self.nodes[link.clone()].iter().cloned()),
+                None => {
+                    nodes.push(Node::Open {
+                        tag: "a".to_string(),
+                        attributes: vec![(
+                            "href".to_string(),
+                            format!("showinfo:{}//{}", DEFAULT_CHARACTER_TYPE, id),
+                        )],
+                    });
+                    nodes.push(Node::Text(escape(name)));
+                    nodes.push(Node::Close {
+                        tag: "a".to_string(),
+                    });
+                }
+            }
+        }
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Assignee, Motd, Node};
+    use crate::data::motd_roles::MotdRole;
+
+    fn roles() -> Vec<MotdRole> {
+        ["DDD", "MS", "LR", "MTAC", "PS"]
+            .iter()
+            .map(|id| MotdRole {
+                id: id.to_string(),
+                name: id.to_string(),
+                hulls: Vec::new(),
+            })
+            .collect()
+    }
+
+    // As the client saves them
+    const CLIENT_MOTD: &str = concat!(
+        r##"<font size="14" color="#bfffffff">Welcome to </font><font size="14" color="#ffd98d00"><a href="joinChannel:-69961116//None//None">TLA Incursions</a></font>"##,
+        r##"<font size="14" color="#bfffffff"><br>FC: </font><font size="14" color="#ff00a99d"><a href="showinfo:1375//2117000001">Mock FC</a></font>"##,
+        r##"<font size="14" color="#bfffffff"><br>DDD: </font><font size="14" color="#ffd98d00"><a href="showinfo:1377//2117000002">Vindi Pilot</a></font>"##,
+        r##"<font size="14" color="#bfffffff">, </font><font size="14" color="#ffd98d00"><a href="showinfo:1383//2117000003">Second Vindi</a></font>"##,
+        r##"<font size="14" color="#bfffffff"><br>MS: <br>LR: </font><font size="14" color="#ffd98d00"><a href="showinfo:1373//2117000004">Kronos Pilot</a></font>"##,
+        r##"<font size="14" color="#bfffffff"><br>MTAC: Typed Name, Other Pilot<br>PS:<br><br>Doctrine: </font>"##,
+        r##"<font size="14" color="#ffffe400"><loc><a href="https://example.com/fits">fits</a></loc></font>"##,
+    );
+
+    // Pasted together by hand
+    const HAND_MOTD: &str = "Fleet rules apply\nddd: <a href=\"showinfo:1377//1\">A &amp; B</a> <a href='showinfo:1377//2'>Quoted</a>\nps: Pally One / Pally Two\n<b>No MAPS: here</b><br/>";
+
+    #[test]
+    fn test_round_trip() {
+        for motd in [CLIENT_MOTD, HAND_MOTD, "", "a < b", "<unclosed", "DDD:"] {
+            assert_eq!(Motd::parse(motd, &roles()).render(), motd);
+        }
+
+        let parsed = Motd::parse(HAND_MOTD, &roles());
+        // Single quotes and self-closing tags don't render the same, they stay as written
+        assert!(parsed
+            .nodes
+            .contains(&Node::Raw("<a href='showinfo:1377//2'>".to_string())));
+        assert!(parsed.nodes.contains(&Node::Raw("<br/>".to_string())));
+    }
+
+    #[test]
+    fn test_assignments() {
+        let motd = Motd::parse(CLIENT_MOTD, &roles());
+        let assignments = motd.all_assignments();
+        assert_eq!(
+            assignments["DDD"],
+            vec![
+                Assignee {
+                    name: "Vindi Pilot".to_string(),
+                    character_id: Some(2117000002)
+                },
+                Assignee {
+                    name: "Second Vindi".to_string(),
+                    character_id: Some(2117000003)
+                },
+            ]
+        );
+        assert!(assignments["MS"].is_empty());
+        assert_eq!(assignments["LR"][0].character_id, Some(2117000004));
+        let mtac: Vec<_> = assignments["MTAC"]
+            .iter()
+            .map(|a| a.name.as_str())
+            .collect();
+        assert_eq!(mtac, vec!["Typed Name", "Other Pilot"]);
+        assert!(assignments["PS"].is_empty());
+
+        let motd = Motd::parse(HAND_MOTD, &roles());
+        // The second link isn't one the parser understands, and "MAPS:" isn't "PS:"
+        let ddd = motd.assignments("DDD");
+        assert_eq!(ddd.len(), 1);
+        assert_eq!(ddd[0].name, "A & B");
+        let ps: Vec<_> = motd.assignments("PS").into_iter().map(|a| a.name).collect();
+        assert_eq!(ps, vec!["Pally One", "Pally Two"]);
+        assert!(motd.assignments("LR").is_empty());
+    }
+
+    #[test]
+    fn test_set_assignments() {
+        let mut motd = Motd::parse(CLIENT_MOTD, &roles());
+
+        // Keeps the remaining link as it was, adds the new one with the same separator
+        motd.set_assignments(
+            "DDD",
+            &[
+                (2117000003, "Second Vindi".to_string()),
+                (2117000009, "New <Vindi>".to_string()),
+            ],
+        );
+        let rendered = motd.render();
+        assert!(rendered.contains(concat!(
+            r##"DDD: </font><font size="14" color="#ffd98d00"><a href="showinfo:1383//2117000003">Second Vindi</a></font>"##,
+            r##"<font size="14" color="#bfffffff">, </font><font size="14" color="#ffd98d00"><a href="showinfo:1377//2117000009">New &lt;Vindi&gt;</a></font>"##,
+        )));
+        assert!(!rendered.contains("Vindi Pilot"));
+
+        // Empty role on a line of its own
+        motd.set_assignments("MS", &[(5, "Boosh".to_string())]);
+        // Typed names become links
+        motd.set_assignments("MTAC", &[(6, "Tractor".to_string())]);
+        motd.set_assignments("LR", &[]);
+        // Not in the MOTD yet
+        let mut roles = roles();
+        roles.push(MotdRole {
+            id: "SCOUT".to_string(),
+            name: "Scout".to_string(),
+            hulls: Vec::new(),
+        });
+        motd.set_assignments("SCOUT", &[(7, "Eyes".to_string())]);
+
+        let rendered = motd.render();
+        assert!(rendered.contains(r##"<br>MS: <a href="showinfo:1377//5">Boosh</a><br>LR: </font><font size="14" color="#ffd98d00"></font><font size="14" color="#bfffffff"><br>MTAC: <a href="showinfo:1377//6">Tractor</a><br>PS:"##));
+        assert!(
+            rendered.ends_with(r##"</loc></font><br>SCOUT: <a href="showinfo:1377//7">Eyes</a>"##)
+        );
+
+        // What was written reads back the same
+        let motd = Motd::parse(&rendered, &roles);
+        assert_eq!(motd.assignments("MS")[0].character_id, Some(5));
+        assert!(motd.assignments("LR").is_empty());
+        assert_eq!(motd.assignments("MTAC")[0].name, "Tractor");
+        assert_eq!(motd.assignments("SCOUT")[0].character_id, Some(7));
+        let ddd: Vec<_> = motd
+            .assignments("DDD")
+            .into_iter()
+            .map(|a| a.name)
+            .collect();
+        assert_eq!(ddd, vec!["Second Vindi", "New <Vindi>"]);
+    }
+}
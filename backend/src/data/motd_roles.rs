@@ -0,0 +1,147 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{data::yamlhelper, util::madness::Madness};
+
+use eve_data_core::{TypeDB, TypeError, TypeID};
+
+const ROLES_FILE: &str = "./data/motd_roles.yaml";
+
+// The roles the MOTD had before they could be configured, for setups without the file
+const DEFAULT_ROLES: &str = include_str!("../../data/motd_roles.yaml.example");
+
+#[derive(thiserror::Error, Debug)]
+pub enum MotdRoleError {
+    #[error("invalid YAML: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("type error: {0}")]
+    TypeError(#[from] TypeError),
+    #[error("role '{0}' is defined more than once")]
+    Duplicate(String),
+    #[error("role IDs can only contain letters and digits, '{0}' doesn't")]
+    InvalidId(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct RoleEntry {
+    id: String,
+    name: String,
+    #[serde(default)]
+    hulls: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RoleFile {
+    roles: Vec<RoleEntry>,
+}
+
+/// A role the FC hands out through the MOTD, written there as `ID: <pilots>`.
+#[derive(Debug, Clone, Serialize)]
+pub struct MotdRole {
+    pub id: String,
+    pub name: String,
+    /// Hulls the role is flown in, empty if any will do
+    pub hulls: Vec<TypeID>,
+}
+
+impl MotdRole {
+    pub fn allows_hull(&self, hull: TypeID) -> bool {
+        self.hulls.is_empty() || self.hulls.contains(&hull)
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref MOTD_ROLES: Arc<RwLock<Vec<MotdRole>>> = Arc::new(RwLock::new(build_roles().unwrap()));
+}
+
+fn parse_roles(file: RoleFile) -> Result<Vec<MotdRole>, MotdRoleError> {
+    let mut roles: Vec<MotdRole> = Vec::new();
+    for entry in file.roles {
+        if entry.id.is_empty() || !entry.id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(MotdRoleError::InvalidId(entry.id));
+        }
+        if roles
+            .iter()
+            .any(|role| role.id.eq_ignore_ascii_case(&entry.id))
+        {
+            return Err(MotdRoleError::Duplicate(entry.id));
+        }
+        let mut hulls = Vec::new();
+        for name in &entry.hulls {
+            hulls.push(TypeDB::id_of(name)?);
+        }
+        roles.push(MotdRole {
+            id: entry.id,
+            name: entry.name,
+            hulls,
+        });
+    }
+    Ok(roles)
+}
+
+fn build_roles() -> Result<Vec<MotdRole>, MotdRoleError> {
+    let file: RoleFile = match Path::new(ROLES_FILE).exists() {
+        true => yamlhelper::from_file(ROLES_FILE),
+        false => {
+            warn!("{} not found, using the default MOTD roles", ROLES_FILE);
+            serde_yaml::from_str(DEFAULT_ROLES)?
+        }
+    };
+    parse_roles(file)
+}
+
+/// Loads the roles, so that a bad motd_roles.yaml fails at startup.
+pub fn check_roles() {
+    let _roles = MOTD_ROLES.read().unwrap();
+}
+
+/// Roles in the order they are shown.
+pub fn roles() -> Vec<MotdRole> {
+    MOTD_ROLES.read().unwrap().clone()
+}
+
+pub fn get(id: &str) -> Option<MotdRole> {
+    MOTD_ROLES
+        .read()
+        .unwrap()
+        .iter()
+        .find(|role| role.id.eq_ignore_ascii_case(id))
+        .cloned()
+}
+
+pub fn reload_roles() -> Result<(), Madness> {
+    let roles = build_roles()
+        .map_err(|e| Madness::BadRequest(format!("Failed to reload MOTD roles: {}", e)))?;
+    *MOTD_ROLES.write().unwrap() = roles;
+    Ok(())
+}
+
+pub fn save_roles_to_file(yaml_content: &str) -> Result<(), Madness> {
+    let file: RoleFile = serde_yaml::from_str(yaml_content)
+        .map_err(|e| Madness::BadRequest(format!("Invalid YAML: {}", e)))?;
+    parse_roles(file).map_err(|e| Madness::BadRequest(format!("Invalid MOTD roles: {}", e)))?;
+
+    if Path::new(ROLES_FILE).exists() {
+        let timestamp = chrono::Utc::now().timestamp();
+        fs::copy(ROLES_FILE, format!("{}.backup.{}", ROLES_FILE, timestamp))
+            .map_err(|e| Madness::BadRequest(format!("Failed to create backup: {}", e)))?;
+    }
+
+    let temp_path = format!("{}.tmp", ROLES_FILE);
+    let mut temp_file = fs::File::create(&temp_path)
+        .map_err(|e| Madness::BadRequest(format!("Failed to create temp file: {}", e)))?;
+    temp_file
+        .write_all(yaml_content.as_bytes())
+        .map_err(|e| Madness::BadRequest(format!("Failed to write temp file: {}", e)))?;
+    temp_file
+        .sync_all()
+        .map_err(|e| Madness::BadRequest(format!("Failed to sync temp file: {}", e)))?;
+    fs::rename(&temp_path, ROLES_FILE)
+        .map_err(|e| Madness::BadRequest(format!("Failed to rename temp file: {}", e)))?;
+
+    Ok(())
+}
@@ -42,7 +42,7 @@ pub async fn load_skills(
     character_id: i64,
 ) -> Result<Skills, SkillsError> {
     let skills: SkillResponse = esi_client
-        .get(
+        .get_cached(
             &format!("/v4/characters/{}/skills/", character_id),
             character_id,
             ESIScope::Skills_ReadSkills_v1,
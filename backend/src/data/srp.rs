@@ -1,4 +1,4 @@
-use crate::core::esi::{self, ESIScope, KillmailData};
+use crate::core::esi::{self, ESIError, ESIScope, KillmailData};
 use crate::util::madness::Madness;
 use serde::{Deserialize, Serialize};
 use sqlx::Row;
@@ -121,63 +121,14 @@ pub async fn fetch_corporation_wallet_journal(
     corporation_id: i64,
     wallet_id: i32,
 ) -> Result<Vec<WalletJournalEntry>, Madness> {
-            // println!("Fetching wallet journal for corp {} wallet {} using character {}", corporation_id, wallet_id, character_id);
-    
-    // ETAG DISABLED: Get the last ETag from the database
-    // let last_etag = sqlx::query!(
-    //     "SELECT etag FROM srp_config WHERE `key` = 'wallet_journal_etag'"
-    // )
-    // .fetch_optional(app.get_db())
-    // .await?
-    // .and_then(|r| r.etag);
-
-    // println!("Last ETag: {:?}", last_etag);
-            // println!("DEBUG: ETag disabled - requesting fresh data");
-
-    // Make the ESI request without ETag to get fresh data
-    let response = match app.esi_client
-        .get_with_etag::<Vec<WalletJournalEntry>>(
+    app.esi_client
+        .get_cached(
             &format!("/v6/corporations/{}/wallets/{}/journal/", corporation_id, wallet_id),
             character_id,
             ESIScope::Wallet_ReadCorporationWallets_v1,
-            None, // ETag disabled
         )
-        .await {
-            Ok(response) => {
-                // println!("ESI request successful, got {} entries", response.data.len());
-                response
-            },
-            Err(e) => {
-                // println!("ESI request failed: {:?}", e);
-                return Err(Madness::BadRequest(format!("ESI request failed: {:?}", e)));
-            }
-        };
-
-    // ETAG DISABLED: Store the new ETag if we got one
-    // if let Some(new_etag) = &response.etag {
-    //     println!("DEBUG: Storing new ETag: {}", new_etag);
-    //     sqlx::query!(
-    //         "INSERT INTO srp_config (`key`, value, etag, updated_at, updated_by_id)
-    //          VALUES (?, ?, ?, ?, ?)
-    //          ON DUPLICATE KEY UPDATE
-    //          etag = VALUES(etag),
-    //          updated_at = VALUES(updated_at),
-    //          updated_by_id = VALUES(updated_by_id)",
-    //         "wallet_journal_etag",
-    //         "", // Empty value since we're only storing ETag
-    //         new_etag,
-    //         chrono::Utc::now().timestamp(),
-    //         character_id
-    //     )
-    //     .execute(app.get_db())
-    //     .await?;
-    // } else {
-    //     println!("DEBUG: No new ETag received (304 Not Modified)");
-    // }
-    
-            // println!("DEBUG: ETag storage disabled - not storing response ETag");
-
-    Ok(response.data)
+        .await
+        .map_err(|e| Madness::BadRequest(format!("ESI request failed: {:?}", e)))
 }
 
 
@@ -373,18 +324,11 @@ pub async fn process_srp_payments(app: &crate::app::Application) -> Result<(), M
         service_account.wallet_id
     ).await?;
 
-    // If no entries returned (ETag disabled, so this means no data available)
+    // If no entries returned, the journal is empty (a 304 still returns the cached entries)
     if entries.is_empty() {
-        // println!("No wallet journal entries found (ETag disabled - fresh request returned no data)");
-        // println!("DEBUG: This could mean either:");
-        // println!("  1. Wallet journal is completely empty");
-        // println!("  2. ESI endpoint issue or permissions problem");
-        // println!("  3. No transactions in the specified time range");
         return Ok(());
     }
 
-    // println!("DEBUG: Got {} new wallet journal entries", entries.len());
-
     // Only clear the database if we have new data to process
     // println!("New data found, clearing existing SRP payments from database");
     sqlx::query!("DELETE FROM srp_payments")
@@ -604,6 +548,8 @@ pub async fn store_service_account_tokens(
     scopes: &str,
 ) -> Result<(), Madness> {
     let now = chrono::Utc::now().timestamp();
+    let access_token = app.esi_client.token_cipher().encrypt(access_token);
+    let refresh_token = app.esi_client.token_cipher().encrypt(refresh_token);
 
     sqlx::query!(
         "INSERT INTO srp_service_account (
@@ -646,7 +592,15 @@ pub async fn get_service_account_tokens(
     .fetch_optional(app.get_db())
     .await?;
 
-    Ok(result.map(|r| (r.access_token, r.refresh_token, r.expires)))
+    match result {
+        Some(r) => {
+            let cipher = app.esi_client.token_cipher();
+            let access_token = cipher.decrypt(&r.access_token).map_err(ESIError::from)?;
+            let refresh_token = cipher.decrypt(&r.refresh_token).map_err(ESIError::from)?;
+            Ok(Some((access_token, refresh_token, r.expires)))
+        }
+        None => Ok(None),
+    }
 }
 
 pub async fn get_service_account_info(
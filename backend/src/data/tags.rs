@@ -1,31 +1,167 @@
-use std::collections::HashSet;
+use std::collections::BTreeMap;
 use std::sync::{Arc, RwLock};
 
 use serde::{Deserialize, Serialize};
 
 use crate::data::yamlhelper;
+use crate::tla::fitcheck::EmittedTag;
 
 lazy_static::lazy_static! {
-    static ref PUBLIC_TAGS: Arc<RwLock<HashSet<String>>> = Arc::new(RwLock::new(build_public_tags()));
+    static ref TAGS: Arc<RwLock<TagRegistry>> = Arc::new(RwLock::new(build_tags().unwrap()));
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TagError {
+    #[error("tag '{0}' can be emitted but is not defined in tags.yaml")]
+    Undefined(&'static str),
+    #[error("tag '{0}' is defined more than once")]
+    Duplicate(String),
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Default for TagSeverity {
+    fn default() -> Self {
+        TagSeverity::Info
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagVisibility {
+    // Shown to everyone, including other pilots on the waitlist
+    Public,
+    // Only shown to people who can view the full waitlist
+    Fc,
+}
+
+impl Default for TagVisibility {
+    fn default() -> Self {
+        TagVisibility::Fc
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Tag {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub severity: TagSeverity,
+    #[serde(default)]
+    pub visibility: TagVisibility,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    // Seconds after joining the waitlist after which the tag is no longer shown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_after: Option<i64>,
+}
+
+impl Tag {
+    // Used for tags that are stored on old x'es but no longer defined
+    fn unknown(name: &str) -> Tag {
+        Tag {
+            name: name.to_string(),
+            description: String::new(),
+            severity: TagSeverity::Info,
+            visibility: TagVisibility::Fc,
+            icon: None,
+            color: None,
+            expires_after: None,
+        }
+    }
+
+    pub fn is_public(&self) -> bool {
+        self.visibility == TagVisibility::Public
+    }
+
+    pub fn is_expired(&self, joined_at: i64, now: i64) -> bool {
+        match self.expires_after {
+            Some(seconds) => now - joined_at > seconds,
+            None => false,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct TagFile {
-    public_tags: Vec<String>,
+    #[serde(default)]
+    tags: Vec<Tag>,
+    // The format from before tags were defined here, which only listed the public ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    public_tags: Option<Vec<String>>,
 }
 
-fn build_public_tags() -> HashSet<String> {
+type TagRegistry = BTreeMap<String, Tag>;
+
+fn parse_tags(file: TagFile) -> Result<TagRegistry, TagError> {
+    let mut registry = BTreeMap::new();
+    for tag in file.tags {
+        if registry.contains_key(&tag.name) {
+            return Err(TagError::Duplicate(tag.name));
+        }
+        registry.insert(tag.name.clone(), tag);
+    }
+
+    // An old tags.yaml keeps working: every tag gets the defaults, and the listed ones are public
+    if let Some(public_tags) = file.public_tags {
+        for emitted in EmittedTag::ALL {
+            let name = emitted.as_str();
+            registry
+                .entry(name.to_string())
+                .or_insert_with(|| Tag::unknown(name));
+        }
+        for name in public_tags {
+            registry
+                .entry(name.clone())
+                .or_insert_with(|| Tag::unknown(&name))
+                .visibility = TagVisibility::Public;
+        }
+    }
+
+    for emitted in EmittedTag::ALL {
+        if !registry.contains_key(emitted.as_str()) {
+            return Err(TagError::Undefined(emitted.as_str()));
+        }
+    }
+
+    Ok(registry)
+}
+
+fn build_tags() -> Result<TagRegistry, TagError> {
     let data: TagFile = yamlhelper::from_file("./data/tags.yaml");
-    data.public_tags.into_iter().collect()
+    parse_tags(data)
+}
+
+/// Forces the registry to load so a bad tags.yaml fails at startup rather than on the first request.
+pub fn check_tags() {
+    let _tags = TAGS.read().unwrap();
+}
+
+pub fn get_tag(name: &str) -> Tag {
+    TAGS.read()
+        .unwrap()
+        .get(name)
+        .cloned()
+        .unwrap_or_else(|| Tag::unknown(name))
 }
 
-pub fn public_tags() -> HashSet<String> {
-    PUBLIC_TAGS.read().unwrap().clone()
+pub fn all_tags() -> Vec<Tag> {
+    TAGS.read().unwrap().values().cloned().collect()
 }
 
 pub fn reload_tags() -> Result<(), crate::util::madness::Madness> {
-    let new_tags = build_public_tags();
-    *PUBLIC_TAGS.write().unwrap() = new_tags;
+    let new_tags = build_tags()
+        .map_err(|e| crate::util::madness::Madness::BadRequest(format!("Failed to reload tags: {}", e)))?;
+    *TAGS.write().unwrap() = new_tags;
     Ok(())
 }
 
@@ -64,8 +200,9 @@ pub fn validate_yaml(yaml_content: &str) -> Result<(), crate::util::madness::Mad
     use crate::util::madness::Madness;
     
     // Validate YAML syntax and structure
-    let _: TagFile = serde_yaml::from_str(yaml_content)
+    let file: TagFile = serde_yaml::from_str(yaml_content)
         .map_err(|e| Madness::BadRequest(format!("Invalid YAML: {}", e)))?;
+    parse_tags(file).map_err(|e| Madness::BadRequest(format!("Invalid tags: {}", e)))?;
     Ok(())
 }
 
@@ -114,3 +251,45 @@ pub fn create_backup() -> Result<(), crate::util::madness::Madness> {
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_tags, EmittedTag, Tag, TagFile, TagVisibility};
+
+    #[test]
+    fn test_emitted_tags_defined() {
+        let file: TagFile = serde_yaml::from_str("tags: []").unwrap();
+        assert!(parse_tags(file).is_err());
+
+        let tags = EmittedTag::ALL
+            .iter()
+            .map(|tag| Tag::unknown(tag.as_str()))
+            .collect();
+        let registry = parse_tags(TagFile {
+            tags,
+            public_tags: None,
+        })
+        .unwrap();
+        assert_eq!(registry.len(), EmittedTag::ALL.len());
+    }
+
+    #[test]
+    fn test_public_tags_file() {
+        let file: TagFile = serde_yaml::from_str("public_tags:\n  - LOGI\n  - DPS").unwrap();
+        let registry = parse_tags(file).unwrap();
+        assert_eq!(registry.len(), EmittedTag::ALL.len());
+        assert!(registry["LOGI"].is_public());
+        assert!(!registry["HQ-FC"].is_public());
+    }
+
+    #[test]
+    fn test_tag_fields() {
+        let tag: Tag = serde_yaml::from_str(
+            "name: DPS-HOURS-REACHED\nvisibility: public\nseverity: warning\nexpires_after: 3600",
+        )
+        .unwrap();
+        assert_eq!(tag.visibility, TagVisibility::Public);
+        assert!(!tag.is_expired(1000, 4000));
+        assert!(tag.is_expired(1000, 5000));
+    }
+}
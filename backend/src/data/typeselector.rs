@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+use eve_data_core::{TypeDB, TypeError, TypeID};
+
+/// A reference to one or more types in the data files. A plain string is a type name,
+/// `{group: ...}` is every published type in an inventory group, and `{market_group: ...}`
+/// is everything below a market group (see `TypeDB::market_group_id_of` for the path syntax).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum TypeSelector {
+    Name(String),
+    Group { group: String },
+    MarketGroup { market_group: String },
+}
+
+impl TypeSelector {
+    pub fn resolve(&self) -> Result<Vec<TypeID>, TypeError> {
+        match self {
+            Self::Name(name) => Ok(vec![TypeDB::id_of(name)?]),
+            Self::Group { group } => TypeDB::group_type_ids(TypeDB::group_id_of(group)?),
+            Self::MarketGroup { market_group } => {
+                TypeDB::market_group_type_ids(TypeDB::market_group_id_of(market_group)?)
+            }
+        }
+    }
+}
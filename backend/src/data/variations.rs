@@ -3,7 +3,7 @@ use std::sync::{Arc, RwLock};
 
 use serde::{Deserialize, Serialize};
 
-use crate::data::yamlhelper;
+use crate::data::{typeselector::TypeSelector, yamlhelper};
 use crate::util::madness::Madness;
 
 use eve_data_core::{Attribute, TypeDB, TypeError, TypeID};
@@ -50,11 +50,11 @@ struct FromAttributeEntry {
 
 #[derive(Debug, Deserialize, Serialize)]
 struct ModuleFile {
-    alternatives: Vec<Vec<Vec<String>>>,
+    alternatives: Vec<Vec<Vec<TypeSelector>>>,
     from_meta: Vec<FromMetaEntry>,
     from_attribute: Vec<FromAttributeEntry>,
     accept_t1: Vec<String>,
-    cargo_ignore: Vec<String>,
+    cargo_ignore: Vec<TypeSelector>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -140,8 +140,10 @@ impl Builder {
             let mut tier_i = 0;
             for tier in group {
                 tier_i += 1;
-                for module in tier {
-                    tiers.insert(TypeDB::id_of(module)?, tier_i);
+                for selector in tier {
+                    for module in selector.resolve()? {
+                        tiers.insert(module, tier_i);
+                    }
                 }
             }
             to_merge.push(tiers);
@@ -153,7 +155,7 @@ impl Builder {
     }
     fn add_cargo_ignore(&mut self) -> Result<(), TypeError> {
         for entry in &self.file.cargo_ignore {
-            self.cargo_ignore.insert(TypeDB::id_of(entry)?);
+            self.cargo_ignore.extend(entry.resolve()?);
         }
         Ok(())
     }
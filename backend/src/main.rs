@@ -7,6 +7,8 @@ mod core;
 mod data;
 mod request_logger;
 mod routes;
+#[cfg(test)]
+mod testing;
 mod tla;
 mod util;
 
@@ -45,6 +47,10 @@ async fn main() {
     let raw_config = std::fs::read_to_string(&config_file).expect("Could not load config");
     let config: config::Config = toml::from_str(&raw_config).expect("Could not load config");
 
+    eve_data_core::TypeDB::open_with_connections(&config.sde.path, config.sde.connections)
+        .and_then(|sde| sde.preload())
+        .expect("Could not open SDE");
+
     let database = options
         .idle_timeout(std::time::Duration::from_secs(config.database.idle_timeout))
         .connect_timeout(std::time::Duration::from_secs(
@@ -57,6 +63,16 @@ async fn main() {
         .unwrap();
     let database = Arc::new(database);
 
+    // Fail early if tags.yaml doesn't cover every tag the fit checker can emit
+    data::tags::check_tags();
+    data::motd_roles::check_roles();
+
+    // Encrypt tokens stored before encryption, and move them off rotated-out keys
+    let token_cipher = core::token_crypto::TokenCipher::new(&config.app.token_encryption_keys);
+    core::token_crypto::migrate(&database, &token_cipher)
+        .await
+        .expect("Could not encrypt stored tokens");
+
     if config.fleet_updater.enable {
         let fleet_updater =
             core::fleet_updater::FleetUpdater::new(database.clone(), config.clone());
@@ -5,6 +5,7 @@ use std::fs;
 use std::path::Path;
 
 use crate::{
+    app::Application,
     core::auth::AuthenticatedAccount,
     data::{guides, locales},
     util::madness::Madness,
@@ -37,7 +38,7 @@ fn get_file_info(filename: &str) -> Option<DataFileInfo> {
     let metadata = fs::metadata(&file_path).ok()?;
     let requires_reload = matches!(
         filename,
-        "skills.yaml" | "categories.yaml" | "modules.yaml" | "tags.yaml" | "fits.dat"
+        "skills.yaml" | "categories.yaml" | "modules.yaml" | "tags.yaml" | "fits.dat" | "motd_roles.yaml"
     );
     
     let file_type = if filename.ends_with(".yaml") {
@@ -81,7 +82,7 @@ fn list_data_files(account: AuthenticatedAccount) -> Result<Json<DataFilesListRe
             if matches!(
                 filename,
                 "skills.yaml" | "categories.yaml" | "modules.yaml" | "tags.yaml" | 
-                "fits.dat" | "fitnotes.yaml" | "skillplan.yaml"
+                "fits.dat" | "fitnotes.yaml" | "skillplan.yaml" | "motd_roles.yaml"
             ) {
                 if let Some(info) = get_file_info(filename) {
                     files.push(info);
@@ -144,7 +145,7 @@ fn get_data_file(account: AuthenticatedAccount, filename: String) -> Result<Stri
     if !matches!(
         filename.as_str(),
         "skills.yaml" | "categories.yaml" | "modules.yaml" | "tags.yaml" | 
-        "fits.dat" | "fitnotes.yaml" | "skillplan.yaml"
+        "fits.dat" | "fitnotes.yaml" | "skillplan.yaml" | "motd_roles.yaml"
     ) {
         return Err(Madness::BadRequest("File not editable".to_string()));
     }
@@ -194,7 +195,7 @@ fn save_data_file(
     if !matches!(
         filename.as_str(),
         "skills.yaml" | "categories.yaml" | "modules.yaml" | "tags.yaml" | 
-        "fits.dat" | "fitnotes.yaml" | "skillplan.yaml"
+        "fits.dat" | "fitnotes.yaml" | "skillplan.yaml" | "motd_roles.yaml"
     ) {
         return Err(Madness::BadRequest("File not editable".to_string()));
     }
@@ -240,6 +241,10 @@ fn save_data_file(
         "skillplan.yaml" => {
             crate::data::skillplans::save_plans_from_raw_yaml(&content)?;
         }
+        "motd_roles.yaml" => {
+            crate::data::motd_roles::save_roles_to_file(&content)?;
+            crate::data::motd_roles::reload_roles()?;
+        }
         _ => return Err(Madness::BadRequest("Unknown file type".to_string())),
     }
     
@@ -295,6 +300,9 @@ fn reload_data_file(account: AuthenticatedAccount, filename: String) -> Result<&
         "fits.dat" => {
             crate::data::fits::reload_fits()?;
         }
+        "motd_roles.yaml" => {
+            crate::data::motd_roles::reload_roles()?;
+        }
         "fitnotes.yaml" | "skillplan.yaml" => {
             return Err(Madness::BadRequest("File does not require reload".to_string()));
         }
@@ -304,6 +312,65 @@ fn reload_data_file(account: AuthenticatedAccount, filename: String) -> Result<&
     Ok("File reloaded successfully")
 }
 
+#[derive(Debug, Serialize)]
+struct SDEReloadResponse {
+    path: String,
+    sde_build: Option<i64>,
+}
+
+#[post("/api/admin/sde/reload")]
+fn reload_sde(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<SDEReloadResponse>, Madness> {
+    account.require_access("commanders-manage:admin")?;
+
+    let sde = std::sync::Arc::new(eve_data_core::SDE::open_with_connections(
+        &app.config.sde.path,
+        app.config.sde.connections,
+    )?);
+    sde.preload()?;
+
+    // Everything we rebuild resolves names against the active SDE, so it has to be swapped in
+    // first. If the new one breaks any of it, go back to the old SDE and what was built from it.
+    let previous = eve_data_core::TypeDB::active()?;
+    eve_data_core::TypeDB::activate(sde.clone());
+    if let Err(e) = rebuild_sde_data() {
+        eve_data_core::TypeDB::activate(previous);
+        if let Err(e) = rebuild_sde_data() {
+            error!("Failed to rebuild data against the previous SDE: {:?}", e);
+        }
+        return Err(e);
+    }
+
+    Ok(Json(SDEReloadResponse {
+        path: sde.path().to_string(),
+        sde_build: sde.build_number(),
+    }))
+}
+
+/// Reloads everything that resolved names to type IDs against the active SDE.
+fn rebuild_sde_data() -> Result<(), Madness> {
+    crate::data::fits::reload_fits()?;
+    crate::data::variations::reload_variations()
+        .map_err(|e| Madness::BadRequest(format!("Failed to reload modules: {}", e)))?;
+    crate::tla::fitmatch::reload_identifier()
+        .map_err(|e| Madness::BadRequest(format!("Failed to reload identifier: {}", e)))?;
+    crate::data::categories::reload_category_data()
+        .map_err(|e| Madness::BadRequest(format!("Failed to reload categories: {}", e)))?;
+    crate::tla::skills::reload_skill_data()
+        .map_err(|e| Madness::BadRequest(format!("Failed to reload skills: {}", e)))?;
+    Ok(())
+}
+
+#[get("/api/admin/esi/budget")]
+fn esi_budget(
+    account: AuthenticatedAccount,
+) -> Result<Json<crate::core::esi_budget::ErrorBudgetStatus>, Madness> {
+    account.require_access("commanders-manage:admin")?;
+    Ok(Json(crate::core::esi_budget::ERROR_BUDGET.status()))
+}
+
 #[derive(Debug, Serialize)]
 struct GuideAssetsListResponse {
     assets: Vec<guides::GuideAssetInfo>,
@@ -369,6 +436,8 @@ pub fn routes() -> Vec<rocket::Route> {
         save_data_file,
         delete_data_file,
         reload_data_file,
+        reload_sde,
+        esi_budget,
         list_guide_assets,
         upload_guide_asset,
         delete_guide_asset,
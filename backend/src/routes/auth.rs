@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::app;
 use crate::core::auth::{AuthenticatedAccount, AuthenticationError, CookieSetter};
-use crate::core::esi::ESIScope;
+use crate::core::esi::{split_scopes, ESIError, ESIScope};
 use crate::util::{madness::Madness, types};
 
 #[derive(Serialize)]
@@ -75,8 +75,41 @@ async fn logout<'r>(
     ))
 }
 
-#[get("/api/auth/login_url?<alt>&<fc>&<srp_admin>")]
-fn login_url(alt: bool, fc: bool, srp_admin: bool, app: &rocket::State<app::Application>) -> String {
+const WAITLIST_SCOPES: &[ESIScope] = &[
+    ESIScope::PublicData,
+    ESIScope::Skills_ReadSkills_v1,
+    ESIScope::Clones_ReadImplants_v1,
+];
+const FC_SCOPES: &[ESIScope] = &[
+    ESIScope::Fleets_ReadFleet_v1,
+    ESIScope::Fleets_WriteFleet_v1,
+    ESIScope::UI_OpenWindow_v1,
+    ESIScope::Search_v1,
+];
+const FITTINGS_SCOPES: &[ESIScope] = &[
+    ESIScope::Fittings_ReadFittings_v1,
+    ESIScope::Fittings_WriteFittings_v1,
+];
+
+fn authorize_url(app: &app::Application, scopes: &[ESIScope], state: &str) -> String {
+    format!(
+        "{}/v2/oauth/authorize?response_type=code&redirect_uri={}&client_id={}&scope={}&state={}",
+        app.config.esi.sso_base_url.trim_end_matches('/'),
+        app.config.esi.url,
+        app.config.esi.client_id,
+        scopes.iter().map(|scope| scope.as_str()).collect::<Vec<_>>().join(" "),
+        state
+    )
+}
+
+#[get("/api/auth/login_url?<alt>&<fc>&<srp_admin>&<fittings>")]
+fn login_url(
+    alt: bool,
+    fc: bool,
+    srp_admin: bool,
+    fittings: bool,
+    app: &rocket::State<app::Application>,
+) -> String {
     let state = if srp_admin {
         "srp_admin"
     } else {
@@ -86,30 +119,131 @@ fn login_url(alt: bool, fc: bool, srp_admin: bool, app: &rocket::State<app::Appl
         }
     };
 
-    let mut scopes = vec![
-        ESIScope::PublicData,
-        ESIScope::Skills_ReadSkills_v1,
-        ESIScope::Clones_ReadImplants_v1,
-    ];
+    let mut scopes = WAITLIST_SCOPES.to_vec();
     if fc {
-        scopes.extend(vec![
-            ESIScope::Fleets_ReadFleet_v1,
-            ESIScope::Fleets_WriteFleet_v1,
-            ESIScope::UI_OpenWindow_v1,
-            ESIScope::Search_v1,
-        ])
+        scopes.extend(FC_SCOPES)
     }
     if srp_admin {
         scopes.push(ESIScope::UI_OpenWindow_v1);
     }
+    if fittings {
+        scopes.extend(FITTINGS_SCOPES)
+    }
 
-    format!(
-        "https://login.eveonline.com/v2/oauth/authorize?response_type=code&redirect_uri={}&client_id={}&scope={}&state={}",
-        app.config.esi.url,
-        app.config.esi.client_id,
-        scopes.iter().fold(String::new(), |acc, scope| acc + " " + scope.as_str()).trim_end(),
-        state
+    authorize_url(app, &scopes, state)
+}
+
+#[derive(Serialize)]
+struct FeatureTokenStatus {
+    feature: &'static str,
+    required: bool,
+    missing_scopes: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+struct CharacterTokenStatus {
+    character: types::Character,
+    has_token: bool,
+    access_token_expires: Option<i64>,
+    scopes: Vec<String>,
+    features: Vec<FeatureTokenStatus>,
+    /// Logs in again asking for exactly the scopes that required features are missing
+    reauth_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TokensResponse {
+    characters: Vec<CharacterTokenStatus>,
+}
+
+#[get("/api/auth/tokens")]
+async fn tokens(
+    app: &rocket::State<app::Application>,
+    account: AuthenticatedAccount,
+) -> Result<Json<TokensResponse>, Madness> {
+    let mut characters = vec![sqlx::query_as!(
+        types::Character,
+        "SELECT id, name, corporation_id FROM `character` WHERE id = ?",
+        account.id
     )
+    .fetch_one(app.get_db())
+    .await?];
+    characters.extend(
+        sqlx::query_as!(
+            types::Character,
+            "SELECT id, name, corporation_id FROM alt_character JOIN `character` ON alt_character.alt_id = `character`.id WHERE account_id = ?",
+            account.id
+        )
+        .fetch_all(app.get_db())
+        .await?,
+    );
+
+    let features = [
+        ("waitlist", true, WAITLIST_SCOPES),
+        ("fc", account.access.contains("fleet-configure"), FC_SCOPES),
+        ("fittings", false, FITTINGS_SCOPES),
+    ];
+
+    let mut statuses = Vec::new();
+    for character in characters {
+        let refresh = sqlx::query!(
+            "SELECT scopes FROM refresh_token WHERE character_id = ?",
+            character.id
+        )
+        .fetch_optional(app.get_db())
+        .await?;
+        let access = sqlx::query!(
+            "SELECT expires FROM access_token WHERE character_id = ?",
+            character.id
+        )
+        .fetch_optional(app.get_db())
+        .await?;
+
+        let scopes = refresh
+            .as_ref()
+            .map(|refresh| split_scopes(&refresh.scopes))
+            .unwrap_or_default();
+        let mut reauth_scopes = Vec::new();
+        let features = features
+            .iter()
+            .map(|&(feature, required, needed)| {
+                let missing: Vec<ESIScope> = needed
+                    .iter()
+                    .filter(|scope| !scopes.contains(scope.as_str()))
+                    .copied()
+                    .collect();
+                if required {
+                    for scope in &missing {
+                        if !reauth_scopes.contains(scope) {
+                            reauth_scopes.push(*scope);
+                        }
+                    }
+                }
+                FeatureTokenStatus {
+                    feature,
+                    required,
+                    missing_scopes: missing.iter().map(|scope| scope.as_str()).collect(),
+                }
+            })
+            .collect();
+
+        let state = if character.id == account.id { "normal" } else { "alt" };
+        statuses.push(CharacterTokenStatus {
+            has_token: refresh.is_some(),
+            access_token_expires: access.map(|access| access.expires),
+            scopes: scopes.into_iter().collect(),
+            features,
+            reauth_url: match reauth_scopes.is_empty() {
+                true => None,
+                false => Some(authorize_url(app, &reauth_scopes, state)),
+            },
+            character,
+        });
+    }
+
+    Ok(Json(TokensResponse {
+        characters: statuses,
+    }))
 }
 
 #[derive(Deserialize)]
@@ -262,6 +396,7 @@ async fn callback_get(
         .fetch_one(app.get_db())
         .await?;
 
+        let cipher = app.esi_client.token_cipher();
         let scopes = vec!["esi-publicdata.v1", "esi-wallet.read_corporation_wallets.v1"];
         let scopes_str = scopes.join(" ");
         
@@ -271,8 +406,8 @@ async fn callback_get(
             &character.name,
             corporation_id,
             wallet_id,
-            &access_token_record.access_token,
-            &refresh_token_record.refresh_token,
+            &cipher.decrypt(&access_token_record.access_token).map_err(ESIError::from)?,
+            &cipher.decrypt(&refresh_token_record.refresh_token).map_err(ESIError::from)?,
             chrono::Utc::now().timestamp() + 1200, // 20 minutes from now
             &scopes_str,
         ).await?;
@@ -286,5 +421,5 @@ async fn callback_get(
 }
 
 pub fn routes() -> Vec<rocket::Route> {
-    routes![whoami, logout, login_url, callback, callback_get]
+    routes![whoami, logout, login_url, tokens, callback, callback_get]
 }
@@ -1,9 +1,11 @@
 use rocket::serde::json::Json;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::core::auth::AuthenticatedAccount;
-use crate::data;
-use crate::util::types::WaitlistCategory;
+use crate::data::{self, categories::RuleMatch};
+use crate::tla::fitmatch;
+use crate::util::{madness::Madness, types::WaitlistCategory};
+use eve_data_core::{AsyncTypeDB, Fitting};
 
 #[derive(Debug, Serialize)]
 struct CategoryResponse {
@@ -17,6 +19,61 @@ fn categories(_account: AuthenticatedAccount) -> Json<CategoryResponse> {
     })
 }
 
+#[derive(Debug, Deserialize)]
+struct CategoryTestRequest {
+    eft: String,
+    #[serde(default)]
+    is_alt: bool,
+    #[serde(default)]
+    badges: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CategoryTestResult {
+    hull: String,
+    doctrine: Option<String>,
+    category: String,
+    rule: Option<RuleMatch>,
+}
+
+#[derive(Debug, Serialize)]
+struct CategoryTestResponse {
+    results: Vec<CategoryTestResult>,
+}
+
+#[post("/api/categories/test", data = "<input>")]
+async fn test_categories(
+    account: AuthenticatedAccount,
+    input: Json<CategoryTestRequest>,
+) -> Result<Json<CategoryTestResponse>, Madness> {
+    account.require_access("commanders-manage:admin")?;
+
+    let mut results = Vec::new();
+    for fit in Fitting::from_eft(&input.eft)? {
+        fit.validate()?;
+
+        let doctrine = fitmatch::find_fit(&fit).map(|(doctrine_fit, _diff)| doctrine_fit.name.clone());
+        let rule = data::categories::explain(&data::categories::CategoryInput {
+            fit: &fit,
+            doctrine: doctrine.as_deref(),
+            is_alt: input.is_alt,
+            badges: &input.badges,
+        });
+
+        results.push(CategoryTestResult {
+            hull: AsyncTypeDB::name_of(fit.hull).await?,
+            category: rule
+                .as_ref()
+                .map(|rule| rule.category.clone())
+                .unwrap_or_else(|| "other".to_string()),
+            doctrine,
+            rule,
+        });
+    }
+
+    Ok(Json(CategoryTestResponse { results }))
+}
+
 pub fn routes() -> Vec<rocket::Route> {
-    routes![categories]
+    routes![categories, test_categories]
 }
@@ -1,6 +1,10 @@
+use crate::app::Application;
+use crate::core::auth::{authorize_character, AuthenticatedAccount};
+use crate::core::esi;
 use crate::data::yamlhelper;
-use crate::util::madness::Madness;
-use eve_data_core::TypeID;
+use crate::tla::fitmatch;
+use crate::util::{madness::Madness, types::Hull};
+use eve_data_core::{AsyncTypeDB, Fitting, TypeID};
 use rocket::serde::json::Json;
 use serde::{Deserialize, Serialize};
 
@@ -131,20 +135,197 @@ async fn fittings() -> Result<Json<FittingResponse>, Madness> {
         })
         .collect::<Vec<_>>();
 
-    let mut logirules = Vec::new();
-
-    for rule in crate::data::categories::rules() {
-        if rule.1 == "logi" {
-            logirules.push(rule.0)
-        }
-    }
     Ok(Json(FittingResponse {
         fittingdata: Some(fits),
         notes: Some(load_notes_from_file()),
-        rules: Some(logirules),
+        rules: Some(crate::data::categories::hulls_in("logi")),
     }))
 }
 
+#[derive(Debug, Serialize)]
+struct SavedFitting {
+    fitting_id: i64,
+    name: String,
+    hull: Hull,
+    dna: String,
+    doctrine: Option<String>,
+    doctrine_match: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct SavedFittingsResponse {
+    fittings: Vec<SavedFitting>,
+}
+
+#[get("/api/fittings/saved?<character_id>")]
+async fn saved_fittings(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    character_id: i64,
+) -> Result<Json<SavedFittingsResponse>, Madness> {
+    authorize_character(app.get_db(), &account, character_id, None).await?;
+
+    let saved = esi::fittings::get(&app.esi_client, character_id).await?;
+    let hulls: Vec<TypeID> = saved.iter().map(|fit| fit.ship_type_id).collect();
+    let hull_names = AsyncTypeDB::names_of(&hulls).await?;
+
+    let mut fittings = Vec::new();
+    for esi_fitting in saved {
+        let fit = esi_fitting.to_fitting();
+        // Saved fittings can contain items we don't know about, skip any fit that doesn't validate
+        if fit.validate().is_err() {
+            continue;
+        }
+
+        let (doctrine, doctrine_match) = match fitmatch::find_fit(&fit) {
+            Some((doctrine_fit, diff)) => (
+                Some(doctrine_fit.name.clone()),
+                diff.module_missing.is_empty()
+                    && diff.module_downgraded.is_empty()
+                    && diff.cargo_missing.is_empty()
+                    && diff.charge_missing.is_empty(),
+            ),
+            None => (None, false),
+        };
+
+        fittings.push(SavedFitting {
+            fitting_id: esi_fitting.fitting_id,
+            name: esi_fitting.name,
+            hull: Hull {
+                id: fit.hull,
+                name: hull_names.get(&fit.hull).cloned().unwrap_or_default(),
+            },
+            dna: fit.to_dna()?,
+            doctrine,
+            doctrine_match,
+        });
+    }
+
+    Ok(Json(SavedFittingsResponse { fittings }))
+}
+
+#[derive(Debug, Deserialize)]
+struct PushFittingsRequest {
+    character_id: i64,
+    fits: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PushFittingsResponse {
+    pushed: Vec<String>,
+    skipped: Vec<String>,
+}
+
+#[post("/api/fittings/push", data = "<input>")]
+async fn push_fittings(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    input: Json<PushFittingsRequest>,
+) -> Result<Json<PushFittingsResponse>, Madness> {
+    authorize_character(app.get_db(), &account, input.character_id, None).await?;
+
+    // Build everything we want to send before talking to ESI, so the fits lock isn't held across awaits
+    let to_push = {
+        let fits_data = crate::data::fits::get_fits();
+        let fits_guard = fits_data.read().unwrap();
+        let mut to_push = Vec::new();
+        for name in &input.fits {
+            let doctrine_fit = fits_guard
+                .values()
+                .flatten()
+                .find(|fit| &fit.name == name)
+                .ok_or_else(|| Madness::BadRequest(format!("Unknown fit: {}", name)))?;
+            to_push.push((
+                doctrine_fit.fit.clone(),
+                esi::fittings::ESINewFitting::from_fitting(name, "", &doctrine_fit.fit)?,
+            ));
+        }
+        to_push
+    };
+
+    let saved = esi::fittings::get(&app.esi_client, input.character_id).await?;
+
+    let mut pushed = Vec::new();
+    let mut skipped = Vec::new();
+    for (fit, new_fitting) in to_push {
+        // Saved fittings drop loaded charges and pilots change their cargo, so only the ship counts
+        let already_saved = saved.iter().any(|existing| {
+            let existing_fit = existing.to_fitting();
+            existing.name == new_fitting.name
+                || (existing_fit.hull == fit.hull && existing_fit.modules == fit.modules)
+        });
+        if already_saved {
+            skipped.push(new_fitting.name);
+            continue;
+        }
+
+        esi::fittings::create(&app.esi_client, input.character_id, &new_fitting).await?;
+        pushed.push(new_fitting.name);
+    }
+
+    Ok(Json(PushFittingsResponse { pushed, skipped }))
+}
+
+#[derive(Debug, Deserialize)]
+struct ParseFittingsRequest {
+    eft: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ParsedFitting {
+    hull: Hull,
+    dna: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ParseDiagnostic {
+    line: usize,
+    text: String,
+    reason: String,
+    suggestion: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ParseFittingsResponse {
+    fits: Vec<ParsedFitting>,
+    diagnostics: Vec<ParseDiagnostic>,
+}
+
+#[post("/api/fittings/parse", data = "<input>")]
+async fn parse_fittings(
+    _account: AuthenticatedAccount,
+    input: Json<ParseFittingsRequest>,
+) -> Result<Json<ParseFittingsResponse>, Madness> {
+    let parsed = Fitting::from_eft_lenient(&input.eft)?;
+
+    let hull_ids: Vec<TypeID> = parsed.fittings.iter().map(|fit| fit.hull).collect();
+    let hull_names = AsyncTypeDB::names_of(&hull_ids).await?;
+
+    let mut fits = Vec::new();
+    for fit in parsed.fittings {
+        fits.push(ParsedFitting {
+            hull: Hull {
+                id: fit.hull,
+                name: hull_names.get(&fit.hull).cloned().unwrap_or_default(),
+            },
+            dna: fit.to_dna()?,
+        });
+    }
+
+    let diagnostics = parsed
+        .diagnostics
+        .into_iter()
+        .map(|diagnostic| ParseDiagnostic {
+            line: diagnostic.line,
+            text: diagnostic.text,
+            reason: diagnostic.error.to_string(),
+            suggestion: diagnostic.suggestion,
+        })
+        .collect();
+
+    Ok(Json(ParseFittingsResponse { fits, diagnostics }))
+}
+
 pub fn routes() -> Vec<rocket::Route> {
-    routes![fittings]
+    routes![fittings, saved_fittings, push_fittings, parse_fittings]
 }
@@ -1,18 +1,23 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use crate::{
     app::Application,
     core::{
         auth::{authorize_character, AuthenticatedAccount},
-        esi::{ESIError, ESIScope},
+        esi::{fleet_members::ESIFleetMember, fleet_wings, ESIError, ESIScope},
+    },
+    data::{
+        fleet_roles::{self, Assignment},
+        motd::{Assignee, Motd},
+        motd_roles::{self, MotdRole},
     },
     util::{
         self,
         madness::Madness,
-        types::{Character, Hull},
+        types::{Character, Hull, WaitlistCategory},
     },
 };
-use eve_data_core::TypeDB;
+use eve_data_core::{TypeDB, TypeID};
 use rocket::serde::json::Json;
 use serde::{Deserialize, Serialize};
 
@@ -132,6 +137,7 @@ async fn fleet_info(
 #[derive(Debug, Serialize)]
 struct RoleAssignment {
     name: String,
+    character_id: Option<i64>,
     in_fleet: bool,
     correct_hull: Option<bool>, // None if not in fleet, Some(true) if correct hull, Some(false) if wrong hull
     hull_id: Option<i64>, // Ship type ID if in fleet, None otherwise
@@ -143,1143 +149,122 @@ struct FleetCompResponse {
     id: i64,
     member: Option<FleetMember>,
     role_assignments: Option<HashMap<String, Vec<RoleAssignment>>>,
+    motd_roles: Vec<MotdRole>,
 }
 
-#[derive(Debug, Serialize)]
-struct FleetCompWing {
-    id: i64,
-    name: String,
-    squads: Vec<FleetCompSquadMembers>,
-    member: Option<FleetMember>,
-}
-
-#[derive(Debug, Serialize)]
-struct FleetCompSquadMembers {
-    id: i64,
-    name: String,
-    members: Vec<FleetMember>,
-}
-
-#[derive(Debug, Serialize)]
-struct FleetMember {
-    id: i64,
-    name: Option<String>,
-    ship: Hull,
-    role: String,
-}
-
-fn strip_html_tags(text: &str) -> String {
-    let mut result = String::new();
-    let mut in_tag = false;
-    
-    for ch in text.chars() {
-        match ch {
-            '<' => in_tag = true,
-            '>' => in_tag = false,
-            _ => {
-                if !in_tag {
-                    result.push(ch);
-                }
-            }
-        }
-    }
-    
-    result
-}
-
-fn extract_names_from_html_tags(text: &str) -> Vec<String> {
-    let mut names = Vec::new();
-    let mut chars = text.chars().peekable();
-    let mut current_name = String::new();
-    let mut in_tag = false;
-    let mut in_closing_tag = false;
-    
-    while let Some(ch) = chars.next() {
-        match ch {
-            '<' => {
-                // Check if this is a closing tag
-                if chars.peek() == Some(&'/') {
-                    // Save the name we were collecting before the closing tag
-                    if !current_name.trim().is_empty() {
-                        names.push(current_name.trim().to_string());
-                        current_name.clear();
-                    }
-                    in_closing_tag = true;
-                    chars.next(); // consume the '/'
-                } else {
-                    // If we were collecting a name, save it (shouldn't happen normally)
-                    if !current_name.trim().is_empty() {
-                        names.push(current_name.trim().to_string());
-                        current_name.clear();
-                    }
-                    in_tag = true;
-                }
-            }
-            '>' => {
-                in_tag = false;
-                in_closing_tag = false;
-            }
-            _ => {
-                if in_tag || in_closing_tag {
-                    // Skip tag content
-                    continue;
-                } else {
-                    // We're outside tags - this is character name content
-                    current_name.push(ch);
-                }
-            }
-        }
-    }
-    
-    // Don't forget the last name (if text ends without a closing tag)
-    if !current_name.trim().is_empty() {
-        names.push(current_name.trim().to_string());
-    }
-    
-    // Only return if we found HTML tags (indicated by having HTML structure)
-    if !names.is_empty() && text.contains('<') {
-        return names.into_iter().filter(|s| !s.is_empty()).collect();
-    }
-    
-    Vec::new()
+/// Who is in the fleet and in what, to check the MOTD against.
+struct InFleet {
+    hulls: HashMap<i64, TypeID>,
+    names: HashMap<i64, String>,
+    ids_by_name: HashMap<String, i64>,
 }
 
-fn parse_character_names(text: &str) -> Vec<String> {
-    // First, try to extract names from HTML tags (e.g., <tag>Name1</tag> <tag>Name2</tag>)
-    let html_names = extract_names_from_html_tags(text);
-    if !html_names.is_empty() {
-        return html_names;
-    }
-    
-    // If no HTML tags found, strip HTML and parse normally
-    let cleaned = strip_html_tags(text).trim().to_string();
-    if cleaned.is_empty() {
-        return Vec::new();
-    }
-    
-    let delimiters = [',', ';', '|', '/', '\\'];
-    
-    // Check if any delimiter exists
-    let has_delimiter = delimiters.iter().any(|&delim| cleaned.contains(delim));
-    
-    if has_delimiter {
-        // Split by the first delimiter found
-        for &delim in &delimiters {
-            if cleaned.contains(delim) {
-                return cleaned
-                    .split(delim)
-                    .map(|s| s.trim().to_string())
-                    .filter(|s| !s.is_empty())
-                    .collect();
+impl InFleet {
+    fn new(members: &[ESIFleetMember], characters: &HashMap<i64, Character>) -> InFleet {
+        let mut in_fleet = InFleet {
+            hulls: HashMap::new(),
+            names: HashMap::new(),
+            ids_by_name: HashMap::new(),
+        };
+        for member in members {
+            in_fleet
+                .hulls
+                .insert(member.character_id, member.ship_type_id);
+            if let Some(character) = characters.get(&member.character_id) {
+                in_fleet
+                    .names
+                    .insert(member.character_id, character.name.clone());
+                in_fleet
+                    .ids_by_name
+                    .insert(character.name.to_lowercase(), member.character_id);
             }
         }
+        in_fleet
     }
-    
-    // If no delimiter found, try to split by multiple spaces or newlines
-    // This handles cases like "Character1  Character2" or names on separate lines
-    let parts: Vec<&str> = cleaned.split_whitespace().collect();
-    
-    // If we have multiple words, they might be separate character names
-    // But we need to be careful - single character names might have spaces
-    // For now, if there are multiple space-separated tokens, treat them as separate names
-    if parts.len() > 1 {
-        parts.into_iter().map(|s| s.to_string()).collect()
-    } else {
-        // Single name (might contain spaces, which is fine)
-        vec![cleaned]
-    }
-}
 
-fn extract_names_from_anchor_tags_after_role(motd: &str, role: &str, roles: &[&str]) -> Vec<String> {
-    let mut names = Vec::new();
-    let role_pattern = format!("{}:", role.to_lowercase());
-    let motd_lower = motd.to_lowercase();
-    
-    // Find the role pattern in the HTML - need to find it as a complete label
-    // Look for the pattern followed by whitespace, HTML tag, or end of text
-    // This might break btw if there is malformed tags or whatever. also you need the colon. 
-    let mut search_positions = Vec::new();
-    let mut search_start_pos = 0;
-    
-    while let Some(role_pos) = motd_lower[search_start_pos..].find(&role_pattern) {
-        let actual_pos = search_start_pos + role_pos;
-        let after_pattern = actual_pos + role_pattern.len();
-        
-        // Check if this is a valid role label (followed by whitespace, tag, or end)
-        if after_pattern >= motd.len() {
-            // End of string - valid
-            search_positions.push(actual_pos);
-            break;
-        } else {
-            let next_char = motd.chars().nth(after_pattern);
-            if let Some(ch) = next_char {
-                // Valid if followed by whitespace, HTML tag start, or closing tag
-                if ch.is_whitespace() || ch == '<' || ch == '>' {
-                    search_positions.push(actual_pos);
-                }
-            }
+    /// The pilot's character ID, if they are in the fleet. Typed in names are matched by name.
+    fn id_of(&self, assignee: &Assignee) -> Option<i64> {
+        match assignee.character_id {
+            Some(id) => self.hulls.get(&id).map(|_| id),
+            None => self.ids_by_name.get(&assignee.name.to_lowercase()).copied(),
         }
-        
-        search_start_pos = actual_pos + 1;
     }
-    
-    // Use the first valid match (should be the correct one)
-    if let Some(&role_pos) = search_positions.first() {
-        // Find the position after the role label
-        let search_start = role_pos + role_pattern.len();
-        
-        // Find where the next role starts (if any)
-        let mut search_end = motd.len();
-        for other_role in roles {
-            if other_role.to_lowercase() != role.to_lowercase() {
-                let other_pattern = format!("{}:", other_role.to_lowercase());
-                if let Some(other_pos) = motd_lower[search_start..].find(&other_pattern) {
-                    let actual_pos = search_start + other_pos;
-                    if actual_pos < search_end {
-                        search_end = actual_pos;
-                    }
-                }
-            }
-        }
-        
-        // Extract all <a> tag contents in this section
-        let section = &motd[search_start..search_end];
-        let mut chars = section.chars().peekable();
-        let mut in_anchor_tag = false;
-        let mut current_name = String::new();
-        let mut tag_depth = 0; // Track depth of nested tags inside anchor
-        
-        while let Some(ch) = chars.next() {
-            if ch == '<' {
-                // Check if this is an anchor tag
-                let mut is_closing = false;
-                let mut tag_name = String::new();
-                
-                // Check for closing tag
-                if chars.peek() == Some(&'/') {
-                    is_closing = true;
-                    chars.next(); // consume '/'
-                }
-                
-                // Read tag name until space or >
-                while let Some(&next_ch) = chars.peek() {
-                    if next_ch == ' ' || next_ch == '>' {
-                        break;
-                    }
-                    if let Some(c) = chars.next() {
-                        tag_name.push(c);
-                    }
-                }
-                
-                let tag_lower = tag_name.to_lowercase();
-                
-                // Skip rest of tag until '>'
-                while let Some(&next_ch) = chars.peek() {
-                    if next_ch == '>' {
-                        chars.next(); // consume '>'
-                        break;
-                    }
-                    chars.next(); // skip attribute content
-                }
-                
-                if tag_lower == "a" {
-                    if is_closing {
-                        // Closing </a> tag - save the name we collected
-                        if !current_name.trim().is_empty() {
-                            names.push(current_name.trim().to_string());
-                            current_name.clear();
-                        }
-                        in_anchor_tag = false;
-                        tag_depth = 0;
-                    } else {
-                        // Opening <a> tag
-                        in_anchor_tag = true;
-                        tag_depth = 1;
-                    }
-                } else if in_anchor_tag {
-                    // We're inside an anchor tag - handle nested tags
-                    if is_closing {
-                        tag_depth -= 1;
-                        // If we've closed all nested tags, we're back to anchor content
-                        if tag_depth <= 0 {
-                            tag_depth = 1; // Back to anchor tag level
-                        }
-                    } else {
-                        tag_depth += 1;
-                    }
-                }
-            } else if in_anchor_tag && tag_depth == 1 {
-                // We're inside an anchor tag and not in a nested tag - collect the character name
-                current_name.push(ch);
-            }
-        }
-        
-        // Don't forget the last name if section ends while in an anchor tag
-        if !current_name.trim().is_empty() {
-            names.push(current_name.trim().to_string());
-        }
-    }
-    
-    names.into_iter().filter(|s| !s.is_empty()).collect()
-}
 
-fn parse_motd_roles(motd: &str) -> HashMap<String, Vec<String>> {
-    let mut role_assignments: HashMap<String, Vec<String>> = HashMap::new();
-    let roles = vec!["DDD", "LR", "PS", "MS", "MTAC"];
-    
-    // First, try to extract names from <a> tags (HTML format)
-    for role in &roles {
-        let names = extract_names_from_anchor_tags_after_role(motd, role, &roles);
-        if !names.is_empty() {
-            role_assignments.insert(role.to_string(), names);
-            continue; // Skip the text-based parsing for this role
-        }
-    }
-    
-    // If we found roles via HTML, return early
-    if !role_assignments.is_empty() {
-        return role_assignments;
-    }
-    
-    // Fall back to text-based parsing (strip HTML first)
-    let motd_clean = strip_html_tags(motd);
-    
-    // Convert MOTD to lowercase for case-insensitive matching
-    let motd_lower = motd_clean.to_lowercase();
-    
-    for role in &roles {
-        let role_lower = role.to_lowercase();
-        let mut found_assignments = Vec::new();
-        
-        // Only match patterns with colon: "ROLE:"
-        let pattern = format!("{}:", role_lower);
-        
-        if let Some(pos) = motd_lower.find(&pattern) {
-            let start = pos + pattern.len();
-            let remaining = &motd_clean[start..];
-            
-            // Extract characters until newline, comma, or another role pattern
-            let mut char_end = remaining.len();
-            for (i, ch) in remaining.char_indices() {
-                if ch == '\n' || ch == '\r' {
-                    char_end = i;
-                    break;
-                }
-                // Check if we hit another role pattern with colon
-                for other_role in &roles {
-                    let other_role_lower = other_role.to_lowercase();
-                    let other_pattern = format!("{}:", other_role_lower);
-                    if i < remaining.len() && remaining[i..].to_lowercase().starts_with(&other_pattern) {
-                        char_end = i;
-                        break;
-                    }
-                }
-                if char_end != remaining.len() {
-                    break;
-                }
-            }
-            
-            let char_text = remaining[..char_end].trim();
-            if !char_text.is_empty() {
-                // Parse character names using flexible delimiter handling
-                let characters = parse_character_names(char_text);
-                
-                for char_name in characters {
-                    let char_name_clean = char_name.trim().to_string();
-                    if !char_name_clean.is_empty() && !found_assignments.contains(&char_name_clean) {
-                        found_assignments.push(char_name_clean);
-                    }
-                }
-            }
-        }
-        
-        // Also try to find role on its own line followed by character names
-        let lines: Vec<&str> = motd_clean.split('\n').collect();
-        for (i, line) in lines.iter().enumerate() {
-            let line_lower_str = line.to_lowercase();
-            let line_lower = line_lower_str.trim();
-            // Only match if line starts with "ROLE:"
-            if line_lower.starts_with(&format!("{}:", role_lower)) {
-                // Extract characters from the same line after the colon
-                let colon_pos = line_lower.find(':').unwrap_or(0);
-                let after_colon = &line[colon_pos + 1..].trim();
-                if !after_colon.is_empty() {
-                    let characters = parse_character_names(after_colon);
-                    for char_name in characters {
-                        let char_name_clean = char_name.trim().to_string();
-                        if !char_name_clean.is_empty() && !found_assignments.contains(&char_name_clean) {
-                            found_assignments.push(char_name_clean);
-                        }
-                    }
-                }
-                
-                // Check next lines for character names (if line only had the role)
-                let mut j = i + 1;
-                while j < lines.len() && j < i + 5 {
-                    let next_line = lines[j].trim();
-                    if !next_line.is_empty() {
-                        let next_line_lower = next_line.to_lowercase();
-                        // Check if this line contains another role with colon
-                        let mut is_role_line = false;
-                        for other_role in &roles {
-                            let other_role_lower = other_role.to_lowercase();
-                            if next_line_lower.starts_with(&format!("{}:", other_role_lower)) {
-                                is_role_line = true;
-                                break;
-                            }
-                        }
-                        if !is_role_line {
-                            let characters = parse_character_names(next_line);
-                            for char_name in characters {
-                                let char_name_clean = char_name.trim().to_string();
-                                if !char_name_clean.is_empty() && !found_assignments.contains(&char_name_clean) {
-                                    found_assignments.push(char_name_clean);
-                                }
-                            }
-                        } else {
-                            break;
-                        }
-                    }
-                    j += 1;
-                }
-            }
-        }
-        
-        if !found_assignments.is_empty() {
-            role_assignments.insert(role.to_string(), found_assignments);
+    fn check(&self, role: &MotdRole, assignee: Assignee) -> RoleAssignment {
+        let hull = self.id_of(&assignee).and_then(|id| self.hulls.get(&id).copied());
+        RoleAssignment {
+            name: assignee.name,
+            character_id: assignee.character_id,
+            in_fleet: hull.is_some(),
+            correct_hull: hull.map(|hull| role.allows_hull(hull)),
+            hull_id: hull.map(|hull| hull as i64),
         }
     }
-    
-    role_assignments
 }
 
-// Function to get allowed hulls for a role
-fn get_allowed_hulls_for_role(role: &str) -> Vec<&'static str> {
-    match role.to_uppercase().as_str() {
-        "DDD" => vec!["vindicator"],
-        "MS" => vec!["damnation", "eos", "claymore"], 
-        "LR" => vec!["kronos"],
-        "MTAC" => vec!["paladin", "vargur", "occator", "mastodon", "bustard", "impel"],
-        "PS" => vec!["paladin", "vargur"],
-        _ => vec![],
-    }
-}
-
-// Extract href from anchor tag for a character name in the MOTD
-fn extract_anchor_href(motd: &str, character_name: &str) -> Option<String> {
-    let name_lower = character_name.to_lowercase();
-    let motd_lower = motd.to_lowercase();
-    
-    // Find the character name in the MOTD (case-insensitive)
-    if let Some(name_pos) = motd_lower.find(&name_lower) {
-        // Look backwards from the name to find the opening <a> tag
-        let before_name = &motd[..name_pos];
-        if let Some(anchor_start) = before_name.rfind("<a") {
-            let anchor_section = &motd[anchor_start..];
-            // Extract href attribute
-            if let Some(href_start) = anchor_section.find("href=\"") {
-                let href_content_start = anchor_start + href_start + 6; // Skip "href=\""
-                if let Some(href_end) = motd[href_content_start..].find('"') {
-                    return Some(motd[href_content_start..href_content_start + href_end].to_string());
-                }
+fn check_assignments(
+    motd: &Motd,
+    roles: &[MotdRole],
+    in_fleet: &InFleet,
+) -> HashMap<String, Vec<RoleAssignment>> {
+    roles
+        .iter()
+        .filter_map(|role| {
+            let assignees = motd.assignments(&role.id);
+            if assignees.is_empty() {
+                return None;
             }
+            let assignments = assignees
+                .into_iter()
+                .map(|assignee| in_fleet.check(role, assignee))
+                .collect();
+            Some((role.id.clone(), assignments))
+        })
+        .collect()
+}
+
+fn check_stored(
+    assignments: &[Assignment],
+    roles: &[MotdRole],
+    in_fleet: &InFleet,
+) -> HashMap<String, Vec<RoleAssignment>> {
+    let mut checked: HashMap<String, Vec<RoleAssignment>> = HashMap::new();
+    for assignment in assignments {
+        if let Some(role) = roles.iter().find(|role| role.id == assignment.role) {
+            let assignee = Assignee {
+                name: assignment.name.clone(),
+                character_id: Some(assignment.character_id),
+            };
+            checked
+                .entry(role.id.clone())
+                .or_default()
+                .push(in_fleet.check(role, assignee));
         }
     }
-    None
+    checked
 }
 
-// Remove characters not in fleet from all role sections - surgically, preserving HTML structure
-fn remove_characters_not_in_fleet_surgical(
-    motd: &str,
-    fleet_member_names: &HashSet<String>,
-) -> String {
-    let roles = vec!["DDD", "MS", "LR", "MTAC", "PS"];
-    let mut result = motd.to_string();
-    
-    // Process roles in reverse order to avoid position shifts
-    for role in roles.iter().rev() {
-        let role_pattern = format!("{}:", role.to_lowercase());
-        let mut result_lower = result.to_lowercase();
-        
-        // Keep processing until no more characters to remove
-        loop {
-            let mut found_any = false;
-            
-            // Use regex to find role pattern with word boundaries to avoid false matches
-            use regex::Regex;
-            let role_regex = Regex::new(&format!(r"(?i)\b{}:\s*", regex::escape(role))).unwrap();
-            
-            if let Some(role_match) = role_regex.find(&result_lower) {
-                let role_start = role_match.end();
-                let mut role_end = result.len();
-                
-                // Find where this role section ends - look for next role pattern with word boundaries
-                for other_role in &roles {
-                    if other_role.to_lowercase() != role.to_lowercase() {
-                        let other_pattern_regex = Regex::new(&format!(r"(?i)\b{}:\s*", regex::escape(other_role))).unwrap();
-                        if let Some(other_match) = other_pattern_regex.find(&result_lower[role_start..]) {
-                            let actual_pos = role_start + other_match.start();
-                            if actual_pos < role_end {
-                                role_end = actual_pos;
-                            }
-                        }
-                    }
-                }
-                
-                // Get all character names in this role section
-                let parsed_names = extract_names_from_anchor_tags_after_role(&result, role, &roles);
-                
-                // For each character not in fleet, surgically remove their anchor tag
-                for name in &parsed_names {
-                    let name_trimmed = name.trim();
-                    let name_lower = name_trimmed.to_lowercase();
-                    
-                    if !fleet_member_names.contains(&name_lower) {
-                        let section = &result[role_start..role_end];
-                        
-                        // Find all anchor tags in the section and match by exact name content
-                        // Search only within the role section, not the entire MOTD
-                        let mut anchor_start = 0;
-                        let mut anchor_count = 0;
-                        while let Some(anchor_pos) = section[anchor_start..].find("<a") {
-                            anchor_count += 1;
-                            // anchor_pos is relative to anchor_start in section
-                            // section starts at role_start, so absolute position is:
-                            let anchor_abs_pos = role_start + anchor_start + anchor_pos;
-                            
-                            // Make sure we don't go beyond role_end
-                            if anchor_abs_pos >= role_end {
-                                break;
-                            }
-                            
-                            // Limit the search to only within the role section
-                            let anchor_section = &result[anchor_abs_pos..role_end];
-                            
-                            if let Some(anchor_tag_end) = anchor_section.find('>') {
-                                let anchor_tag_end_absolute = anchor_abs_pos + anchor_tag_end + 1;
-                                
-                                // Find the closing </a> tag - search only within role section
-                                // anchor_tag_end is relative to anchor_section, which starts at anchor_abs_pos
-                                // So we need to search from anchor_tag_end position in anchor_section
-                                let search_start = anchor_tag_end;
-                                if let Some(anchor_close) = anchor_section[search_start..].find("</a>") {
-                                    // anchor_close is relative to search_start, which is relative to anchor_section start (anchor_abs_pos)
-                                    let anchor_close_absolute = anchor_abs_pos + search_start + anchor_close;
-                                    
-                                    // Make sure the entire anchor tag is within the role section
-                                    // anchor_close_absolute points to the '<' of '</a>', so +4 gives us the position after '</a>'
-                                    let anchor_end_absolute = anchor_close_absolute + 4; // +4 for "</a>"
-                                    
-                                    // Verify we're not going beyond the role section
-                                    if anchor_end_absolute > role_end {
-                                        // This anchor extends beyond the role section, skip it
-                                        anchor_start += anchor_pos + 2;
-                                        continue;
-                                    }
-                                    
-                                    if anchor_end_absolute > role_end {
-                                        // This anchor extends beyond the role section, skip it
-                                        anchor_start += anchor_pos + 2;
-                                        continue;
-                                    }
-                                    
-                                    // Extract the name from this anchor tag
-                                    let anchor_content = &result[anchor_tag_end_absolute..anchor_close_absolute];
-                                    
-                                    // Strip HTML tags from the anchor content for comparison
-                                    let mut stripped_name = String::new();
-                                    let mut in_tag = false;
-                                    for ch in anchor_content.chars() {
-                                        if ch == '<' {
-                                            in_tag = true;
-                                        } else if ch == '>' {
-                                            in_tag = false;
-                                        } else if !in_tag {
-                                            stripped_name.push(ch);
-                                        }
-                                    }
-                                    let stripped_name_lower = stripped_name.trim().to_lowercase();
-                                    
-                                    // Match if the stripped name matches (case-insensitive)
-                                    if stripped_name_lower == name_lower {
-                                        // Found the matching anchor tag - remove it
-                                        // anchor_close_absolute points to the '<' of '</a>'
-                                        // So anchor_close_absolute + 4 points to the position AFTER '</a>'
-                                        let anchor_end_absolute = anchor_close_absolute + 4; // +4 for "</a>"
-                                        
-                                        // Make sure we don't go beyond the string length
-                                        let actual_end = anchor_end_absolute.min(result.len());
-                                        let after_anchor = &result[actual_end..];
-                                        
-                                        let mut new_result = String::new();
-                                        new_result.push_str(&result[..anchor_abs_pos]);
-                                        
-                                        // Check what comes after the anchor - preserve delimiters appropriately
-                                        let trimmed_after = after_anchor.trim_start();
-                                        
-                                        // Skip leading comma and/or space after the anchor
-                                        let mut skip = after_anchor.len() - trimmed_after.len();
-                                        if trimmed_after.starts_with(',') {
-                                            skip += 1;
-                                            if trimmed_after.len() > 1 && trimmed_after.chars().nth(1) == Some(' ') {
-                                                skip += 1;
-                                            }
-                                        }
-                                        
-                                        // Use actual_end instead of anchor_end_absolute to avoid going beyond string length
-                                        new_result.push_str(&result[actual_end + skip..]);
-                                        
-                                        result = new_result;
-                                        result_lower = result.to_lowercase();
-                                        found_any = true;
-                                        break; // Break inner loop, will continue outer loop
-                                    }
-                                }
-                            }
-                            anchor_start += anchor_pos + 2;
-                        }
-                        
-                        if found_any {
-                            break; // Break out of name loop to restart role processing
-                        }
-                    }
-                }
-            }
-            
-            if !found_any {
-                break; // No more characters to remove for this role
-            }
-        }
-    }
-    
-    result
+#[derive(Debug, Serialize)]
+struct FleetCompWing {
+    id: i64,
+    name: String,
+    squads: Vec<FleetCompSquadMembers>,
+    member: Option<FleetMember>,
 }
 
-// Add or replace a character in a specific role section of the MOTD
-// This function surgically replaces only the character name and ID in the anchor tag
-fn add_character_to_motd_role(
-    original_motd: &str,
-    role: &str,
-    character_name: &str,
-    character_id: i64,
-    character_name_to_id: &HashMap<String, i64>,
-    fleet_member_names: &HashSet<String>,
-) -> String {
-    let roles = vec!["DDD", "MS", "LR", "MTAC", "PS"];
-    let role_pattern = format!("{}:", role.to_lowercase());
-    let motd_lower = original_motd.to_lowercase();
-    let character_name_lower = character_name.to_lowercase();
-    
-    // Find the role section
-    if let Some(role_pos) = motd_lower.find(&role_pattern) {
-        // Find where this role section ends (next role or end of MOTD)
-        let role_start = role_pos + role_pattern.len();
-        let mut role_end = original_motd.len();
-        
-        for other_role in &roles {
-            if other_role.to_lowercase() != role.to_lowercase() {
-                let other_pattern = format!("{}:", other_role.to_lowercase());
-                if let Some(other_pos) = motd_lower[role_start..].find(&other_pattern) {
-                    let actual_pos = role_start + other_pos;
-                    if actual_pos < role_end {
-                        role_end = actual_pos;
-                    }
-                }
-            }
-        }
-        
-        // Extract the role section
-        let role_section = &original_motd[role_start..role_end];
-        let section_lower = role_section.to_lowercase();
-        
-        let has_anchor_tags = role_section.contains("<a");
-        
-        let mut character_found = false;
-        let mut existing_name_in_motd = None;
-        let mut existing_characters_in_fleet = Vec::new(); // Track characters in fleet in this role
-        let mut anchor_start = 0;
-        while let Some(anchor_pos) = role_section[anchor_start..].find("<a") {
-            let anchor_abs_pos = role_start + anchor_start + anchor_pos;
-            let anchor_section = &original_motd[anchor_abs_pos..];
-            if let Some(anchor_close) = anchor_section.find('>') {
-                let name_start = anchor_abs_pos + anchor_close + 1;
-                if let Some(name_end) = original_motd[name_start..].find('<') {
-                    let name_in_motd = &original_motd[name_start..name_start + name_end];
-                    let name_in_motd_lower = name_in_motd.to_lowercase();
-                    
-                    // Check if this is the character we're adding
-                    if name_in_motd_lower == character_name_lower {
-                        character_found = true;
-                        existing_name_in_motd = Some(name_in_motd);
-                    }
-                    
-                    // Track if this character is in fleet
-                    if fleet_member_names.contains(&name_in_motd_lower) {
-                        existing_characters_in_fleet.push(anchor_abs_pos);
-                    }
-                }
-            }
-            anchor_start += anchor_pos + 2;
-        }
-        
-        // If character is already in the role, update them (replace)
-        if has_anchor_tags && character_found {
-            let name_to_find = existing_name_in_motd.unwrap();
-            if let Some(name_pos_in_section) = section_lower.find(&name_to_find.to_lowercase()) {
-            let name_pos_approx = role_start + name_pos_in_section;
-            let before_name = &original_motd[role_start..name_pos_approx];
-            if let Some(anchor_start_rel) = before_name.rfind("<a") {
-                let anchor_start_absolute = role_start + anchor_start_rel;
-                
-                let anchor_section = &original_motd[anchor_start_absolute..];
-                if let Some(anchor_tag_end) = anchor_section.find('>') {
-                    let anchor_tag_end_absolute = anchor_start_absolute + anchor_tag_end + 1;
-                    let anchor_content = &original_motd[anchor_tag_end_absolute..];
-                    let name_start = if let Some(name_pos_in_anchor) = anchor_content.find(character_name) {
-                        anchor_tag_end_absolute + name_pos_in_anchor
-                    } else {
-                        name_pos_approx
-                    };
-                    let name_end = name_start + character_name.len();
-                    
-                    if let Some(href_start) = anchor_section.find("href=\"") {
-                        let href_content_start = anchor_start_absolute + href_start + 6;
-                        
-                        if let Some(href_end) = original_motd[href_content_start..].find('"') {
-                            let href_end_absolute = href_content_start + href_end;
-                            let href_content = &original_motd[href_content_start..href_end_absolute];
-                            
-                            if let Some(id_start_in_href) = href_content.find("//") {
-                                let id_start_absolute = href_content_start + id_start_in_href + 2;
-                                let id_end_absolute = href_end_absolute;
-                                
-                                let anchor_tag_close = anchor_start_absolute + anchor_section.find('>').unwrap() + 1;
-                                let anchor_content_after_gt = &original_motd[anchor_tag_close..];
-                                let name_start_exact = if let Some(name_pos) = anchor_content_after_gt.find(character_name) {
-                                    anchor_tag_close + name_pos
-                                } else {
-                                    name_start
-                                };
-                                let name_end_exact = name_start_exact + character_name.len();
-                                
-                                let mut result = String::new();
-                                result.push_str(&original_motd[..id_start_absolute]);
-                                result.push_str(&character_id.to_string());
-                                result.push_str(&original_motd[id_end_absolute..anchor_tag_close]);
-                                result.push_str(&original_motd[anchor_tag_close..name_start_exact]);
-                                result.push_str(character_name);
-                                result.push_str(&original_motd[name_end_exact..]);
-                                
-                                return result;
-                            }
-                        }
-                    }
-                }
-            }
-            }
-        }
-        
-        // If character is NOT in the role, but there are existing characters in fleet, add after the last one
-        if has_anchor_tags && !character_found && !existing_characters_in_fleet.is_empty() {
-            // Find the last character in fleet (the rightmost one)
-            let last_char_pos = *existing_characters_in_fleet.iter().max().unwrap();
-            
-            // Find the end of that anchor tag
-            let anchor_section = &original_motd[last_char_pos..];
-            if let Some(anchor_close) = anchor_section.find("</a>") {
-                let insert_pos = last_char_pos + anchor_close + 4; // +4 for "</a>"
-                
-                // Check what comes after the last anchor
-                let after_last_anchor = &original_motd[insert_pos..role_end.min(original_motd.len())];
-                let trimmed_after = after_last_anchor.trim_start();
-                
-                // Determine delimiter - use space if there's no comma, otherwise use ", "
-                let delimiter = if trimmed_after.starts_with(',') {
-                    ", "
-                } else if trimmed_after.is_empty() || trimmed_after.starts_with('<') {
-                    " "
-                } else {
-                    " "
-                };
-                
-                // Get showinfo type from existing anchors
-                let showinfo_type = if let Some(existing_anchor) = role_section.find("<a") {
-                    let anchor_part = &role_section[existing_anchor..];
-                    if let Some(href_start) = anchor_part.find("href=\"") {
-                        let href_content = &anchor_part[href_start + 6..];
-                        if let Some(href_end) = href_content.find('"') {
-                            let href = &href_content[..href_end];
-                            if let Some(type_start) = href.find("showinfo:") {
-                                let after_showinfo = &href[type_start + 9..];
-                                if let Some(type_end) = after_showinfo.find("//") {
-                                    &after_showinfo[..type_end]
-                                } else {
-                                    "1377"
-                                }
-                            } else {
-                                "1377"
-                            }
-                        } else {
-                            "1377"
-                        }
-                    } else {
-                        "1377"
-                    }
-                } else {
-                    "1377"
-                };
-                
-                let mut result = String::new();
-                result.push_str(&original_motd[..insert_pos]);
-                result.push_str(delimiter);
-                let href = format!("showinfo:{}//{}", showinfo_type, character_id);
-                result.push_str(&format!("<a href=\"{}\">{}</a>", href, character_name));
-                result.push_str(&original_motd[insert_pos..]);
-                
-                return result;
-            }
-        }
-        
-        if !has_anchor_tags {
-            let role_content = &original_motd[role_start..role_end];
-            let trimmed_content = role_content.trim();
-            let has_content = !trimmed_content.is_empty();
-            
-            let mut content_start = role_start;
-            for (idx, ch) in role_content.char_indices() {
-                if !ch.is_whitespace() {
-                    content_start = role_start + idx;
-                    break;
-                }
-            }
-            
-            let showinfo_type = "1377";
-            let mut result = String::new();
-            result.push_str(&original_motd[..role_start]);
-            result.push(' ');
-            
-            let href = format!("showinfo:{}//{}", showinfo_type, character_id);
-            result.push_str(&format!("<a href=\"{}\">{}</a>", href, character_name));
-            result.push_str("<br>");
-            result.push_str(&original_motd[role_end..]);
-            
-            return result;
-        }
-        
-        // If there's only one anchor tag and the character is not found, check if that character is in fleet
-        let anchor_count = role_section.matches("<a").count();
-        
-        if anchor_count == 1 && !character_found {
-            if let Some(anchor_start_rel) = role_section.find("<a") {
-                let anchor_start_absolute = role_start + anchor_start_rel;
-                let anchor_section = &original_motd[anchor_start_absolute..];
-                if let Some(anchor_tag_end) = anchor_section.find('>') {
-                    let anchor_tag_end_absolute = anchor_start_absolute + anchor_tag_end + 1;
-                    if let Some(anchor_close) = anchor_section.find("</a>") {
-                        let anchor_close_absolute = anchor_start_absolute + anchor_close;
-                        
-                        // Extract the existing character's name
-                        let existing_name = &original_motd[anchor_tag_end_absolute..anchor_close_absolute];
-                        // Strip HTML tags from the name
-                        let mut existing_name_clean = String::new();
-                        let mut in_tag = false;
-                        for ch in existing_name.chars() {
-                            if ch == '<' {
-                                in_tag = true;
-                            } else if ch == '>' {
-                                in_tag = false;
-                            } else if !in_tag {
-                                existing_name_clean.push(ch);
-                            }
-                        }
-                        let existing_name_lower = existing_name_clean.trim().to_lowercase();
-                        
-                        // If the existing character is in fleet, add the new character after them
-                        if fleet_member_names.contains(&existing_name_lower) {
-                            let insert_pos = anchor_close_absolute + 4; // +4 for "</a>"
-                            
-                            let delimiter = " ";
-                            
-                            // Get showinfo type from existing anchor
-                            let showinfo_type = if let Some(href_start) = anchor_section.find("href=\"") {
-                                let href_content = &anchor_section[href_start + 6..];
-                                if let Some(href_end) = href_content.find('"') {
-                                    let href = &href_content[..href_end];
-                                    if let Some(type_start) = href.find("showinfo:") {
-                                        let after_showinfo = &href[type_start + 9..];
-                                        if let Some(type_end) = after_showinfo.find("//") {
-                                            &after_showinfo[..type_end]
-                                        } else {
-                                            "1377"
-                                        }
-                                    } else {
-                                        "1377"
-                                    }
-                                } else {
-                                    "1377"
-                                }
-                            } else {
-                                "1377"
-                            };
-                            
-                            let mut result = String::new();
-                            result.push_str(&original_motd[..insert_pos]);
-                            result.push_str(delimiter);
-                            let href = format!("showinfo:{}//{}", showinfo_type, character_id);
-                            result.push_str(&format!("<a href=\"{}\">{}</a>", href, character_name));
-                            result.push_str(&original_motd[insert_pos..]);
-                            
-                            return result;
-                        } else {
-                            // Existing character is not in fleet, replace them
-                            if let Some(href_start) = anchor_section.find("href=\"") {
-                                let href_content_start = anchor_start_absolute + href_start + 6;
-                                if let Some(href_end) = original_motd[href_content_start..].find('"') {
-                                    let href_end_absolute = href_content_start + href_end;
-                                    let href_content = &original_motd[href_content_start..href_end_absolute];
-                                    if let Some(id_start_in_href) = href_content.find("//") {
-                                        let id_start_absolute = href_content_start + id_start_in_href + 2;
-                                        let id_end_absolute = href_end_absolute;
-                                        let name_start = anchor_tag_end_absolute;
-                                        let name_end = anchor_close_absolute;
-                                        
-                                        let mut result = String::new();
-                                        result.push_str(&original_motd[..id_start_absolute]);
-                                        result.push_str(&character_id.to_string());
-                                        result.push_str(&original_motd[id_end_absolute..name_start]);
-                                        result.push_str(character_name);
-                                        result.push_str(&original_motd[name_end..]);
-                                        return result;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        
-        // Multiple characters - add to the end
-        // Find the last </a> tag in the section
-        if let Some(last_anchor_end) = role_section.rfind("</a>") {
-            let insert_pos = role_start + last_anchor_end + 4;
-            
-            let after_last_anchor = &original_motd[insert_pos..role_end];
-            let delimiter = if after_last_anchor.trim_start().starts_with(',') {
-                ", "
-            } else if after_last_anchor.trim_start().is_empty() || after_last_anchor.trim_start().starts_with('<') {
-                ""
-            } else {
-                ", "
-            };
-            let showinfo_type = if let Some(existing_anchor) = role_section.find("<a") {
-                let anchor_part = &role_section[existing_anchor..];
-                if let Some(href_start) = anchor_part.find("href=\"") {
-                    let href_content = &anchor_part[href_start + 6..];
-                    if let Some(href_end) = href_content.find('"') {
-                        let href = &href_content[..href_end];
-                        if let Some(type_start) = href.find("showinfo:") {
-                            let after_showinfo = &href[type_start + 9..];
-                            if let Some(type_end) = after_showinfo.find("//") {
-                                &after_showinfo[..type_end]
-                            } else {
-                                "1377"
-                            }
-                        } else {
-                            "1377"
-                        }
-                    } else {
-                        "1377"
-                    }
-                } else {
-                    "1377"
-                }
-            } else {
-                "1377"
-            };
-            
-            let mut result = String::new();
-            result.push_str(&original_motd[..insert_pos]);
-            result.push_str(delimiter);
-            let href = format!("showinfo:{}//{}", showinfo_type, character_id);
-            result.push_str(&format!("<a href=\"{}\">{}</a>", href, character_name));
-            result.push_str(&original_motd[insert_pos..]);
-            
-            return result;
-        }
-    }
-    
-    // Role not found, append it at the end
-    let mut result = original_motd.to_string();
-    if !result.trim().is_empty() && !result.trim_end().ends_with('\n') {
-        result.push('\n');
-    }
-    result.push_str(&format!("{}: ", role.to_uppercase()));
-    let href = format!("showinfo:1377//{}", character_id);
-    result.push_str(&format!("<a href=\"{}\">{}</a>", href, character_name));
-    result.push('\n');
-    
-    result
+#[derive(Debug, Serialize)]
+struct FleetCompSquadMembers {
+    id: i64,
+    name: String,
+    members: Vec<FleetMember>,
 }
 
-// Reconstruct MOTD by removing characters not in fleet and updating the specific role
-fn reconstruct_motd(
-    original_motd: &str,
-    role_assignments: &HashMap<String, Vec<String>>,
-    character_name_to_id: &HashMap<String, i64>,
-    fleet_member_names: &HashSet<String>,
-    updated_role: Option<&str>,
-    updated_character: Option<&str>,
-) -> String {
-    let roles = vec!["DDD", "MS", "LR", "MTAC", "PS"];
-    let mut result = original_motd.to_string();
-    
-    // If we're updating a specific role, use the surgical approach
-    if let (Some(role), Some(character)) = (updated_role, updated_character) {
-        if let Some(&char_id) = character_name_to_id.get(&character.to_lowercase()) {
-            result = add_character_to_motd_role(
-                &result,
-                role,
-                character,
-                char_id,
-                character_name_to_id,
-                fleet_member_names,
-            );
-        }
-    }
-    
-    // Remove characters not in fleet from all role sections
-    for role in &roles {
-        let role_pattern = format!("{}:", role.to_lowercase());
-        let result_lower = result.to_lowercase();
-        
-        if let Some(role_pos) = result_lower.find(&role_pattern) {
-            let role_start = role_pos + role_pattern.len();
-            let mut role_end = result.len();
-            
-            // Find where this role section ends
-            for other_role in &roles {
-                if other_role.to_lowercase() != role.to_lowercase() {
-                    let other_pattern = format!("{}:", other_role.to_lowercase());
-                    if let Some(other_pos) = result_lower[role_start..].find(&other_pattern) {
-                        let actual_pos = role_start + other_pos;
-                        if actual_pos < role_end {
-                            role_end = actual_pos;
-                        }
-                    }
-                }
-            }
-            
-            let role_section = &result[role_start..role_end];
-            let mut new_section = String::new();
-            let mut in_anchor = false;
-            let mut current_anchor = String::new();
-            let mut current_name = String::new();
-            let mut tag_depth = 0;
-            
-            // Parse through the section and keep only characters in fleet
-            let mut chars = role_section.chars().peekable();
-            while let Some(ch) = chars.next() {
-                if ch == '<' {
-                    let mut is_closing = false;
-                    let mut tag_name = String::new();
-                    
-                    if chars.peek() == Some(&'/') {
-                        is_closing = true;
-                        chars.next();
-                    }
-                    
-                    while let Some(&next_ch) = chars.peek() {
-                        if next_ch == ' ' || next_ch == '>' {
-                            break;
-                        }
-                        if let Some(c) = chars.next() {
-                            tag_name.push(c);
-                        }
-                    }
-                    
-                    while let Some(&next_ch) = chars.peek() {
-                        if next_ch == '>' {
-                            chars.next();
-                            break;
-                        }
-                        chars.next();
-                    }
-                    
-                    let tag_lower = tag_name.to_lowercase();
-                    
-                    if tag_lower == "a" {
-                        if is_closing {
-                            // Closing </a> tag - check if we should keep this character
-                            if !current_name.trim().is_empty() {
-                                if fleet_member_names.contains(&current_name.trim().to_lowercase()) {
-                                    new_section.push_str(&current_anchor);
-                                    new_section.push_str("</a>");
-                                }
-                            }
-                            current_anchor.clear();
-                            current_name.clear();
-                            in_anchor = false;
-                            tag_depth = 0;
-                        } else {
-                            // Opening <a> tag
-                            in_anchor = true;
-                            tag_depth = 1;
-                            current_anchor.push(ch);
-                            current_anchor.push(if is_closing { '/' } else { ' ' });
-                            // Reconstruct the opening tag
-                            let mut tag_rebuild = String::from("<");
-                            if is_closing {
-                                tag_rebuild.push('/');
-                            }
-                            tag_rebuild.push_str(&tag_name);
-                            // Skip to '>'
-                            let mut attr_part = String::new();
-                            while let Some(&next_ch) = chars.peek() {
-                                if next_ch == '>' {
-                                    chars.next();
-                                    attr_part.push(next_ch);
-                                    break;
-                                }
-                                if let Some(c) = chars.next() {
-                                    attr_part.push(c);
-                                }
-                            }
-                            current_anchor = format!("<{}", tag_name);
-                            current_anchor.push_str(&attr_part);
-                        }
-                    } else if in_anchor {
-                        current_anchor.push(ch);
-                        if is_closing {
-                            tag_depth -= 1;
-                        } else {
-                            tag_depth += 1;
-                        }
-                    } else {
-                        new_section.push(ch);
-                    }
-                } else if in_anchor && tag_depth == 1 {
-                    current_name.push(ch);
-                    current_anchor.push(ch);
-                } else {
-                    if in_anchor {
-                        current_anchor.push(ch);
-                    } else {
-                        new_section.push(ch);
-                    }
-                }
-            }
-            
-            // Replace the role section in result
-            let mut new_result = String::new();
-            new_result.push_str(&result[..role_start]);
-            new_result.push_str(&new_section);
-            new_result.push_str(&result[role_end..]);
-            result = new_result;
-        }
-    }
-    
-    result
+#[derive(Debug, Serialize)]
+struct FleetMember {
+    id: i64,
+    name: Option<String>,
+    ship: Hull,
+    role: String,
 }
 
 #[get("/api/fleet/fleetcomp?<character_id>")]
@@ -1304,22 +289,7 @@ async fn fleet_composition(
     let character_ids: Vec<_> = members.iter().map(|member| member.character_id).collect();
     let mut characters = crate::data::character::lookup(app.get_db(), &character_ids).await?;
     
-    // Create a set of fleet member names (case-insensitive) for checking MOTD assignments
-    let fleet_member_names: std::collections::HashSet<String> = characters
-        .values()
-        .map(|char| char.name.to_lowercase())
-        .collect();
-    
-    // Create a mapping of character names to hull names and IDs (case-insensitive)
-    let mut character_to_hull: HashMap<String, String> = HashMap::new();
-    let mut character_to_hull_id: HashMap<String, i64> = HashMap::new();
-    for member in &members {
-        if let Some(character) = characters.get(&member.character_id) {
-            let hull_name = TypeDB::name_of(member.ship_type_id).unwrap();
-            character_to_hull.insert(character.name.to_lowercase(), hull_name.to_lowercase());
-            character_to_hull_id.insert(character.name.to_lowercase(), member.ship_type_id as i64);
-        }
-    }
+    let in_fleet = InFleet::new(&members, &characters);
     let fleet_commander = members
         .iter()
         .find(|member| member.role == "fleet_commander")
@@ -1378,65 +348,34 @@ async fn fleet_composition(
         })
         .collect();
 
-    // Fetch fleet MOTD and parse role assignments
-    let role_assignments = match crate::core::esi::fleet_info::get(&app.esi_client, fleet_id, fleet.boss_id).await {
-        Ok(fleet_info) => {
-            if fleet_info.motd.is_empty() {
-                None
-            } else {
-                let parsed = parse_motd_roles(&fleet_info.motd);
-                if parsed.is_empty() {
-                    None
-                } else {
-                    // Convert parsed names to RoleAssignment structs, checking fleet membership and hull
-                    let mut role_assignments_with_status: HashMap<String, Vec<RoleAssignment>> = HashMap::new();
-                    for (role, names) in parsed {
-                        let allowed_hulls: Vec<String> = get_allowed_hulls_for_role(&role)
-                            .into_iter()
-                            .map(|s| s.to_lowercase())
-                            .collect();
-                        
-                        let assignments: Vec<RoleAssignment> = names
-                            .into_iter()
-                            .map(|name| {
-                                let name_lower = name.to_lowercase();
-                                let in_fleet = fleet_member_names.contains(&name_lower);
-                                
-                                let (correct_hull, hull_id) = if in_fleet {
-                                    // Check if the character's hull matches the allowed hulls for this role
-                                    if let Some(hull_name) = character_to_hull.get(&name_lower) {
-                                        let is_correct = allowed_hulls.contains(hull_name);
-                                        let ship_id = character_to_hull_id.get(&name_lower).copied();
-                                        (Some(is_correct), ship_id)
-                                    } else {
-                                        (Some(false), None) // In fleet but hull not found (shouldn't happen)
-                                    }
-                                } else {
-                                    (None, None) // Not in fleet, so hull check doesn't apply
-                                };
-                                
-                                RoleAssignment {
-                                    name,
-                                    in_fleet,
-                                    correct_hull,
-                                    hull_id,
-                                }
-                            })
-                            .collect();
-                        role_assignments_with_status.insert(role, assignments);
-                    }
-                    Some(role_assignments_with_status)
+    // Role assignments are a nice to have, the fleet is shown without them. Until roles are
+    // handed out through us the MOTD is all we have to go on.
+    let motd_roles = motd_roles::roles();
+    let session_id = fleet_roles::session_id(app.get_db(), fleet_id).await?;
+    let role_assignments = match session_id {
+        Some(session_id) if fleet_roles::any(app.get_db(), session_id).await? => {
+            let assignments = fleet_roles::current(app.get_db(), session_id).await?;
+            Some(check_stored(&assignments, &motd_roles, &in_fleet))
+        }
+        _ => {
+            match crate::core::esi::fleet_info::get(&app.esi_client, fleet_id, fleet.boss_id).await
+            {
+                Ok(info) => {
+                    let motd = Motd::parse(&info.motd, &motd_roles);
+                    Some(check_assignments(&motd, &motd_roles, &in_fleet))
                 }
+                Err(_) => None,
             }
         }
-        Err(_) => None, // Gracefully handle errors - if MOTD fetch fails, return None
-    };
+    }
+    .filter(|a| !a.is_empty());
 
     Ok(Json(FleetCompResponse {
         wings,
         id: fleet_id,
         member: fleet_commander,
         role_assignments,
+        motd_roles,
     }))
 }
 
@@ -1528,32 +467,36 @@ struct RegisterRequest {
     assignments: HashMap<String, (i64, i64)>,
 }
 
-#[post("/api/fleet/register", data = "<input>")]
-async fn register_fleet(
+async fn save_registration(
     app: &rocket::State<Application>,
-    account: AuthenticatedAccount,
-    input: Json<RegisterRequest>,
-) -> Result<&'static str, Madness> {
-    account.require_access("fleet-configure")?;
-    authorize_character(app.get_db(), &account, input.character_id, None).await?;
-
+    character_id: i64,
+    fleet_id: i64,
+    assignments: &HashMap<String, (i64, i64)>,
+) -> Result<(), Madness> {
     let mut tx = app.get_db().begin().await?;
-    sqlx::query!("DELETE FROM fleet_squad WHERE fleet_id=?", input.fleet_id)
+    sqlx::query!("DELETE FROM fleet_squad WHERE fleet_id=?", fleet_id)
         .execute(&mut tx)
         .await?;
     sqlx::query!(
         "REPLACE INTO fleet (id, boss_id) VALUES (?, ?)",
-        input.fleet_id,
-        input.character_id
+        fleet_id,
+        character_id
     )
     .execute(&mut tx)
     .await?;
+    crate::data::fleet_session::start(
+        &mut tx,
+        fleet_id,
+        character_id,
+        chrono::Utc::now().timestamp(),
+    )
+    .await?;
 
     let categories = crate::data::categories::categories();
-    for category in categories {
-        if let Some((wing_id, squad_id)) = input.assignments.get(&category.id) {
+    for category in &categories {
+        if let Some((wing_id, squad_id)) = assignments.get(&category.id) {
             sqlx::query!("INSERT INTO fleet_squad (fleet_id, wing_id, squad_id, category) VALUES (?, ?, ?, ?)",
-            input.fleet_id, wing_id, squad_id, category.id).execute(&mut tx).await?;
+            fleet_id, wing_id, squad_id, category.id).execute(&mut tx).await?;
         } else {
             return Err(Madness::BadRequest(format!(
                 "Missing assignment for {}",
@@ -1561,8 +504,181 @@ async fn register_fleet(
             )));
         }
     }
+    // Alts are invited by the x-up's alt flag, so their squad isn't one of the categories
+    if !categories
+        .iter()
+        .any(|category| category.id == ALT_CATEGORY)
+    {
+        if let Some((wing_id, squad_id)) = assignments.get(ALT_CATEGORY) {
+            sqlx::query!("INSERT INTO fleet_squad (fleet_id, wing_id, squad_id, category) VALUES (?, ?, ?, ?)",
+            fleet_id, wing_id, squad_id, ALT_CATEGORY).execute(&mut tx).await?;
+        }
+    }
 
     tx.commit().await?;
+    Ok(())
+}
+
+#[post("/api/fleet/register", data = "<input>")]
+async fn register_fleet(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    input: Json<RegisterRequest>,
+) -> Result<&'static str, Madness> {
+    account.require_access("fleet-configure")?;
+    authorize_character(app.get_db(), &account, input.character_id, None).await?;
+
+    save_registration(app, input.character_id, input.fleet_id, &input.assignments).await?;
+
+    Ok("OK")
+}
+
+const ALT_CATEGORY: &str = "alt";
+// ESI doesn't allow more squads in a wing
+const SQUADS_PER_WING: usize = 5;
+// ESI rejects longer wing and squad names
+const MAX_NAME_LENGTH: usize = 10;
+
+fn truncate_name(name: &str, length: usize) -> String {
+    name.chars().take(length).collect()
+}
+
+/// The squads auto-configure creates: one per category, named like it, plus one for alts.
+/// Grouped into as many wings as that takes.
+fn squad_template(categories: &[WaitlistCategory]) -> Vec<(String, Vec<WaitlistCategory>)> {
+    let mut squads = categories.to_vec();
+    if !squads.iter().any(|category| category.id == ALT_CATEGORY) {
+        squads.push(WaitlistCategory {
+            id: ALT_CATEGORY.to_string(),
+            name: "Alts".to_string(),
+        });
+    }
+
+    let wings = (squads.len() + SQUADS_PER_WING - 1) / SQUADS_PER_WING;
+    squads
+        .chunks(SQUADS_PER_WING)
+        .enumerate()
+        .map(|(i, squads)| {
+            let name = match wings {
+                1 => "Waitlist".to_string(),
+                _ => {
+                    // Shorten the prefix rather than the number, so the names stay unique
+                    let number = format!(" {}", i + 1);
+                    let prefix = truncate_name("Waitlist", MAX_NAME_LENGTH - number.len());
+                    prefix + &number
+                }
+            };
+            (name, squads.to_vec())
+        })
+        .collect()
+}
+
+/// Creates the template's wings and squads, returning the squad of each category. Squads are
+/// named after their category, cut down to what ESI accepts. The IDs of the wings are added to
+/// `wing_ids` as they are created, so they can be cleaned up on error.
+async fn create_squads(
+    app: &rocket::State<Application>,
+    fleet_id: i64,
+    boss_id: i64,
+    template: &[(String, Vec<WaitlistCategory>)],
+    wing_ids: &mut Vec<i64>,
+) -> Result<HashMap<String, (i64, i64)>, Madness> {
+    for (name, _squads) in template {
+        wing_ids.push(fleet_wings::create_wing(&app.esi_client, fleet_id, boss_id, name).await?);
+    }
+
+    // New wings may come with a squad already, use those before creating more
+    let existing = fetch_fleet_wings(app, boss_id, fleet_id).await?;
+    let mut assignments = HashMap::new();
+    for ((_name, squads), &wing_id) in template.iter().zip(wing_ids.iter()) {
+        let mut existing_squads = existing
+            .iter()
+            .filter(|wing| wing.id == wing_id)
+            .flat_map(|wing| wing.squads.iter().map(|squad| squad.id))
+            .collect::<Vec<i64>>()
+            .into_iter();
+
+        for category in squads {
+            let squad_id = match existing_squads.next() {
+                Some(squad_id) => {
+                    fleet_wings::rename_squad(
+                        &app.esi_client,
+                        fleet_id,
+                        boss_id,
+                        squad_id,
+                        &truncate_name(&category.name, MAX_NAME_LENGTH),
+                    )
+                    .await?;
+                    squad_id
+                }
+                None => {
+                    fleet_wings::create_squad(
+                        &app.esi_client,
+                        fleet_id,
+                        boss_id,
+                        wing_id,
+                        &truncate_name(&category.name, MAX_NAME_LENGTH),
+                    )
+                    .await?
+                }
+            };
+            assignments.insert(category.id.clone(), (wing_id, squad_id));
+        }
+    }
+
+    Ok(assignments)
+}
+
+#[derive(Debug, Deserialize)]
+struct AutoRegisterRequest {
+    character_id: i64,
+}
+
+#[post("/api/fleet/register/auto", data = "<input>")]
+async fn auto_register_fleet(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    input: Json<AutoRegisterRequest>,
+) -> Result<&'static str, Madness> {
+    account.require_access("fleet-configure")?;
+    authorize_character(app.get_db(), &account, input.character_id, None).await?;
+
+    let boss_id = input.character_id;
+    let fleet_id = get_current_fleet_id(app, boss_id).await?;
+    let template = squad_template(&crate::data::categories::categories());
+
+    // Running it twice would leave a second set of wings behind
+    let wings = fetch_fleet_wings(app, boss_id, fleet_id).await?;
+    if let Some(wing) = wings
+        .iter()
+        .find(|wing| template.iter().any(|(name, _squads)| *name == wing.name))
+    {
+        return Err(Madness::BadRequest(format!(
+            "The fleet already has a \"{}\" wing, register the fleet instead",
+            wing.name
+        )));
+    }
+
+    let mut wing_ids = Vec::new();
+    let assignments = match create_squads(app, fleet_id, boss_id, &template, &mut wing_ids).await {
+        Ok(assignments) => assignments,
+        Err(e) => {
+            // Don't leave half a setup in the fleet
+            for wing_id in wing_ids {
+                if let Err(e) =
+                    fleet_wings::delete_wing(&app.esi_client, fleet_id, boss_id, wing_id).await
+                {
+                    warn!(
+                        "Could not delete wing {} of fleet {}: {:?}",
+                        wing_id, fleet_id, e
+                    );
+                }
+            }
+            return Err(e);
+        }
+    };
+
+    save_registration(app, boss_id, fleet_id, &assignments).await?;
 
     Ok("OK")
 }
@@ -1646,16 +762,22 @@ struct UpdateMotdResponse {
     has_incorrect_hull: bool,
 }
 
-#[post("/api/fleet/update-motd", data = "<input>")]
-async fn update_motd(
+struct FleetMotd {
+    fleet_id: i64,
+    boss_id: i64,
+    session_id: i64,
+    /// The MOTD as ESI has it, to compare the new one against
+    text: String,
+    motd: Motd,
+    in_fleet: InFleet,
+}
+
+async fn fleet_motd(
     app: &rocket::State<Application>,
-    account: AuthenticatedAccount,
-    input: Json<UpdateMotdRequest>,
-) -> Result<Json<UpdateMotdResponse>, Madness> {
-    account.require_access("fleet-configure")?;
-    authorize_character(app.get_db(), &account, input.character_id, None).await?;
-    
-    let fleet_id = get_current_fleet_id(app, input.character_id).await?;
+    character_id: i64,
+    roles: &[MotdRole],
+) -> Result<FleetMotd, Madness> {
+    let fleet_id = get_current_fleet_id(app, character_id).await?;
     let fleet = match sqlx::query!("SELECT boss_id FROM fleet WHERE id = ?", fleet_id)
         .fetch_optional(app.get_db())
         .await?
@@ -1663,87 +785,107 @@ async fn update_motd(
         Some(fleet) => fleet,
         None => return Err(Madness::NotFound("Fleet not configured")),
     };
-    
-    // Fetch current MOTD
-    let fleet_info = crate::core::esi::fleet_info::get(&app.esi_client, fleet_id, fleet.boss_id).await?;
-    let current_motd = fleet_info.motd;
-    
-    // Parse current role assignments
-    let mut role_assignments = parse_motd_roles(&current_motd);
-    
-    // Get current fleet members
-    let members = crate::core::esi::fleet_members::get(&app.esi_client, fleet_id, fleet.boss_id).await?;
+
+    let session_id = match fleet_roles::session_id(app.get_db(), fleet_id).await? {
+        Some(session_id) => session_id,
+        None => return Err(Madness::NotFound("Fleet not configured")),
+    };
+
+    let info = crate::core::esi::fleet_info::get(&app.esi_client, fleet_id, fleet.boss_id).await?;
+    let members =
+        crate::core::esi::fleet_members::get(&app.esi_client, fleet_id, fleet.boss_id).await?;
     let character_ids: Vec<_> = members.iter().map(|member| member.character_id).collect();
     let characters = crate::data::character::lookup(app.get_db(), &character_ids).await?;
-    
-    // Create mappings
-    let fleet_member_names: HashSet<String> = characters
-        .values()
-        .map(|char| char.name.to_lowercase())
-        .collect();
-    
-    let mut character_name_to_id: HashMap<String, i64> = HashMap::new();
-    for (char_id, char) in &characters {
-        character_name_to_id.insert(char.name.to_lowercase(), *char_id);
+    let motd = Motd::parse(&info.motd, roles);
+    let in_fleet = InFleet::new(&members, &characters);
+
+    Ok(FleetMotd {
+        fleet_id,
+        boss_id: fleet.boss_id,
+        session_id,
+        text: info.motd,
+        motd,
+        in_fleet,
+    })
+}
+
+/// Rewrites the lines of the changed roles, and returns the assignments after the changes.
+/// The changes are only stored once the MOTD is written.
+async fn write_motd(
+    app: &rocket::State<Application>,
+    changes: fleet_roles::Changes,
+    fleet_motd: &mut FleetMotd,
+) -> Result<Vec<Assignment>, Madness> {
+    changes.project(&mut fleet_motd.motd);
+    let rendered = fleet_motd.motd.render();
+    if rendered != fleet_motd.text {
+        crate::core::esi::fleet_info::update(
+            &app.esi_client,
+            fleet_motd.fleet_id,
+            fleet_motd.boss_id,
+            rendered,
+        )
+        .await?;
     }
-    
-    let mut character_to_hull: HashMap<String, String> = HashMap::new();
-    for member in &members {
-        if let Some(character) = characters.get(&member.character_id) {
-            let hull_name = TypeDB::name_of(member.ship_type_id).unwrap();
-            character_to_hull.insert(character.name.to_lowercase(), hull_name.to_lowercase());
+    changes.store(app.get_db()).await?;
+
+    Ok(changes.assignments().to_vec())
+}
+
+/// Starts the changes to the roles, by picking up pilots added to the MOTD by hand.
+async fn begin_roles(
+    app: &rocket::State<Application>,
+    fleet_motd: &FleetMotd,
+    roles: &[MotdRole],
+    now: i64,
+) -> Result<fleet_roles::Changes, Madness> {
+    let mut changes = fleet_roles::Changes::load(app.get_db(), fleet_motd.session_id, now).await?;
+    changes.import(&fleet_motd.motd, roles, &fleet_motd.in_fleet.names);
+    Ok(changes)
+}
+
+fn get_role(id: &str) -> Result<MotdRole, Madness> {
+    motd_roles::get(id).ok_or_else(|| Madness::BadRequest(format!("Unknown role {}", id)))
+}
+
+#[post("/api/fleet/update-motd", data = "<input>")]
+async fn update_motd(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    input: Json<UpdateMotdRequest>,
+) -> Result<Json<UpdateMotdResponse>, Madness> {
+    account.require_access("fleet-configure")?;
+    authorize_character(app.get_db(), &account, input.character_id, None).await?;
+
+    let roles = motd_roles::roles();
+    let role = get_role(&input.role)?;
+    let mut fleet_motd = fleet_motd(app, input.character_id, &roles).await?;
+    let in_fleet = &fleet_motd.in_fleet;
+
+    let character_id = match in_fleet
+        .ids_by_name
+        .get(&input.character_name.to_lowercase())
+    {
+        Some(&id) => id,
+        None => {
+            return Err(Madness::BadRequest(format!(
+                "{} is not in the fleet",
+                input.character_name
+            )))
         }
-    }
-    
-    // Check for duplicate
-    let role_upper = input.role.to_uppercase();
-    let character_name_lower = input.character_name.to_lowercase();
-    let is_duplicate = role_assignments
-        .get(&role_upper)
-        .map(|names| names.iter().any(|n| n.to_lowercase() == character_name_lower))
-        .unwrap_or(false);
-    
-    // Check hull
-    let has_incorrect_hull = if let Some(hull_name) = character_to_hull.get(&character_name_lower) {
-        let allowed_hulls: Vec<String> = get_allowed_hulls_for_role(&role_upper)
-            .into_iter()
-            .map(|s| s.to_lowercase())
-            .collect();
-        !allowed_hulls.contains(hull_name)
-    } else {
-        false // Not in fleet, can't check hull
     };
-    
-    // Add character to role (even if duplicate - frontend will handle confirmation)
-    let role_assignments_entry = role_assignments.entry(role_upper.clone()).or_insert_with(Vec::new);
-    // Always add, even if duplicate (frontend shows confirmation)
-    role_assignments_entry.push(input.character_name.clone());
-    
-    // Filter out characters not in fleet from all roles
-    for names in role_assignments.values_mut() {
-        names.retain(|name| fleet_member_names.contains(&name.to_lowercase()));
-    }
-    
-    // Reconstruct MOTD - add the new character to the role
-    // We only modify the specific character's name and ID, preserving all HTML structure
-    let new_motd = add_character_to_motd_role(
-        &current_motd,
-        &role_upper,
-        &input.character_name,
-        *character_name_to_id.get(&character_name_lower).unwrap_or(&0),
-        &character_name_to_id,
-        &fleet_member_names,
-    );
-    
-    // Remove characters not in fleet - but do it surgically, preserving HTML structure
-    let new_motd = remove_characters_not_in_fleet_surgical(
-        &new_motd,
-        &fleet_member_names,
-    );
-    
-    // Update MOTD via ESI
-    crate::core::esi::fleet_info::update(&app.esi_client, fleet_id, fleet.boss_id, new_motd).await?;
-    
+    let has_incorrect_hull = !role.allows_hull(in_fleet.hulls[&character_id]);
+
+    // Pilots who left come off every role while we're at it
+    let now = chrono::Utc::now().timestamp();
+    let present: Vec<i64> = in_fleet.hulls.keys().copied().collect();
+    let name = in_fleet.names[&character_id].clone();
+    let mut changes = begin_roles(app, &fleet_motd, &roles, now).await?;
+    changes.remove_missing(&present);
+    let is_duplicate = !changes.assign(&role.id, character_id, &name, Some(input.character_id));
+
+    write_motd(app, changes, &mut fleet_motd).await?;
+
     Ok(Json(UpdateMotdResponse {
         success: true,
         message: "MOTD updated successfully".to_string(),
@@ -1752,6 +894,53 @@ async fn update_motd(
     }))
 }
 
+#[derive(Debug, Deserialize)]
+struct SetRoleRequest {
+    character_id: i64,
+    role: String,
+    assignees: Vec<i64>,
+}
+
+/// Sets who is on a role. Everything else in the MOTD stays as it is.
+#[post("/api/fleet/motd/roles", data = "<input>")]
+async fn set_motd_role(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    input: Json<SetRoleRequest>,
+) -> Result<Json<HashMap<String, Vec<RoleAssignment>>>, Madness> {
+    account.require_access("fleet-configure")?;
+    authorize_character(app.get_db(), &account, input.character_id, None).await?;
+
+    let roles = motd_roles::roles();
+    let role = get_role(&input.role)?;
+    let mut fleet_motd = fleet_motd(app, input.character_id, &roles).await?;
+
+    let mut assignees = Vec::new();
+    for id in &input.assignees {
+        match fleet_motd.in_fleet.names.get(id) {
+            Some(name) => assignees.push((*id, name.clone())),
+            None => {
+                return Err(Madness::BadRequest(format!(
+                    "Character {} is not in the fleet",
+                    id
+                )))
+            }
+        }
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let mut changes = begin_roles(app, &fleet_motd, &roles, now).await?;
+    changes.set(&role.id, &assignees, input.character_id);
+
+    let assignments = write_motd(app, changes, &mut fleet_motd).await?;
+
+    Ok(Json(check_stored(
+        &assignments,
+        &roles,
+        &fleet_motd.in_fleet,
+    )))
+}
+
 pub fn routes() -> Vec<rocket::Route> {
     routes![
         fleet_status,
@@ -1759,7 +948,9 @@ pub fn routes() -> Vec<rocket::Route> {
         close_fleet,
         fleet_members,
         register_fleet,
+        auto_register_fleet,
         fleet_composition,
         update_motd,
+        set_motd_role,
     ]
 }
@@ -1,7 +1,17 @@
+use rocket::serde::json::Json;
+use serde::Serialize;
+
 use crate::{app::Application, data, tla, util::madness::Madness};
+use eve_data_core::TypeDB;
+
+#[derive(Debug, Serialize)]
+struct HealthResponse {
+    status: &'static str,
+    sde_build: Option<i64>,
+}
 
 #[get("/healthz")]
-async fn health_check(app: &rocket::State<Application>) -> Result<&'static str, Madness> {
+async fn health_check(app: &rocket::State<Application>) -> Result<Json<HealthResponse>, Madness> {
     // Check database connection
     let _oneoneone = sqlx::query!("SELECT 1 'one'")
         .fetch_one(app.get_db())
@@ -14,7 +24,10 @@ async fn health_check(app: &rocket::State<Application>) -> Result<&'static str,
 
     // Don't check ESI.
 
-    Ok("OK")
+    Ok(Json(HealthResponse {
+        status: "OK",
+        sde_build: TypeDB::active()?.build_number(),
+    }))
 }
 
 pub fn routes() -> Vec<rocket::Route> {
@@ -11,7 +11,7 @@ use crate::{
     },
 };
 
-use eve_data_core::{TypeDB, TypeID};
+use eve_data_core::{AsyncTypeDB, TypeID};
 
 #[derive(Serialize, Debug)]
 struct ActivityEntry {
@@ -64,7 +64,7 @@ async fn fleet_history(
         entries.push(ActivityEntry {
             hull: Hull {
                 id: hull,
-                name: TypeDB::name_of(hull)?,
+                name: AsyncTypeDB::name_of(hull).await?,
             },
             logged_at: first_seen,
             time_in_fleet,
@@ -76,7 +76,7 @@ async fn fleet_history(
         summary.push(ActivitySummaryEntry {
             hull: Hull {
                 id: hull,
-                name: TypeDB::name_of(hull)?,
+                name: AsyncTypeDB::name_of(hull).await?,
             },
             time_in_fleet,
         })
@@ -153,7 +153,7 @@ async fn fleet_comp(
         fleet.push(FleetCompEntry {
             hull: Hull {
                 id: entry.hull as TypeID,
-                name: TypeDB::name_of(entry.hull as TypeID)?,
+                name: AsyncTypeDB::name_of(entry.hull as TypeID).await?,
             },
             character: Character {
                 id: entry.character_id,
@@ -1,7 +1,16 @@
 mod activity;
+mod roles;
+mod sessions;
 mod skills;
 mod xup;
 
 pub fn routes() -> Vec<rocket::Route> {
-    [skills::routes(), xup::routes(), activity::routes()].concat()
+    [
+        skills::routes(),
+        xup::routes(),
+        activity::routes(),
+        sessions::routes(),
+        roles::routes(),
+    ]
+    .concat()
 }
@@ -0,0 +1,88 @@
+use rocket::serde::json::Json;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    app::Application,
+    core::auth::{authorize_character, AuthenticatedAccount},
+    util::{madness::Madness, types::Character},
+};
+
+#[derive(Debug, Serialize)]
+struct RoleTimeEntry {
+    character: Character,
+    role: String,
+    sessions: usize,
+    time_in_role: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct RoleTimeResponse {
+    roles: Vec<RoleTimeEntry>,
+}
+
+/// Time spent on each MOTD role, per pilot. Pilots can see their own.
+#[get("/api/history/roles?<character_id>")]
+async fn role_time(
+    character_id: Option<i64>,
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<RoleTimeResponse>, Madness> {
+    match character_id {
+        Some(character_id) => {
+            authorize_character(
+                app.get_db(),
+                &account,
+                character_id,
+                Some("fleet-activity-view"),
+            )
+            .await?
+        }
+        None => account.require_access("fleet-history-view")?,
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let rows = sqlx::query!(
+        "
+            SELECT session_id, character_id, `character`.name, role, assigned_at, removed_at
+            FROM fleet_role_assignment JOIN `character` ON character_id=`character`.id
+            WHERE ? IS NULL OR character_id = ?
+        ",
+        character_id,
+        character_id
+    )
+    .fetch_all(app.get_db())
+    .await?;
+
+    let mut by_role: HashMap<(i64, String), (String, HashSet<i64>, i64)> = HashMap::new();
+    for row in rows {
+        let (_name, sessions, time_in_role) = by_role
+            .entry((row.character_id, row.role))
+            .or_insert_with(|| (row.name, HashSet::new(), 0));
+        sessions.insert(row.session_id);
+        *time_in_role += row.removed_at.unwrap_or(now) - row.assigned_at;
+    }
+
+    let mut roles: Vec<RoleTimeEntry> = by_role
+        .into_iter()
+        .map(
+            |((character_id, role), (name, sessions, time_in_role))| RoleTimeEntry {
+                character: Character {
+                    id: character_id,
+                    name,
+                    corporation_id: None,
+                },
+                role,
+                sessions: sessions.len(),
+                time_in_role,
+            },
+        )
+        .collect();
+    roles.sort_by(|a, b| b.time_in_role.cmp(&a.time_in_role));
+
+    Ok(Json(RoleTimeResponse { roles }))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![role_time]
+}
@@ -0,0 +1,330 @@
+use rocket::serde::json::Json;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{
+    app::Application,
+    core::auth::AuthenticatedAccount,
+    util::{
+        madness::Madness,
+        types::{Character, Hull},
+    },
+};
+
+use eve_data_core::{AsyncTypeDB, TypeID};
+
+const PAGE_SIZE: i64 = 50;
+
+#[derive(Debug, Serialize)]
+struct SessionBoss {
+    character: Character,
+    started_at: i64,
+    ended_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct Waitlist {
+    id: i64,
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Constellation {
+    id: i64,
+    name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct FleetSession {
+    id: i64,
+    fleet_id: i64,
+    started_at: i64,
+    ended_at: Option<i64>,
+    waitlist: Option<Waitlist>,
+    peak_size: i32,
+    constellation: Option<Constellation>,
+    bosses: Vec<SessionBoss>,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionsResponse {
+    sessions: Vec<FleetSession>,
+}
+
+#[derive(Debug, Serialize)]
+struct TimelineEntry {
+    character: Character,
+    hull: Hull,
+    joined_at: i64,
+    last_seen: i64,
+    is_boss: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct CompositionEntry {
+    hull: Hull,
+    pilots: usize,
+    time_in_fleet: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct RoleEntry {
+    role: String,
+    character: Character,
+    /// `None` if the role was taken over from the MOTD
+    assigned_by: Option<Character>,
+    assigned_at: i64,
+    removed_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionDetailResponse {
+    session: FleetSession,
+    timeline: Vec<TimelineEntry>,
+    composition: Vec<CompositionEntry>,
+    roles: Vec<RoleEntry>,
+}
+
+struct SessionRow {
+    id: i64,
+    fleet_id: i64,
+    started_at: i64,
+    ended_at: Option<i64>,
+    waitlist_id: Option<i64>,
+    waitlist_name: Option<String>,
+    peak_size: i32,
+    constellation_id: Option<i64>,
+    constellation_name: Option<String>,
+}
+
+impl SessionRow {
+    fn into_session(self, bosses: Vec<SessionBoss>) -> FleetSession {
+        let SessionRow {
+            id,
+            fleet_id,
+            started_at,
+            ended_at,
+            waitlist_id,
+            waitlist_name,
+            peak_size,
+            constellation_id,
+            constellation_name,
+        } = self;
+        FleetSession {
+            id,
+            fleet_id,
+            started_at,
+            ended_at,
+            waitlist: match (waitlist_id, waitlist_name) {
+                (Some(id), Some(name)) => Some(Waitlist { id, name }),
+                _ => None,
+            },
+            peak_size,
+            constellation: constellation_id.map(|id| Constellation {
+                id,
+                name: constellation_name,
+            }),
+            bosses,
+        }
+    }
+}
+
+async fn load_bosses(
+    app: &Application,
+    session_ids: &[i64],
+) -> Result<HashMap<i64, Vec<SessionBoss>>, Madness> {
+    let mut bosses: HashMap<i64, Vec<SessionBoss>> = HashMap::new();
+    if session_ids.is_empty() {
+        return Ok(bosses);
+    }
+
+    let placeholders = vec!["?"; session_ids.len()].join(",");
+    let query = format!(
+        "SELECT session_id, character_id, `character`.name, started_at, ended_at FROM fleet_session_boss JOIN `character` ON character_id=`character`.id WHERE session_id IN ({}) ORDER BY started_at",
+        placeholders
+    );
+    let mut query = sqlx::query_as::<_, (i64, i64, String, i64, Option<i64>)>(&query);
+    for id in session_ids {
+        query = query.bind(id);
+    }
+
+    for (session_id, character_id, name, started_at, ended_at) in
+        query.fetch_all(app.get_db()).await?
+    {
+        bosses.entry(session_id).or_default().push(SessionBoss {
+            character: Character {
+                id: character_id,
+                name,
+                corporation_id: None,
+            },
+            started_at,
+            ended_at,
+        });
+    }
+
+    Ok(bosses)
+}
+
+#[get("/api/history/fleets?<before>")]
+async fn list_sessions(
+    before: Option<i64>,
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<SessionsResponse>, Madness> {
+    account.require_access("fleet-history-view")?;
+
+    let rows = sqlx::query_as!(
+        SessionRow,
+        "
+            SELECT fleet_session.id, fleet_id, started_at, ended_at, waitlist_id, waitlist.name AS `waitlist_name?`,
+                peak_size, constellation_id, constellation_name
+            FROM fleet_session LEFT JOIN waitlist ON waitlist_id=waitlist.id
+            WHERE started_at < ?
+            ORDER BY started_at DESC
+            LIMIT ?
+        ",
+        before.unwrap_or(i64::MAX),
+        PAGE_SIZE
+    )
+    .fetch_all(app.get_db())
+    .await?;
+
+    let ids: Vec<i64> = rows.iter().map(|row| row.id).collect();
+    let mut bosses = load_bosses(app, &ids).await?;
+
+    let sessions = rows
+        .into_iter()
+        .map(|row| {
+            let row_bosses = bosses.remove(&row.id).unwrap_or_default();
+            row.into_session(row_bosses)
+        })
+        .collect();
+
+    Ok(Json(SessionsResponse { sessions }))
+}
+
+#[get("/api/history/fleets/<session_id>")]
+async fn session_detail(
+    session_id: i64,
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<SessionDetailResponse>, Madness> {
+    account.require_access("fleet-history-view")?;
+
+    let row = match sqlx::query_as!(
+        SessionRow,
+        "
+            SELECT fleet_session.id, fleet_id, started_at, ended_at, waitlist_id, waitlist.name AS `waitlist_name?`,
+                peak_size, constellation_id, constellation_name
+            FROM fleet_session LEFT JOIN waitlist ON waitlist_id=waitlist.id
+            WHERE fleet_session.id = ?
+        ",
+        session_id
+    )
+    .fetch_optional(app.get_db())
+    .await?
+    {
+        Some(row) => row,
+        None => return Err(Madness::NotFound("Fleet not found")),
+    };
+
+    let activity = sqlx::query!(
+        "
+            SELECT character_id, `character`.name AS character_name, hull, first_seen, last_seen, is_boss
+            FROM fleet_activity JOIN `character` ON character_id=`character`.id
+            WHERE fleet_id = ? AND last_seen >= ? AND first_seen <= ?
+            ORDER BY first_seen
+        ",
+        row.fleet_id,
+        row.started_at,
+        row.ended_at.unwrap_or(i64::MAX)
+    )
+    .fetch_all(app.get_db())
+    .await?;
+
+    let mut timeline = Vec::new();
+    let mut by_hull: BTreeMap<TypeID, (HashSet<i64>, i64)> = BTreeMap::new();
+    for entry in activity {
+        let hull = entry.hull as TypeID;
+        let (pilots, time_in_fleet) = by_hull.entry(hull).or_default();
+        pilots.insert(entry.character_id);
+        *time_in_fleet += entry.last_seen - entry.first_seen;
+
+        timeline.push(TimelineEntry {
+            character: Character {
+                id: entry.character_id,
+                name: entry.character_name,
+                corporation_id: None,
+            },
+            hull: Hull {
+                id: hull,
+                name: AsyncTypeDB::name_of(hull).await?,
+            },
+            joined_at: entry.first_seen,
+            last_seen: entry.last_seen,
+            is_boss: entry.is_boss > 0,
+        });
+    }
+
+    let mut composition = Vec::new();
+    for (hull, (pilots, time_in_fleet)) in by_hull {
+        composition.push(CompositionEntry {
+            hull: Hull {
+                id: hull,
+                name: AsyncTypeDB::name_of(hull).await?,
+            },
+            pilots: pilots.len(),
+            time_in_fleet,
+        });
+    }
+    composition.sort_by(|a, b| b.pilots.cmp(&a.pilots));
+
+    let roles = sqlx::query!(
+        "
+            SELECT role, character_id, holder.name, assigned_by, assigner.name AS `assigned_by_name?`,
+                assigned_at, removed_at
+            FROM fleet_role_assignment
+            JOIN `character` holder ON character_id=holder.id
+            LEFT JOIN `character` assigner ON assigned_by=assigner.id
+            WHERE session_id = ?
+            ORDER BY assigned_at, fleet_role_assignment.id
+        ",
+        row.id
+    )
+    .fetch_all(app.get_db())
+    .await?
+    .into_iter()
+    .map(|role| RoleEntry {
+        role: role.role,
+        character: Character {
+            id: role.character_id,
+            name: role.name,
+            corporation_id: None,
+        },
+        assigned_by: match (role.assigned_by, role.assigned_by_name) {
+            (Some(id), Some(name)) => Some(Character {
+                id,
+                name,
+                corporation_id: None,
+            }),
+            _ => None,
+        },
+        assigned_at: role.assigned_at,
+        removed_at: role.removed_at,
+    })
+    .collect();
+
+    let mut bosses = load_bosses(app, &[row.id]).await?;
+    let session_bosses = bosses.remove(&row.id).unwrap_or_default();
+
+    Ok(Json(SessionDetailResponse {
+        session: row.into_session(session_bosses),
+        timeline,
+        composition,
+        roles,
+    }))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![list_sessions, session_detail]
+}
@@ -4,7 +4,7 @@ use crate::{
     util::{madness::Madness, types::Hull},
 };
 
-use eve_data_core::{TypeDB, TypeID};
+use eve_data_core::{AsyncTypeDB, TypeID};
 use rocket::serde::json::Json;
 use serde::Serialize;
 
@@ -61,7 +61,7 @@ async fn xup_history(
                 .collect(),
             hull: Hull {
                 id: xup.hull as TypeID,
-                name: TypeDB::name_of(xup.hull as TypeID)?,
+                name: AsyncTypeDB::name_of(xup.hull as TypeID).await?,
             },
         });
     }
@@ -1,6 +1,7 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
-use eve_data_core::{TypeDB, TypeID};
+use eve_data_core::{AsyncTypeDB, Type, TypeDB, TypeID};
 use rocket::serde::json::Json;
 use serde::Serialize;
 
@@ -14,9 +15,12 @@ struct Module {
 }
 type ModuleResponse = BTreeMap<TypeID, Module>;
 
-fn module_info_impl(ids: &[TypeID]) -> Result<ModuleResponse, Madness> {
+fn module_info_impl(
+    types: HashMap<TypeID, Option<Arc<Type>>>,
+    localized: HashMap<TypeID, String>,
+) -> ModuleResponse {
     let mut result = BTreeMap::new();
-    for (id, typeinfo) in TypeDB::load_types(ids)? {
+    for (id, typeinfo) in types {
         if let Some(typeinfo) = typeinfo {
             let slot = if typeinfo.category.category_name() == "_other" {
                 Some("other")
@@ -26,7 +30,7 @@ fn module_info_impl(ids: &[TypeID]) -> Result<ModuleResponse, Madness> {
             result.insert(
                 id,
                 Module {
-                    name: typeinfo.name.clone(),
+                    name: localized.get(&id).unwrap_or(&typeinfo.name).clone(),
                     category: typeinfo.category.category_name(),
                     slot,
                 },
@@ -34,17 +38,18 @@ fn module_info_impl(ids: &[TypeID]) -> Result<ModuleResponse, Madness> {
         }
     }
 
-    Ok(result)
+    result
 }
 
 lazy_static::lazy_static! {
     static ref PRELOAD: ModuleResponse = make_preload();
 }
 
-#[get("/api/module/info?<ids>")]
-fn module_info(
+#[get("/api/module/info?<ids>&<locale>")]
+async fn module_info(
     _account: AuthenticatedAccount,
     ids: String,
+    locale: Option<String>,
 ) -> Result<Json<ModuleResponse>, Madness> {
     let mut type_ids = Vec::new();
     for id in ids.split(',') {
@@ -59,17 +64,24 @@ fn module_info(
         return Err(Madness::BadRequest("Too many IDs".to_string()));
     }
 
-    Ok(Json(module_info_impl(&type_ids)?))
+    let localized = match locale {
+        Some(locale) => AsyncTypeDB::names_of_locale(&type_ids, &locale).await?,
+        None => Default::default(),
+    };
+    let types = AsyncTypeDB::load_types(&type_ids).await?;
+
+    Ok(Json(module_info_impl(types, localized)))
 }
 
 fn make_preload() -> ModuleResponse {
     let module_ids = crate::data::fits::used_module_ids();
-    module_info_impl(&module_ids).unwrap()
+    module_info_impl(TypeDB::load_types(&module_ids).unwrap(), Default::default())
 }
 
 #[get("/api/module/preload")]
-fn preload() -> Json<&'static ModuleResponse> {
-    Json(&PRELOAD)
+async fn preload() -> Result<Json<&'static ModuleResponse>, Madness> {
+    // The first request builds the preload from SQLite, so keep it on the blocking pool
+    Ok(Json(AsyncTypeDB::run(|_| Ok(&*PRELOAD)).await?))
 }
 
 pub fn routes() -> Vec<rocket::Route> {
@@ -1,10 +1,10 @@
-use eve_data_core::{SkillLevel, TypeDB, TypeID};
+use eve_data_core::{AsyncTypeDB, SkillLevel, TypeDB, TypeID};
 use rocket::serde::json::Json;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     core::auth::AuthenticatedAccount,
-    data::skillplans::{self, SkillPlan, SkillPlanError, SkillPlanLevel},
+    data::skillplans::{self, SkillPlan, SkillPlanLevel},
     util::madness::Madness,
     util::types::Hull,
 };
@@ -21,55 +21,6 @@ struct SkillPlansResponsePlan {
     ships: Vec<Hull>,
 }
 
-fn build_data() -> Result<SkillPlansResponse, SkillPlanError> {
-    let plans = skillplans::load_plans_from_file();
-    let mut result = Vec::new();
-    for plan in plans {
-        let levels = skillplans::build_plan(&plan)?;
-        let mut ships = Vec::new();
-
-        for level in &plan.plan {
-            match level {
-                SkillPlanLevel::Skills { from, tier: _ } => {
-                    let ship_id = TypeDB::id_of(from)?;
-                    if !ships.contains(&ship_id) {
-                        ships.push(ship_id);
-                    }
-                }
-                SkillPlanLevel::Fit { hull, fit: _ } => {
-                    let ship_id = TypeDB::id_of(hull)?;
-                    if !ships.contains(&ship_id) {
-                        ships.push(ship_id);
-                    }
-                }
-                _ => (),
-            };
-        }
-
-        let ships_lookup = TypeDB::load_types(&ships)?;
-        let mut hulls = Vec::new();
-        for ship in ships {
-            let hull = ships_lookup
-                .get(&ship)
-                .unwrap()
-                .as_ref()
-                .expect("Ships exist");
-            hulls.push(Hull {
-                id: ship,
-                name: hull.name.clone(),
-            });
-        }
-
-        result.push(SkillPlansResponsePlan {
-            source: plan,
-            levels,
-            ships: hulls,
-        });
-    }
-
-    Ok(SkillPlansResponse { plans: result })
-}
-
 fn build_data_skip_errors() -> SkillPlansResponse {
     let plans = skillplans::load_plans_from_file();
     let mut result = Vec::new();
@@ -192,8 +143,12 @@ fn build_data_skip_errors() -> SkillPlansResponse {
 }
 
 #[get("/api/skills/plans")]
-fn get_skill_plans(_account: AuthenticatedAccount) -> Result<Json<SkillPlansResponse>, Madness> {
-    Ok(Json(build_data_skip_errors()))
+async fn get_skill_plans(
+    _account: AuthenticatedAccount,
+) -> Result<Json<SkillPlansResponse>, Madness> {
+    // Reads the plan file and looks up every ship and skill, so run it on the blocking pool
+    let plans = AsyncTypeDB::run(|_| Ok(build_data_skip_errors())).await?;
+    Ok(Json(plans))
 }
 
 // Admin routes
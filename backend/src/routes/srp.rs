@@ -116,7 +116,8 @@ async fn get_srp_setup(
     let scope_str = scopes.iter().fold(String::new(), |acc, scope| acc + " " + scope.as_str()).trim_end().to_string();
     
     let login_url = format!(
-        "https://login.eveonline.com/v2/oauth/authorize?response_type=code&redirect_uri={}&client_id={}&scope={}&state=srp_setup",
+        "{}/v2/oauth/authorize?response_type=code&redirect_uri={}&client_id={}&scope={}&state=srp_setup",
+        app.config.esi.sso_base_url.trim_end_matches('/'),
         app.config.esi.url,
         app.config.esi.client_id,
         scope_str
@@ -1,6 +1,6 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
-use eve_data_core::{TypeDB, TypeError, TypeID};
+use eve_data_core::{AsyncTypeDB, TypeError, TypeID};
 use rocket::serde::json::Json;
 
 use crate::{app::Application, core::auth::AuthenticatedAccount, util::madness::Madness};
@@ -109,14 +109,12 @@ fn filter_into_other_2d<T: Ord>(
 
 fn translate_hulls_1d<T: Copy>(
     source: &BTreeMap<TypeID, T>,
+    names: &HashMap<TypeID, String>,
 ) -> Result<BTreeMap<String, T>, TypeError> {
-    let ids: Vec<TypeID> = source.keys().copied().collect();
-    let types = TypeDB::load_types(&ids)?;
-
     let mut result = BTreeMap::new();
     for (t, value) in source {
-        if let Some(the_type) = types.get(t).unwrap() {
-            result.insert(the_type.name.clone(), *value);
+        if let Some(name) = names.get(t) {
+            result.insert(name.clone(), *value);
         } else {
             return Err(TypeError::NothingMatched);
         }
@@ -127,10 +125,11 @@ fn translate_hulls_1d<T: Copy>(
 
 fn translate_hulls_2d<K: Copy + Ord, T: Copy>(
     source: &BTreeMap<K, BTreeMap<TypeID, T>>,
+    names: &HashMap<TypeID, String>,
 ) -> Result<BTreeMap<K, BTreeMap<String, T>>, TypeError> {
     let mut result = BTreeMap::new();
     for (k, value) in source {
-        result.insert(*k, translate_hulls_1d(value)?);
+        result.insert(*k, translate_hulls_1d(value, names)?);
     }
     Ok(result)
 }
@@ -311,14 +310,22 @@ struct Displayer {}
 impl Displayer {
     fn build_fleet_seconds_by_hull_by_month(
         source: &BTreeMap<YearMonth, BTreeMap<TypeID, f64>>,
+        names: &HashMap<TypeID, String>,
     ) -> Result<BTreeMap<YearMonth, BTreeMap<String, f64>>, Madness> {
-        Ok(filter_into_other_2d(translate_hulls_2d(source)?, 0.01))
+        Ok(filter_into_other_2d(
+            translate_hulls_2d(source, names)?,
+            0.01,
+        ))
     }
 
     fn build_xes_by_hull_by_month(
         source: &BTreeMap<YearMonth, BTreeMap<TypeID, f64>>,
+        names: &HashMap<TypeID, String>,
     ) -> Result<BTreeMap<YearMonth, BTreeMap<String, f64>>, Madness> {
-        Ok(filter_into_other_2d(translate_hulls_2d(source)?, 0.01))
+        Ok(filter_into_other_2d(
+            translate_hulls_2d(source, names)?,
+            0.01,
+        ))
     }
 
     fn build_fleet_seconds_by_month(
@@ -341,24 +348,33 @@ impl Displayer {
 
     fn build_xes_by_hull_28d(
         source: &BTreeMap<TypeID, f64>,
+        names: &HashMap<TypeID, String>,
     ) -> Result<BTreeMap<String, f64>, Madness> {
-        Ok(filter_into_other_1d(translate_hulls_1d(source)?, 0.01))
+        Ok(filter_into_other_1d(
+            translate_hulls_1d(source, names)?,
+            0.01,
+        ))
     }
 
     fn build_fleet_seconds_by_hull_28d(
         source: &BTreeMap<TypeID, f64>,
+        names: &HashMap<TypeID, String>,
     ) -> Result<BTreeMap<String, f64>, Madness> {
-        Ok(filter_into_other_1d(translate_hulls_1d(source)?, 0.01))
+        Ok(filter_into_other_1d(
+            translate_hulls_1d(source, names)?,
+            0.01,
+        ))
     }
 
     fn build_x_vs_time_by_hull_28d(
         source_x: &BTreeMap<TypeID, f64>,
         source_time: &BTreeMap<TypeID, f64>,
+        names: &HashMap<TypeID, String>,
     ) -> Result<BTreeMap<String, BTreeMap<&'static str, f64>>, Madness> {
         let sum_x: f64 = source_x.values().sum();
         let sum_time: f64 = source_time.values().sum();
-        let translated_x = filter_into_other_1d(translate_hulls_1d(source_x)?, 0.01);
-        let translated_time = translate_hulls_1d(source_time)?;
+        let translated_x = filter_into_other_1d(translate_hulls_1d(source_x, names)?, 0.01);
+        let translated_time = translate_hulls_1d(source_time, names)?;
 
         let mut result = BTreeMap::new();
         for (hull, x_count) in translated_x {
@@ -428,20 +444,35 @@ async fn statistics(
     let xes_by_hull_28d = Queries::xes_by_hull_28d(app.get_db()).await?;
     let seconds_by_hull_28d = Queries::fleet_seconds_by_hull_28d(app.get_db()).await?;
 
+    let mut hulls: Vec<TypeID> = seconds_by_hull_month
+        .values()
+        .chain(xes_by_hull_month.values())
+        .flat_map(|by_hull| by_hull.keys())
+        .chain(xes_by_hull_28d.keys())
+        .chain(seconds_by_hull_28d.keys())
+        .copied()
+        .collect();
+    hulls.sort_unstable();
+    hulls.dedup();
+    let names = AsyncTypeDB::names_of(&hulls).await?;
+
     Ok(Json(StatsResponse {
         fleet_seconds_by_hull_by_month: Displayer::build_fleet_seconds_by_hull_by_month(
             &seconds_by_hull_month,
+            &names,
         )?,
-        xes_by_hull_by_month: Displayer::build_xes_by_hull_by_month(&xes_by_hull_month)?,
+        xes_by_hull_by_month: Displayer::build_xes_by_hull_by_month(&xes_by_hull_month, &names)?,
         fleet_seconds_by_month: Displayer::build_fleet_seconds_by_month(&seconds_by_hull_month),
         pilots_by_month: Displayer::build_pilots_by_month(&seconds_by_character_month),
-        xes_by_hull_28d: Displayer::build_xes_by_hull_28d(&xes_by_hull_28d)?,
+        xes_by_hull_28d: Displayer::build_xes_by_hull_28d(&xes_by_hull_28d, &names)?,
         fleet_seconds_by_hull_28d: Displayer::build_fleet_seconds_by_hull_28d(
             &seconds_by_hull_28d,
+            &names,
         )?,
         x_vs_time_by_hull_28d: Displayer::build_x_vs_time_by_hull_28d(
             &xes_by_hull_28d,
             &seconds_by_hull_28d,
+            &names,
         )?,
         time_spent_in_fleet_by_month: Displayer::build_time_spent_in_fleet_by_month(
             &seconds_by_character_month,
@@ -9,7 +9,7 @@ use crate::{
     },
     util::madness::Madness,
 };
-use eve_data_core::{TypeDB, TypeID};
+use eve_data_core::{AsyncTypeDB, TypeID};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize)]
@@ -34,6 +34,7 @@ async fn invite(
                 wef.character_id wef_character_id,
 				wef.is_alt wef_is_alt,
                 we.account_id we_account_id,
+                we.waitlist_id we_waitlist_id,
                 fitting.hull fitting_hull,
                 EXISTS (SELECT character_id FROM admin WHERE character_id=we.account_id) as `has_acl!: bool`
             FROM waitlist_entry_fit wef
@@ -87,20 +88,23 @@ async fn invite(
             ESIScope::Fleets_WriteFleet_v1,
         )
         .await?;
+    crate::data::fleet_session::record_waitlist(
+        app.get_db(),
+        squad_info.fleet_id,
+        xup.we_waitlist_id,
+    )
+    .await?;
 
     let fc = sqlx::query!("SELECT name FROM `character` WHERE id=?", account.id)
         .fetch_one(app.get_db())
         .await?;
 
+    let hull_name = AsyncTypeDB::name_of(xup.fitting_hull as TypeID).await?;
     app.sse_client
         .submit(vec![Event::new(
             &format!("account;{}", xup.we_account_id),
             "wakeup",
-            format!(
-                "{} has invited your {} to fleet.",
-                fc.name,
-                TypeDB::name_of(xup.fitting_hull as TypeID)?
-            ),
+            format!("{} has invited your {} to fleet.", fc.name, hull_name),
         )])
         .await?;
 
@@ -6,13 +6,13 @@ use serde::Serialize;
 use crate::{
     app::Application,
     core::auth::AuthenticatedAccount,
-    data,
+    data::{self, tags::Tag},
     util::{
         madness::Madness,
         types::{Character, Hull},
     },
 };
-use eve_data_core::{TypeDB, TypeID};
+use eve_data_core::{AsyncTypeDB, TypeID};
 
 #[derive(Debug, Serialize)]
 struct WaitlistResponse {
@@ -38,7 +38,7 @@ struct WaitlistEntryFit {
     category: String,
     hull: Hull,
     character: Option<Character>,
-    tags: Vec<String>,
+    tags: Vec<Tag>,
     hours_in_fleet: Option<i64>,
     review_comment: Option<String>,
     dna: Option<String>,
@@ -54,6 +54,7 @@ async fn list(
     account: AuthenticatedAccount,
     waitlist_id: i64,
 ) -> Result<Json<WaitlistResponse>, Madness> {
+    let now = chrono::Utc::now().timestamp();
     let categories = data::categories::categories();
     let waitlist_categories: Vec<String> = categories
         .iter()
@@ -117,7 +118,7 @@ async fn list(
         .collect::<BTreeSet<_>>()
         .into_iter()
         .collect();
-    let hull_names = TypeDB::names_of(&hulls)?;
+    let hull_names = AsyncTypeDB::names_of(&hulls).await?;
 
     let pinned_notes: HashMap<i64, String> = if account.access.contains("waitlist-view") {
         sqlx::query!(
@@ -186,7 +187,8 @@ async fn list(
             .split(',')
             .into_iter()
             .filter(|s| !s.is_empty())
-            .map(|s| s.to_string());
+            .map(data::tags::get_tag)
+            .filter(|tag| !tag.is_expired(record.we_joined_at, now));
 
         if x_is_ours || account.access.contains("waitlist-view") {
             this_fit.character = Some(Character {
@@ -199,9 +201,7 @@ async fn list(
             this_fit.messagexup = record.wef_messagexup;
             this_fit.tags = tags.collect();
         } else {
-            this_fit.tags = tags
-                .filter(|t| data::tags::public_tags().contains(t))
-                .collect();
+            this_fit.tags = tags.filter(|tag| tag.is_public()).collect();
         }
 
         if x_is_ours
@@ -5,7 +5,10 @@ use serde::Deserialize;
 
 use crate::{
     app::Application,
-    core::auth::{authorize_character, AuthenticatedAccount},
+    core::{
+        auth::{authorize_character, AuthenticatedAccount},
+        esi,
+    },
     data::{implants, skills},
     tla,
     util::madness::Madness,
@@ -18,17 +21,27 @@ struct DnaXup {
     dna: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct SavedFittingXup {
+    character_id: i64,
+    fitting_id: i64,
+}
+
 #[derive(Debug, Deserialize)]
 struct XupRequest {
     waitlist_id: i64,
 
     character_id: i64,
+    #[serde(default)]
     eft: String,
     is_alt: bool,
     messagexup: String,
 
     #[serde(default)]
     dna: Vec<DnaXup>,
+
+    #[serde(default)]
+    saved: Vec<SavedFittingXup>,
 }
 
 const MAX_X_PER_ACCOUNT: usize = 10;
@@ -165,6 +178,7 @@ async fn xup_multi(
                 time_in_fleet: *time_in_fleet,
                 skills,
                 access_keys: account.access,
+                is_alt,
             },
         );
     }
@@ -276,8 +290,19 @@ async fn xup(
 ) -> Result<&'static str, Madness> {
     // Character authorization is done by xup_multi!
 
-    // EFT x'es
-    let fits = Fitting::from_eft(&input.eft)?;
+    // EFT x'es. Report every line that would change the fit, so the pilot can fix them all
+    // in one go. Stray text around the fit is ignored.
+    let parsed = Fitting::from_eft_lenient(&input.eft)?;
+    let errors: Vec<String> = parsed
+        .diagnostics
+        .iter()
+        .filter(|d| d.error.changes_fit())
+        .map(|d| d.to_string())
+        .collect();
+    if !errors.is_empty() {
+        return Err(Madness::BadRequest(errors.join("\n")));
+    }
+    let fits = parsed.fittings;
     let mut xups: Vec<_> = fits
         .into_iter()
         .map(|fit| (input.character_id, fit))
@@ -289,6 +314,25 @@ async fn xup(
         xups.push((dna_xup.character_id, fit));
     }
 
+    // Saved fitting x'es. We have to check the character before asking ESI for its fittings.
+    let mut saved_fittings = HashMap::new();
+    for saved_xup in &input.saved {
+        if !saved_fittings.contains_key(&saved_xup.character_id) {
+            authorize_character(app.get_db(), &account, saved_xup.character_id, None).await?;
+            let fittings = esi::fittings::get(&app.esi_client, saved_xup.character_id).await?;
+            saved_fittings.insert(saved_xup.character_id, fittings);
+        }
+
+        let fit = saved_fittings
+            .get(&saved_xup.character_id)
+            .unwrap()
+            .iter()
+            .find(|fit| fit.fitting_id == saved_xup.fitting_id)
+            .ok_or(Madness::NotFound("Saved fitting not found"))?
+            .to_fitting();
+        xups.push((saved_xup.character_id, fit));
+    }
+
     xup_multi(
         app,
         account,
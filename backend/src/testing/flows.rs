@@ -0,0 +1,634 @@
+use rocket::http::Status;
+use serde_json::json;
+
+use super::harness::Harness;
+use super::mock_esi::{MockCharacter, MockFleet, MockFleetMember};
+use crate::core::esi::{KillmailData, KillmailVictim, Position};
+use crate::data::srp::{self, WalletJournalEntry};
+
+const FC: i64 = 90000001;
+const PILOT: i64 = 90000002;
+const SRP_ACCOUNT: i64 = 90000003;
+const NEW_FC: i64 = 90000004;
+const SRP_CORPORATION: i64 = 98000001;
+const FLEET_ID: i64 = 1000000001;
+
+// A fit from the doctrine, so that x-up gets past the fit checker
+fn doctrine_fit() -> (String, eve_data_core::Fitting) {
+    let fits = crate::data::fits::get_fits();
+    let fits = fits.read().unwrap();
+    let doctrine = fits
+        .values()
+        .flatten()
+        .next()
+        .expect("data/fits.dat has no fits");
+    (doctrine.fit.to_dna().unwrap(), doctrine.fit.clone())
+}
+
+async fn query_i64(harness: &Harness, sql: &str, bind: i64) -> i64 {
+    sqlx::query_scalar(sql)
+        .bind(bind)
+        .fetch_one(harness.db.as_ref())
+        .await
+        .unwrap()
+}
+
+#[rocket::async_test]
+#[ignore = "needs WAITLIST_TEST_DATABASE_URL, see testing/mod.rs"]
+async fn test_xup_invite_fleet_srp() {
+    let harness = Harness::new().await;
+    let (dna, fit) = doctrine_fit();
+    let mut types: Vec<_> = fit.modules.keys().copied().collect();
+    types.push(fit.hull);
+
+    harness
+        .add_character(FC, "Mock FC", MockCharacter::default())
+        .await;
+    harness.grant_role(FC, "fc").await;
+    harness
+        .add_character(
+            PILOT,
+            "Mock Pilot",
+            MockCharacter {
+                skills: harness.skills_for(&types),
+                ship_type_id: fit.hull,
+                ..Default::default()
+            },
+        )
+        .await;
+
+    // The FC has a fleet with a squad for every category, and registers it
+    let (wings, assignments) = harness.category_wings();
+    harness.mock.state().fleets.insert(
+        FLEET_ID,
+        MockFleet {
+            boss_id: FC,
+            motd: String::new(),
+            members: vec![MockFleetMember {
+                character_id: FC,
+                ship_type_id: fit.hull,
+                squad_id: -1,
+                wing_id: -1,
+                role: "fleet_commander".to_string(),
+            }],
+            wings,
+        },
+    );
+    let (status, body) = harness
+        .post(
+            FC,
+            "/api/fleet/register",
+            &json!({ "character_id": FC, "fleet_id": FLEET_ID, "assignments": assignments }),
+        )
+        .await;
+    assert_eq!(status, Status::Ok, "{}", body);
+
+    // x-up
+    sqlx::query("UPDATE waitlist SET is_open=1 WHERE id=1")
+        .execute(harness.db.as_ref())
+        .await
+        .unwrap();
+    let (status, body) = harness
+        .post(
+            PILOT,
+            "/api/waitlist/xup",
+            &json!({
+                "waitlist_id": 1,
+                "character_id": PILOT,
+                "is_alt": false,
+                "messagexup": "",
+                "dna": [{ "character_id": PILOT, "dna": dna }],
+            }),
+        )
+        .await;
+    assert_eq!(status, Status::Ok, "{}", body);
+    let entry_fit_id = query_i64(
+        &harness,
+        "SELECT id FROM waitlist_entry_fit WHERE character_id=?",
+        PILOT,
+    )
+    .await;
+
+    // Invite, into the squad registered for the fit's category
+    let (status, body) = harness
+        .post(
+            FC,
+            "/api/waitlist/invite",
+            &json!({ "id": entry_fit_id, "character_id": FC }),
+        )
+        .await;
+    assert_eq!(status, Status::Ok, "{}", body);
+    {
+        let state = harness.mock.state();
+        assert_eq!(state.invites.len(), 1);
+        let (fleet_id, invite) = &state.invites[0];
+        assert_eq!(*fleet_id, FLEET_ID);
+        assert_eq!(invite.character_id, PILOT);
+        assert!(assignments
+            .values()
+            .any(|&(wing, squad)| (wing, squad) == (invite.wing_id, invite.squad_id)));
+        let wakeup = state
+            .events
+            .iter()
+            .find(|e| e.topic == format!("account;{}", PILOT) && e.event == "wakeup")
+            .expect("No wakeup for the pilot");
+        assert!(wakeup.data.starts_with("Mock FC has invited"));
+    }
+
+    // The fleet updater sees the pilot in fleet: off the waitlist, and fleet time starts
+    crate::core::fleet_updater::FleetUpdater::new(harness.db.clone(), harness.config.clone())
+        .run_once()
+        .await
+        .unwrap();
+    assert_eq!(
+        query_i64(
+            &harness,
+            "SELECT COUNT(*) FROM waitlist_entry_fit WHERE character_id=?",
+            PILOT
+        )
+        .await,
+        0
+    );
+    assert_eq!(
+        query_i64(
+            &harness,
+            "SELECT hull FROM fleet_activity WHERE character_id=? AND has_left=0",
+            PILOT
+        )
+        .await,
+        fit.hull as i64
+    );
+
+    // SRP: the pilot paid for daily cover and then lost the ship in fleet
+    let now = chrono::Utc::now();
+    let timestamp = now.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    harness
+        .add_character(SRP_ACCOUNT, "Mock SRP", MockCharacter::default())
+        .await;
+    sqlx::query("INSERT INTO srp_service_account (character_id, character_name, corporation_id, wallet_id, access_token, refresh_token, expires, scopes, created_at, updated_at) VALUES (?, 'Mock SRP', ?, 1, '', '', 0, '', ?, ?)")
+        .bind(SRP_ACCOUNT)
+        .bind(SRP_CORPORATION)
+        .bind(now.timestamp())
+        .bind(now.timestamp())
+        .execute(harness.db.as_ref())
+        .await
+        .unwrap();
+    {
+        let mut state = harness.mock.state();
+        state.wallet_journals.insert(
+            (SRP_CORPORATION, 1),
+            vec![WalletJournalEntry {
+                amount: 20_000_000.0,
+                balance: 20_000_000.0,
+                context_id: None,
+                context_id_type: None,
+                date: timestamp.clone(),
+                description: "Mock Pilot deposited cash into Mock SRP's account".to_string(),
+                first_party_id: Some(PILOT),
+                first_party_type: Some("character".to_string()),
+                id: 1,
+                reason: None,
+                ref_type: "player_donation".to_string(),
+                second_party_id: Some(SRP_CORPORATION),
+                second_party_type: Some("corporation".to_string()),
+                tax: None,
+                tax_receiver_id: None,
+            }],
+        );
+        state.killmails.insert(
+            (1, "mockhash".to_string()),
+            KillmailData {
+                attackers: Vec::new(),
+                killmail_id: 1,
+                killmail_time: timestamp.clone(),
+                moon_id: None,
+                solar_system_id: 30000142,
+                victim: KillmailVictim {
+                    alliance_id: None,
+                    character_id: Some(PILOT),
+                    corporation_id: None,
+                    damage_taken: 1000000,
+                    items: Vec::new(),
+                    position: Position {
+                        x: 0.0,
+                        y: 0.0,
+                        z: 0.0,
+                    },
+                    ship_type_id: fit.hull as i64,
+                },
+                war_id: None,
+            },
+        );
+        state
+            .type_names
+            .insert(fit.hull as i64, "Mock Hull".to_string());
+    }
+
+    srp::process_srp_payments(harness.app()).await.unwrap();
+    let covered: String = sqlx::query_scalar(
+        "SELECT CAST(coverage_type AS CHAR) FROM srp_payments WHERE character_name='Mock Pilot'",
+    )
+    .fetch_one(harness.db.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(covered, "daily");
+
+    srp::submit_srp_report(
+        harness.app(),
+        &format!("{}/v1/killmails/1/mockhash/", harness.mock.base_url),
+        "Lost it",
+        false,
+        PILOT,
+    )
+    .await
+    .unwrap();
+    let (victim, ship, srp_paid): (String, String, String) = sqlx::query_as(
+        "SELECT victim_character_name, victim_ship_type, CAST(srp_paid AS CHAR) FROM srp_reports WHERE killmail_id=1",
+    )
+    .fetch_one(harness.db.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(victim, "Mock Pilot");
+    assert_eq!(ship, "Mock Hull");
+    let srp_paid: serde_json::Value = serde_json::from_str(&srp_paid).unwrap();
+    assert_eq!(srp_paid["had_coverage"], json!(true));
+}
+
+#[rocket::async_test]
+#[ignore = "needs WAITLIST_TEST_DATABASE_URL, see testing/mod.rs"]
+async fn test_closed_fleet_is_removed() {
+    let harness = Harness::new().await;
+    let (_dna, fit) = doctrine_fit();
+    harness
+        .add_character(FC, "Mock FC", MockCharacter::default())
+        .await;
+    harness.grant_role(FC, "fc").await;
+    harness
+        .add_character(PILOT, "Mock Pilot", MockCharacter::default())
+        .await;
+    sqlx::query("INSERT INTO fleet (id, boss_id) VALUES (?, ?)")
+        .bind(FLEET_ID)
+        .bind(FC)
+        .execute(harness.db.as_ref())
+        .await
+        .unwrap();
+    let members = [FC, PILOT]
+        .iter()
+        .map(|&character_id| MockFleetMember {
+            character_id,
+            ship_type_id: fit.hull,
+            squad_id: -1,
+            wing_id: -1,
+            role: match character_id {
+                FC => "fleet_commander",
+                _ => "squad_member",
+            }
+            .to_string(),
+        })
+        .collect();
+    harness.mock.state().fleets.insert(
+        FLEET_ID,
+        MockFleet {
+            boss_id: FC,
+            motd: String::new(),
+            members,
+            wings: Vec::new(),
+        },
+    );
+    let updater =
+        crate::core::fleet_updater::FleetUpdater::new(harness.db.clone(), harness.config.clone());
+    updater.run_once().await.unwrap();
+
+    // ESI doesn't know the fleet any more, so the updater forgets it
+    harness.mock.state().fleets.remove(&FLEET_ID);
+    updater.run_once().await.unwrap();
+    assert_eq!(
+        query_i64(&harness, "SELECT COUNT(*) FROM fleet WHERE id=?", FLEET_ID).await,
+        0
+    );
+
+    // Its session stays behind, closed
+    let session_id = query_i64(
+        &harness,
+        "SELECT id FROM fleet_session WHERE fleet_id=? AND ended_at IS NOT NULL",
+        FLEET_ID,
+    )
+    .await;
+    assert_eq!(
+        query_i64(
+            &harness,
+            "SELECT COUNT(*) FROM fleet_session_boss WHERE session_id=? AND ended_at IS NULL",
+            session_id
+        )
+        .await,
+        0
+    );
+
+    let (status, body) = harness.get(FC, "/api/history/fleets").await;
+    assert_eq!(status, Status::Ok, "{}", body);
+    let sessions: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let session = &sessions["sessions"][0];
+    assert_eq!(session["id"], json!(session_id));
+    assert_eq!(session["fleet_id"], json!(FLEET_ID));
+    assert_eq!(session["peak_size"], json!(2));
+    assert!(!session["ended_at"].is_null());
+
+    let (status, body) = harness
+        .get(FC, &format!("/api/history/fleets/{}", session_id))
+        .await;
+    assert_eq!(status, Status::Ok, "{}", body);
+    let detail: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let bosses = detail["session"]["bosses"].as_array().unwrap();
+    assert_eq!(bosses.len(), 1);
+    assert_eq!(bosses[0]["character"]["id"], json!(FC));
+    assert!(!bosses[0]["ended_at"].is_null());
+    assert_eq!(detail["timeline"].as_array().unwrap().len(), 2);
+    let composition = detail["composition"].as_array().unwrap();
+    assert_eq!(composition.len(), 1);
+    assert_eq!(composition[0]["hull"]["id"], json!(fit.hull));
+    assert_eq!(composition[0]["pilots"], json!(2));
+}
+
+#[rocket::async_test]
+#[ignore = "needs WAITLIST_TEST_DATABASE_URL, see testing/mod.rs"]
+async fn test_fleet_boss_handover() {
+    let harness = Harness::new().await;
+    let (_dna, fit) = doctrine_fit();
+    for (id, name) in [(FC, "Mock FC"), (NEW_FC, "Mock New FC")] {
+        harness
+            .add_character(id, name, MockCharacter::default())
+            .await;
+    }
+    sqlx::query("INSERT INTO fleet (id, boss_id) VALUES (?, ?)")
+        .bind(FLEET_ID)
+        .bind(FC)
+        .execute(harness.db.as_ref())
+        .await
+        .unwrap();
+    let now = chrono::Utc::now().timestamp();
+    sqlx::query("INSERT INTO fleet_activity (character_id, fleet_id, first_seen, last_seen, hull, has_left, is_boss) VALUES (?, ?, ?, ?, ?, 0, 0)")
+        .bind(NEW_FC)
+        .bind(FLEET_ID)
+        .bind(now)
+        .bind(now)
+        .bind(fit.hull)
+        .execute(harness.db.as_ref())
+        .await
+        .unwrap();
+
+    // The FC has handed boss over and left, so only the new boss can read the fleet
+    harness.mock.state().fleets.insert(
+        FLEET_ID,
+        MockFleet {
+            boss_id: NEW_FC,
+            motd: String::new(),
+            members: vec![MockFleetMember {
+                character_id: NEW_FC,
+                ship_type_id: fit.hull,
+                squad_id: -1,
+                wing_id: -1,
+                role: "fleet_commander".to_string(),
+            }],
+            wings: Vec::new(),
+        },
+    );
+
+    crate::core::fleet_updater::FleetUpdater::new(harness.db.clone(), harness.config.clone())
+        .run_once()
+        .await
+        .unwrap();
+    assert_eq!(
+        query_i64(&harness, "SELECT boss_id FROM fleet WHERE id=?", FLEET_ID).await,
+        NEW_FC
+    );
+    assert_eq!(
+        query_i64(
+            &harness,
+            "SELECT character_id FROM fleet_session_boss JOIN fleet_session ON session_id=fleet_session.id WHERE fleet_id=? AND fleet_session_boss.ended_at IS NULL",
+            FLEET_ID
+        )
+        .await,
+        NEW_FC
+    );
+    assert!(harness
+        .mock
+        .state()
+        .events
+        .iter()
+        .any(|e| e.topic == "fleet_comp" && e.data.contains("handed fleet boss to Mock New FC")));
+}
+
+#[rocket::async_test]
+#[ignore = "needs WAITLIST_TEST_DATABASE_URL, see testing/mod.rs"]
+async fn test_auto_register_fleet() {
+    let harness = Harness::new().await;
+    harness
+        .add_character(FC, "Mock FC", MockCharacter::default())
+        .await;
+    harness.grant_role(FC, "fc").await;
+    harness.mock.state().fleets.insert(
+        FLEET_ID,
+        MockFleet {
+            boss_id: FC,
+            motd: String::new(),
+            members: Vec::new(),
+            wings: Vec::new(),
+        },
+    );
+
+    let (status, body) = harness
+        .post(
+            FC,
+            "/api/fleet/register/auto",
+            &json!({ "character_id": FC }),
+        )
+        .await;
+    assert_eq!(status, Status::Ok, "{}", body);
+
+    // A squad per category, named after it, and one for alts
+    let categories = crate::data::categories::categories();
+    let squads: Vec<String> = harness.mock.state().fleets[&FLEET_ID]
+        .wings
+        .iter()
+        .flat_map(|wing| wing.squads.iter().map(|squad| squad.name.clone()))
+        .collect();
+    for category in &categories {
+        assert!(squads.contains(&category.name), "{:?}", squads);
+    }
+    if !categories.iter().any(|category| category.id == "alt") {
+        assert!(squads.contains(&"Alts".to_string()), "{:?}", squads);
+    }
+    assert_eq!(
+        query_i64(
+            &harness,
+            "SELECT COUNT(*) FROM fleet_squad WHERE fleet_id=?",
+            FLEET_ID
+        )
+        .await,
+        squads.len() as i64
+    );
+    assert_eq!(
+        query_i64(&harness, "SELECT boss_id FROM fleet WHERE id=?", FLEET_ID).await,
+        FC
+    );
+
+    // A second click doesn't make another set of wings
+    let wings = harness.mock.state().fleets[&FLEET_ID].wings.len();
+    let (status, body) = harness
+        .post(
+            FC,
+            "/api/fleet/register/auto",
+            &json!({ "character_id": FC }),
+        )
+        .await;
+    assert_eq!(status, Status::BadRequest, "{}", body);
+    assert_eq!(harness.mock.state().fleets[&FLEET_ID].wings.len(), wings);
+}
+
+#[rocket::async_test]
+#[ignore = "needs WAITLIST_TEST_DATABASE_URL, see testing/mod.rs"]
+async fn test_fleet_roles_survive_motd_edits() {
+    let harness = Harness::new().await;
+    let (_dna, fit) = doctrine_fit();
+    let role = crate::data::motd_roles::roles()
+        .into_iter()
+        .next()
+        .expect("data/motd_roles.yaml has no roles")
+        .id;
+    harness
+        .add_character(FC, "Mock FC", MockCharacter::default())
+        .await;
+    harness.grant_role(FC, "fc").await;
+    for (id, name) in [(PILOT, "Mock Pilot"), (NEW_FC, "Mock New FC")] {
+        harness
+            .add_character(id, name, MockCharacter::default())
+            .await;
+    }
+    let member = |character_id, role: &str| MockFleetMember {
+        character_id,
+        ship_type_id: fit.hull,
+        squad_id: -1,
+        wing_id: -1,
+        role: role.to_string(),
+    };
+    harness.mock.state().fleets.insert(
+        FLEET_ID,
+        MockFleet {
+            boss_id: FC,
+            motd: String::new(),
+            members: vec![
+                member(FC, "fleet_commander"),
+                member(PILOT, "squad_member"),
+                member(NEW_FC, "squad_member"),
+            ],
+            wings: Vec::new(),
+        },
+    );
+    let (status, body) = harness
+        .post(
+            FC,
+            "/api/fleet/register/auto",
+            &json!({ "character_id": FC }),
+        )
+        .await;
+    assert_eq!(status, Status::Ok, "{}", body);
+
+    let (status, body) = harness
+        .post(
+            FC,
+            "/api/fleet/motd/roles",
+            &json!({ "character_id": FC, "role": role, "assignees": [PILOT] }),
+        )
+        .await;
+    assert_eq!(status, Status::Ok, "{}", body);
+    assert!(harness.mock.state().fleets[&FLEET_ID]
+        .motd
+        .contains("Mock Pilot"));
+
+    // Someone edits the MOTD by hand, the next assignment puts the pilot back in
+    harness.mock.state().fleets.get_mut(&FLEET_ID).unwrap().motd = "Welcome".to_string();
+    let (status, body) = harness
+        .post(
+            FC,
+            "/api/fleet/update-motd",
+            &json!({ "character_id": FC, "role": role, "character_name": "Mock FC" }),
+        )
+        .await;
+    assert_eq!(status, Status::Ok, "{}", body);
+    let motd = harness.mock.state().fleets[&FLEET_ID].motd.clone();
+    assert!(motd.starts_with("Welcome"), "{}", motd);
+    assert!(
+        motd.contains("Mock Pilot") && motd.contains("Mock FC"),
+        "{}",
+        motd
+    );
+
+    // Someone links another pilot by hand, and the pilot leaves. The updater takes them off
+    // the role and keeps the one added by hand
+    harness.mock.state().fleets.get_mut(&FLEET_ID).unwrap().motd = format!(
+        "{}, <a href=\"showinfo:1377//{}\">Mock New FC</a>",
+        motd, NEW_FC
+    );
+    harness
+        .mock
+        .state()
+        .fleets
+        .get_mut(&FLEET_ID)
+        .unwrap()
+        .members
+        .retain(|m| m.character_id != PILOT);
+    crate::core::fleet_updater::FleetUpdater::new(harness.db.clone(), harness.config.clone())
+        .run_once()
+        .await
+        .unwrap();
+    let motd = harness.mock.state().fleets[&FLEET_ID].motd.clone();
+    assert!(!motd.contains("Mock Pilot"), "{}", motd);
+    assert!(motd.contains("Mock New FC"), "{}", motd);
+    assert_eq!(
+        query_i64(
+            &harness,
+            "SELECT COUNT(*) FROM fleet_role_assignment WHERE character_id=? AND removed_at IS NOT NULL",
+            PILOT
+        )
+        .await,
+        1
+    );
+
+    let (status, body) = harness
+        .get(FC, &format!("/api/history/roles?character_id={}", PILOT))
+        .await;
+    assert_eq!(status, Status::Ok, "{}", body);
+    assert!(body.contains(&role), "{}", body);
+}
+
+#[rocket::async_test]
+#[ignore = "needs WAITLIST_TEST_DATABASE_URL, see testing/mod.rs"]
+async fn test_sso_login() {
+    let harness = Harness::new().await;
+    harness.mock.state().characters.insert(
+        PILOT,
+        MockCharacter {
+            name: "Mock Pilot".to_string(),
+            scopes: vec!["publicData".to_string()],
+            ..Default::default()
+        },
+    );
+
+    // The mock hands out codes that are just the character ID
+    let character_id = harness
+        .app()
+        .esi_client
+        .process_authorization_code(&PILOT.to_string())
+        .await
+        .unwrap();
+    assert_eq!(character_id, PILOT);
+
+    let name: String = sqlx::query_scalar("SELECT name FROM `character` WHERE id=?")
+        .bind(PILOT)
+        .fetch_one(harness.db.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(name, "Mock Pilot");
+    let (status, _) = harness.get(PILOT, "/api/auth/whoami").await;
+    assert_eq!(status, Status::Ok);
+}
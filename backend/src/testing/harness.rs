@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Once};
+
+use eve_data_core::{SkillLevel, TypeDB, TypeID};
+use rocket::http::{Cookie, Status};
+use rocket::local::asynchronous::Client;
+use serde::Serialize;
+use sqlx::Executor;
+
+use super::mock_esi::{MockCharacter, MockESI, MockSquad, MockWing};
+use crate::app::Application;
+use crate::config::Config;
+
+const SCHEMA: &str = include_str!("../../sql/mysql.sql");
+
+const ALL_SCOPES: &[&str] = &[
+    "publicData",
+    "esi-fleets.read_fleet.v1",
+    "esi-fleets.write_fleet.v1",
+    "esi-ui.open_window.v1",
+    "esi-skills.read_skills.v1",
+    "esi-clones.read_implants.v1",
+    "esi-search.search_structures.v1",
+    "esi-wallet.read_corporation_wallets.v1",
+    "esi-fittings.read_fittings.v1",
+    "esi-fittings.write_fittings.v1",
+];
+
+lazy_static::lazy_static! {
+    // Every test wipes the same database, so they take turns
+    static ref DATABASE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+static OPEN_SDE: Once = Once::new();
+
+/// The backend with a freshly created database, talking to a mock ESI.
+pub struct Harness {
+    pub mock: MockESI,
+    pub client: Client,
+    pub db: Arc<crate::DB>,
+    pub config: Config,
+    _lock: tokio::sync::MutexGuard<'static, ()>,
+}
+
+impl Harness {
+    pub async fn new() -> Harness {
+        let lock = DATABASE_LOCK.lock().await;
+        let database_url = std::env::var("WAITLIST_TEST_DATABASE_URL")
+            .expect("WAITLIST_TEST_DATABASE_URL must point at a database the tests may wipe");
+
+        let mut config: Config = toml::from_str(include_str!("../../config.example.toml"))
+            .expect("Could not load config.example.toml");
+        config.database.url = database_url;
+        if let Ok(path) = std::env::var("WAITLIST_TEST_SDE") {
+            config.sde.path = path;
+        }
+        OPEN_SDE.call_once(|| {
+            TypeDB::open_with_connections(&config.sde.path, config.sde.connections)
+                .and_then(|sde| sde.preload())
+                .expect("Could not open SDE");
+        });
+
+        let mock = MockESI::start(&hex::decode(&config.sse.secret).unwrap()).await;
+        config.esi.esi_base_url = mock.base_url.clone();
+        config.esi.sso_base_url = mock.base_url.clone();
+        config.sse.url = mock.base_url.clone();
+        config.fleet_updater.min_in_fleet = 1;
+
+        let db = Arc::new(
+            sqlx::mysql::MySqlPoolOptions::new()
+                .max_connections(4)
+                .connect(&config.database.url)
+                .await
+                .expect("Could not connect to the test database"),
+        );
+        reset_database(&db).await;
+
+        let application = crate::app::new(db.clone(), config.clone());
+        let rocket = rocket::build()
+            .mount("/", crate::routes::routes())
+            .manage(application);
+        let client = Client::tracked(rocket).await.unwrap();
+
+        Harness {
+            mock,
+            client,
+            db,
+            config,
+            _lock: lock,
+        }
+    }
+
+    pub fn app(&self) -> &Application {
+        self.client.rocket().state().unwrap()
+    }
+
+    /// Creates a character that has logged in with every scope, both here and in the mock.
+    pub async fn add_character(&self, id: i64, name: &str, character: MockCharacter) {
+        sqlx::query("INSERT INTO `character` (id, name) VALUES (?, ?)")
+            .bind(id)
+            .bind(name)
+            .execute(self.db.as_ref())
+            .await
+            .unwrap();
+        let scopes = ALL_SCOPES.join(" ");
+        sqlx::query("INSERT INTO access_token (character_id, access_token, expires, scopes) VALUES (?, ?, ?, ?)")
+            .bind(id)
+            .bind(format!("mock-access-{}", id))
+            .bind(chrono::Utc::now().timestamp() + 3600)
+            .bind(&scopes)
+            .execute(self.db.as_ref())
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO refresh_token (character_id, refresh_token, scopes) VALUES (?, ?, ?)",
+        )
+        .bind(id)
+        .bind(format!("mock-refresh-{}", id))
+        .bind(&scopes)
+        .execute(self.db.as_ref())
+        .await
+        .unwrap();
+
+        self.mock.state().characters.insert(
+            id,
+            MockCharacter {
+                name: name.to_string(),
+                scopes: ALL_SCOPES.iter().map(|s| s.to_string()).collect(),
+                ..character
+            },
+        );
+    }
+
+    pub async fn grant_role(&self, id: i64, role: &str) {
+        sqlx::query(
+            "INSERT INTO admin (character_id, role, granted_at, granted_by_id) VALUES (?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(role)
+        .bind(chrono::Utc::now().timestamp())
+        .bind(id)
+        .execute(self.db.as_ref())
+        .await
+        .unwrap();
+    }
+
+    /// Every skill needed to fly the given types, at level 5.
+    pub fn skills_for(&self, ids: &[TypeID]) -> BTreeMap<TypeID, SkillLevel> {
+        let tree = TypeDB::skill_tree(ids).unwrap();
+        tree.levels.keys().map(|&skill| (skill, 5)).collect()
+    }
+
+    /// Wings for a fleet with one squad per waitlist category, and the assignments to
+    /// register them with.
+    pub fn category_wings(&self) -> (Vec<MockWing>, BTreeMap<String, (i64, i64)>) {
+        let wing_id = 2000;
+        let mut squads = Vec::new();
+        let mut assignments = BTreeMap::new();
+        for (i, category) in crate::data::categories::categories().iter().enumerate() {
+            let squad_id = 3000 + i as i64;
+            squads.push(MockSquad {
+                id: squad_id,
+                name: category.name.clone(),
+            });
+            assignments.insert(category.id.clone(), (wing_id, squad_id));
+        }
+        let wings = vec![MockWing {
+            id: wing_id,
+            name: "On grid".to_string(),
+            squads,
+        }];
+        (wings, assignments)
+    }
+
+    fn cookie(&self, account_id: i64) -> Cookie<'static> {
+        let token = crate::core::auth::create_cookie(self.app(), account_id, None);
+        Cookie::new("authToken", token.0)
+    }
+
+    pub async fn get(&self, account_id: i64, path: &str) -> (Status, String) {
+        let response = self
+            .client
+            .get(path.to_string())
+            .cookie(self.cookie(account_id))
+            .dispatch()
+            .await;
+        (
+            response.status(),
+            response.into_string().await.unwrap_or_default(),
+        )
+    }
+
+    pub async fn post<T: Serialize>(
+        &self,
+        account_id: i64,
+        path: &str,
+        body: &T,
+    ) -> (Status, String) {
+        let response = self
+            .client
+            .post(path.to_string())
+            .cookie(self.cookie(account_id))
+            .json(body)
+            .dispatch()
+            .await;
+        (
+            response.status(),
+            response.into_string().await.unwrap_or_default(),
+        )
+    }
+}
+
+async fn reset_database(db: &crate::DB) {
+    let mut conn = db.acquire().await.unwrap();
+    let tables: Vec<String> = sqlx::query_scalar(
+        "SELECT CAST(table_name AS CHAR) FROM information_schema.tables WHERE table_schema = DATABASE()",
+    )
+    .fetch_all(&mut conn)
+    .await
+    .unwrap();
+
+    conn.execute("SET FOREIGN_KEY_CHECKS=0").await.unwrap();
+    for table in tables {
+        conn.execute(format!("DROP TABLE `{}`", table).as_str())
+            .await
+            .unwrap();
+    }
+    conn.execute("SET FOREIGN_KEY_CHECKS=1").await.unwrap();
+    conn.execute(SCHEMA)
+        .await
+        .expect("Could not create the schema");
+}
@@ -0,0 +1,659 @@
+//! A local stand-in for ESI, EVE SSO and the SSE server. Everything it serves comes from a
+//! `MockState` that tests script up front and inspect afterwards.
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+use branca::Branca;
+use eve_data_core::{SkillLevel, TypeID};
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::json::Json;
+use rocket::{Shutdown, State};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::core::esi::KillmailData;
+use crate::data::srp::WalletJournalEntry;
+
+#[derive(Debug, Clone, Default)]
+pub struct MockCharacter {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub skills: BTreeMap<TypeID, SkillLevel>,
+    pub implants: Vec<TypeID>,
+    /// Hull the character shows up in once it joins a fleet
+    pub ship_type_id: TypeID,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MockFleetMember {
+    pub character_id: i64,
+    pub ship_type_id: TypeID,
+    pub squad_id: i64,
+    pub wing_id: i64,
+    pub role: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MockSquad {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MockWing {
+    pub id: i64,
+    pub name: String,
+    pub squads: Vec<MockSquad>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockFleet {
+    pub boss_id: i64,
+    pub motd: String,
+    pub members: Vec<MockFleetMember>,
+    pub wings: Vec<MockWing>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MockInvite {
+    pub character_id: i64,
+    pub role: String,
+    pub squad_id: i64,
+    pub wing_id: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MockEvent {
+    pub topic: String,
+    pub event: String,
+    pub data: String,
+}
+
+#[derive(Debug, Default)]
+pub struct MockState {
+    pub characters: HashMap<i64, MockCharacter>,
+    pub fleets: HashMap<i64, MockFleet>,
+    pub killmails: HashMap<(i64, String), KillmailData>,
+    /// (corporation, division) -> journal, newest first like ESI returns it
+    pub wallet_journals: HashMap<(i64, i32), Vec<WalletJournalEntry>>,
+    pub type_names: HashMap<i64, String>,
+
+    /// Fleet invites that were sent, by fleet
+    pub invites: Vec<(i64, MockInvite)>,
+    /// Events submitted to the SSE server
+    pub events: Vec<MockEvent>,
+}
+
+impl MockState {
+    pub fn fleet_of(&self, character_id: i64) -> Option<i64> {
+        self.fleets
+            .iter()
+            .find(|(_, fleet)| {
+                fleet.boss_id == character_id
+                    || fleet.members.iter().any(|m| m.character_id == character_id)
+            })
+            .map(|(&id, _)| id)
+    }
+}
+
+/// The running server. Stops when dropped.
+pub struct MockESI {
+    pub base_url: String,
+    pub state: Arc<Mutex<MockState>>,
+    shutdown: Shutdown,
+}
+
+impl MockESI {
+    /// Serves ESI and SSO on a free local port. `sse_secret` is the key SSE submissions are
+    /// encrypted with, so that they can be decoded and recorded.
+    pub async fn start(sse_secret: &[u8]) -> MockESI {
+        let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .and_then(|listener| listener.local_addr())
+            .expect("No free port for the mock ESI")
+            .port();
+        let config = rocket::Config {
+            port,
+            address: Ipv4Addr::LOCALHOST.into(),
+            log_level: rocket::config::LogLevel::Off,
+            ..rocket::Config::debug_default()
+        };
+
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let rocket = rocket::custom(config)
+            .mount("/", routes())
+            .manage(state.clone())
+            .manage(Branca::new(sse_secret).unwrap())
+            .ignite()
+            .await
+            .expect("Could not start the mock ESI");
+        let shutdown = rocket.shutdown();
+        tokio::spawn(rocket.launch());
+
+        // Don't hand out the URL before anything listens on it
+        while TcpStream::connect((Ipv4Addr::LOCALHOST, port)).is_err() {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        MockESI {
+            base_url: format!("http://127.0.0.1:{}", port),
+            state,
+            shutdown,
+        }
+    }
+
+    pub fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl Drop for MockESI {
+    fn drop(&mut self) {
+        self.shutdown.clone().notify();
+    }
+}
+
+type MockStateRef = State<Arc<Mutex<MockState>>>;
+
+/// The character an access token was handed out to. Tokens look like `mock-access-<id>`.
+struct Bearer(i64);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Bearer {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let character_id = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer mock-access-"))
+            .and_then(|id| id.parse().ok());
+        match character_id {
+            Some(id) => Outcome::Success(Bearer(id)),
+            None => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+fn esi_error(status: Status, message: &str) -> (Status, Json<Value>) {
+    (status, Json(json!({ "error": message })))
+}
+
+type MockResult<T> = Result<Json<T>, (Status, Json<Value>)>;
+
+#[derive(FromForm)]
+struct TokenRequest {
+    grant_type: String,
+    code: Option<String>,
+    refresh_token: Option<String>,
+}
+
+// The authorization code is the character ID, so tests can log in as anyone they scripted
+#[post("/v2/oauth/token", data = "<input>")]
+fn oauth_token(state: &MockStateRef, input: Form<TokenRequest>) -> MockResult<Value> {
+    let character_id: Option<i64> = match input.grant_type.as_str() {
+        "authorization_code" => input.code.as_ref().and_then(|code| code.parse().ok()),
+        "refresh_token" => input
+            .refresh_token
+            .as_ref()
+            .and_then(|token| token.strip_prefix("mock-refresh-"))
+            .and_then(|id| id.parse().ok()),
+        _ => None,
+    };
+    match character_id {
+        Some(id) if state.lock().unwrap().characters.contains_key(&id) => Ok(Json(json!({
+            "access_token": format!("mock-access-{}", id),
+            "refresh_token": format!("mock-refresh-{}", id),
+            "expires_in": 1199,
+        }))),
+        _ => Err(esi_error(Status::BadRequest, "invalid_grant")),
+    }
+}
+
+#[get("/oauth/verify")]
+fn oauth_verify(state: &MockStateRef, bearer: Bearer) -> MockResult<Value> {
+    let state = state.lock().unwrap();
+    let character = state
+        .characters
+        .get(&bearer.0)
+        .ok_or_else(|| esi_error(Status::Unauthorized, "invalid token"))?;
+    Ok(Json(json!({
+        "CharacterID": bearer.0,
+        "CharacterName": character.name,
+        "Scopes": character.scopes.join(" "),
+    })))
+}
+
+fn character_name(state: &MockStateRef, character_id: i64) -> MockResult<Value> {
+    match state.lock().unwrap().characters.get(&character_id) {
+        Some(character) => Ok(Json(json!({ "name": character.name }))),
+        None => Err(esi_error(Status::NotFound, "Character not found")),
+    }
+}
+
+#[get("/v4/characters/<character_id>")]
+fn character_v4(state: &MockStateRef, character_id: i64) -> MockResult<Value> {
+    character_name(state, character_id)
+}
+
+#[get("/v5/characters/<character_id>")]
+fn character_v5(state: &MockStateRef, character_id: i64) -> MockResult<Value> {
+    character_name(state, character_id)
+}
+
+#[get("/v4/characters/<character_id>/skills")]
+fn skills(state: &MockStateRef, bearer: Bearer, character_id: i64) -> MockResult<Value> {
+    if bearer.0 != character_id {
+        return Err(esi_error(
+            Status::Forbidden,
+            "Token not valid for character",
+        ));
+    }
+    let state = state.lock().unwrap();
+    let character = state
+        .characters
+        .get(&character_id)
+        .ok_or_else(|| esi_error(Status::NotFound, "Character not found"))?;
+    let skills: Vec<Value> = character
+        .skills
+        .iter()
+        .map(|(skill_id, level)| {
+            json!({
+                "skill_id": skill_id,
+                "trained_skill_level": level,
+                "active_skill_level": level,
+                "skillpoints_in_skill": 0,
+            })
+        })
+        .collect();
+    Ok(Json(json!({ "skills": skills, "total_sp": 0 })))
+}
+
+#[get("/v2/characters/<character_id>/implants")]
+fn implants(state: &MockStateRef, bearer: Bearer, character_id: i64) -> MockResult<Vec<TypeID>> {
+    if bearer.0 != character_id {
+        return Err(esi_error(
+            Status::Forbidden,
+            "Token not valid for character",
+        ));
+    }
+    match state.lock().unwrap().characters.get(&character_id) {
+        Some(character) => Ok(Json(character.implants.clone())),
+        None => Err(esi_error(Status::NotFound, "Character not found")),
+    }
+}
+
+#[get("/v1/characters/<character_id>/fleet")]
+fn character_fleet(state: &MockStateRef, bearer: Bearer, character_id: i64) -> MockResult<Value> {
+    if bearer.0 != character_id {
+        return Err(esi_error(
+            Status::Forbidden,
+            "Token not valid for character",
+        ));
+    }
+    let state = state.lock().unwrap();
+    let fleet_id = state
+        .fleet_of(character_id)
+        .ok_or_else(|| esi_error(Status::NotFound, "Character is not in a fleet"))?;
+    let fleet = &state.fleets[&fleet_id];
+    let member = fleet
+        .members
+        .iter()
+        .find(|m| m.character_id == character_id);
+    Ok(Json(json!({
+        "fleet_id": fleet_id,
+        "fleet_boss_id": fleet.boss_id,
+        "role": member.map(|m| m.role.as_str()).unwrap_or("fleet_commander"),
+        "squad_id": member.map(|m| m.squad_id).unwrap_or(-1),
+        "wing_id": member.map(|m| m.wing_id).unwrap_or(-1),
+    })))
+}
+
+/// Runs `f` on a fleet the bearer is the boss of, failing like ESI does otherwise.
+fn with_boss_fleet<T>(
+    state: &MockStateRef,
+    bearer: &Bearer,
+    fleet_id: i64,
+    f: impl FnOnce(&mut MockFleet) -> T,
+) -> Result<T, (Status, Json<Value>)> {
+    let mut state = state.lock().unwrap();
+    match state.fleets.get_mut(&fleet_id) {
+        Some(fleet) if fleet.boss_id == bearer.0 => Ok(f(fleet)),
+        Some(_) => Err(esi_error(
+            Status::Forbidden,
+            "The fleet does not exist or you don't have access to it!",
+        )),
+        None => Err(esi_error(
+            Status::NotFound,
+            "The fleet does not exist or you don't have access to it!",
+        )),
+    }
+}
+
+#[get("/v1/fleets/<fleet_id>")]
+fn fleet(state: &MockStateRef, bearer: Bearer, fleet_id: i64) -> MockResult<Value> {
+    with_boss_fleet(state, &bearer, fleet_id, |fleet| {
+        Json(json!({
+            "is_free_move": false,
+            "is_registered": false,
+            "is_voice_enabled": false,
+            "motd": fleet.motd,
+        }))
+    })
+}
+
+#[derive(Deserialize)]
+struct FleetUpdate {
+    motd: Option<String>,
+}
+
+#[put("/v1/fleets/<fleet_id>", data = "<input>")]
+fn update_fleet(
+    state: &MockStateRef,
+    bearer: Bearer,
+    fleet_id: i64,
+    input: Json<FleetUpdate>,
+) -> Result<Status, (Status, Json<Value>)> {
+    with_boss_fleet(state, &bearer, fleet_id, |fleet| {
+        if let Some(motd) = &input.motd {
+            fleet.motd = motd.clone();
+        }
+        Status::NoContent
+    })
+}
+
+#[get("/v1/fleets/<fleet_id>/members")]
+fn fleet_members(
+    state: &MockStateRef,
+    bearer: Bearer,
+    fleet_id: i64,
+) -> MockResult<Vec<MockFleetMember>> {
+    with_boss_fleet(state, &bearer, fleet_id, |fleet| {
+        Json(fleet.members.clone())
+    })
+}
+
+// Pilots in the mock accept every invite straight away
+#[post("/v1/fleets/<fleet_id>/members", data = "<input>")]
+fn invite(
+    state: &MockStateRef,
+    bearer: Bearer,
+    fleet_id: i64,
+    input: Json<MockInvite>,
+) -> Result<Status, (Status, Json<Value>)> {
+    let ship_type_id = state
+        .lock()
+        .unwrap()
+        .characters
+        .get(&input.character_id)
+        .map(|character| character.ship_type_id)
+        .ok_or_else(|| esi_error(Status::UnprocessableEntity, "Character not found"))?;
+    with_boss_fleet(state, &bearer, fleet_id, |fleet| {
+        fleet
+            .members
+            .retain(|m| m.character_id != input.character_id);
+        fleet.members.push(MockFleetMember {
+            character_id: input.character_id,
+            ship_type_id,
+            squad_id: input.squad_id,
+            wing_id: input.wing_id,
+            role: input.role.clone(),
+        });
+    })?;
+    state
+        .lock()
+        .unwrap()
+        .invites
+        .push((fleet_id, input.into_inner()));
+    Ok(Status::NoContent)
+}
+
+#[delete("/v1/fleets/<fleet_id>/members/<member_id>")]
+fn kick(
+    state: &MockStateRef,
+    bearer: Bearer,
+    fleet_id: i64,
+    member_id: i64,
+) -> Result<Status, (Status, Json<Value>)> {
+    with_boss_fleet(state, &bearer, fleet_id, |fleet| {
+        fleet.members.retain(|m| m.character_id != member_id);
+        Status::NoContent
+    })
+}
+
+#[get("/v1/fleets/<fleet_id>/wings")]
+fn fleet_wings(state: &MockStateRef, bearer: Bearer, fleet_id: i64) -> MockResult<Vec<MockWing>> {
+    with_boss_fleet(state, &bearer, fleet_id, |fleet| Json(fleet.wings.clone()))
+}
+
+fn next_wing_id(fleet: &MockFleet) -> i64 {
+    let wings = fleet.wings.iter().map(|wing| wing.id);
+    let squads = fleet
+        .wings
+        .iter()
+        .flat_map(|wing| wing.squads.iter().map(|squad| squad.id));
+    wings.chain(squads).max().unwrap_or(0) + 1
+}
+
+// Like ESI, new wings come without squads and everything new gets a default name
+#[post("/v1/fleets/<fleet_id>/wings")]
+fn create_wing(state: &MockStateRef, bearer: Bearer, fleet_id: i64) -> MockResult<Value> {
+    with_boss_fleet(state, &bearer, fleet_id, |fleet| {
+        let id = next_wing_id(fleet);
+        fleet.wings.push(MockWing {
+            id,
+            name: "Wing".to_string(),
+            squads: Vec::new(),
+        });
+        Json(json!({ "wing_id": id }))
+    })
+}
+
+#[post("/v1/fleets/<fleet_id>/wings/<wing_id>/squads")]
+fn create_squad(
+    state: &MockStateRef,
+    bearer: Bearer,
+    fleet_id: i64,
+    wing_id: i64,
+) -> MockResult<Value> {
+    with_boss_fleet(state, &bearer, fleet_id, |fleet| {
+        let id = next_wing_id(fleet);
+        let wing = fleet.wings.iter_mut().find(|wing| wing.id == wing_id)?;
+        wing.squads.push(MockSquad {
+            id,
+            name: "Squad".to_string(),
+        });
+        Some(Json(json!({ "squad_id": id })))
+    })?
+    .ok_or_else(|| esi_error(Status::NotFound, "Wing not found"))
+}
+
+#[derive(Deserialize)]
+struct Rename {
+    name: String,
+}
+
+#[put("/v1/fleets/<fleet_id>/wings/<wing_id>", data = "<input>")]
+fn rename_wing(
+    state: &MockStateRef,
+    bearer: Bearer,
+    fleet_id: i64,
+    wing_id: i64,
+    input: Json<Rename>,
+) -> Result<Status, (Status, Json<Value>)> {
+    with_boss_fleet(state, &bearer, fleet_id, |fleet| {
+        match fleet.wings.iter_mut().find(|wing| wing.id == wing_id) {
+            Some(wing) => {
+                wing.name = input.name.clone();
+                Status::NoContent
+            }
+            None => Status::NotFound,
+        }
+    })
+}
+
+#[delete("/v1/fleets/<fleet_id>/wings/<wing_id>")]
+fn delete_wing(
+    state: &MockStateRef,
+    bearer: Bearer,
+    fleet_id: i64,
+    wing_id: i64,
+) -> Result<Status, (Status, Json<Value>)> {
+    with_boss_fleet(state, &bearer, fleet_id, |fleet| {
+        let before = fleet.wings.len();
+        fleet.wings.retain(|wing| wing.id != wing_id);
+        match fleet.wings.len() < before {
+            true => Status::NoContent,
+            false => Status::NotFound,
+        }
+    })
+}
+
+#[put("/v1/fleets/<fleet_id>/squads/<squad_id>", data = "<input>")]
+fn rename_squad(
+    state: &MockStateRef,
+    bearer: Bearer,
+    fleet_id: i64,
+    squad_id: i64,
+    input: Json<Rename>,
+) -> Result<Status, (Status, Json<Value>)> {
+    with_boss_fleet(state, &bearer, fleet_id, |fleet| {
+        match fleet
+            .wings
+            .iter_mut()
+            .flat_map(|wing| wing.squads.iter_mut())
+            .find(|squad| squad.id == squad_id)
+        {
+            Some(squad) => {
+                squad.name = input.name.clone();
+                Status::NoContent
+            }
+            None => Status::NotFound,
+        }
+    })
+}
+
+#[get("/v1/killmails/<killmail_id>/<hash>")]
+fn killmail(state: &MockStateRef, killmail_id: i64, hash: String) -> MockResult<KillmailData> {
+    let state = state.lock().unwrap();
+    let killmail = state.killmails.get(&(killmail_id, hash)).ok_or_else(|| {
+        esi_error(
+            Status::UnprocessableEntity,
+            "Invalid killmail_id and/or killmail_hash",
+        )
+    })?;
+    // KillmailData isn't Clone, round-trip it instead
+    let killmail = serde_json::to_value(killmail).unwrap();
+    Ok(Json(serde_json::from_value(killmail).unwrap()))
+}
+
+#[get("/v1/corporations/<corporation_id>/wallets")]
+fn corporation_wallets(state: &MockStateRef, _bearer: Bearer, corporation_id: i64) -> Json<Value> {
+    let state = state.lock().unwrap();
+    let wallets: Vec<Value> = state
+        .wallet_journals
+        .iter()
+        .filter(|((corporation, _), _)| *corporation == corporation_id)
+        .map(|(&(_, division), journal)| {
+            let balance = journal.first().map(|entry| entry.balance).unwrap_or(0.0);
+            json!({ "division": division, "balance": balance })
+        })
+        .collect();
+    Json(json!(wallets))
+}
+
+#[get("/v6/corporations/<corporation_id>/wallets/<division>/journal")]
+fn corporation_journal(
+    state: &MockStateRef,
+    _bearer: Bearer,
+    corporation_id: i64,
+    division: i32,
+) -> Json<Vec<WalletJournalEntry>> {
+    let state = state.lock().unwrap();
+    Json(
+        state
+            .wallet_journals
+            .get(&(corporation_id, division))
+            .cloned()
+            .unwrap_or_default(),
+    )
+}
+
+#[get("/v3/universe/types/<type_id>")]
+fn universe_type(state: &MockStateRef, type_id: i64) -> MockResult<Value> {
+    match state.lock().unwrap().type_names.get(&type_id) {
+        Some(name) => Ok(Json(json!({ "type_id": type_id, "name": name }))),
+        None => Err(esi_error(Status::NotFound, "Type not found!")),
+    }
+}
+
+#[post("/v2/universe/names", data = "<ids>")]
+fn universe_names(state: &MockStateRef, ids: Json<Vec<i64>>) -> Json<Vec<Value>> {
+    let state = state.lock().unwrap();
+    let names = ids
+        .iter()
+        .filter_map(|id| {
+            if let Some(character) = state.characters.get(id) {
+                Some(json!({ "id": id, "name": character.name, "category": "character" }))
+            } else {
+                state
+                    .type_names
+                    .get(id)
+                    .map(|name| json!({ "id": id, "name": name, "category": "inventory_type" }))
+            }
+        })
+        .collect();
+    Json(names)
+}
+
+#[derive(Deserialize)]
+struct Submission {
+    events: Vec<MockEvent>,
+}
+
+#[post("/submit", data = "<body>")]
+fn sse_submit(state: &MockStateRef, branca: &State<Branca>, body: String) -> Status {
+    let submission: Option<Submission> = branca
+        .decode(&body, 0)
+        .ok()
+        .and_then(|payload| rmp_serde::from_read_ref(&payload).ok());
+    match submission {
+        Some(submission) => {
+            state.lock().unwrap().events.extend(submission.events);
+            Status::Ok
+        }
+        None => Status::BadRequest,
+    }
+}
+
+fn routes() -> Vec<rocket::Route> {
+    routes![
+        oauth_token,
+        oauth_verify,
+        character_v4,
+        character_v5,
+        skills,
+        implants,
+        character_fleet,
+        fleet,
+        update_fleet,
+        fleet_members,
+        invite,
+        kick,
+        fleet_wings,
+        create_wing,
+        create_squad,
+        rename_wing,
+        delete_wing,
+        rename_squad,
+        killmail,
+        corporation_wallets,
+        corporation_journal,
+        universe_type,
+        universe_names,
+        sse_submit,
+    ]
+}
@@ -5,6 +5,38 @@ use crate::data::{categories, fits::DoctrineFit, skills::Skills};
 use eve_data_core::{FitError, Fitting, TypeDB, TypeID};
 use serde::Serialize;
 
+macro_rules! emitted_tags {
+    ($($variant:ident => $name:literal,)*) => {
+        /// Every tag the checker can attach to an x-up. Each one needs an entry in tags.yaml.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+        pub enum EmittedTag {
+            $($variant,)*
+        }
+
+        impl EmittedTag {
+            pub const ALL: &'static [EmittedTag] = &[$(EmittedTag::$variant,)*];
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(EmittedTag::$variant => $name,)*
+                }
+            }
+        }
+    };
+}
+
+emitted_tags! {
+    HqFc => "HQ-FC",
+    Trainee => "TRAINEE",
+    Logi => "LOGI",
+    Alt => "ALT",
+    Dps => "DPS",
+    HybridTrimark => "HYBRID-TRIMARK",
+    NonHybridHyperspatial => "NON-HYBRID-HYPERSPATIAL",
+    DpsHoursReached => "DPS-HOURS-REACHED",
+    Overfitted => "OVERFITTED",
+}
+
 #[derive(Debug)]
 pub struct Output {
     pub approved: bool,
@@ -21,6 +53,7 @@ pub struct PubAnalysis {
     missing: BTreeMap<TypeID, i64>,
     extra: BTreeMap<TypeID, i64>,
     cargo_missing: BTreeMap<TypeID, i64>,
+    charge_missing: BTreeMap<TypeID, BTreeMap<TypeID, i64>>,
     downgraded: BTreeMap<TypeID, BTreeMap<TypeID, i64>>,
 }
 
@@ -29,6 +62,7 @@ pub struct PilotData<'a> {
     pub time_in_fleet: i64,
     pub skills: &'a Skills,
     pub access_keys: &'a BTreeSet<String>,
+    pub is_alt: bool,
 }
 
 pub struct FitChecker<'a> {
@@ -39,7 +73,7 @@ pub struct FitChecker<'a> {
     doctrine_fit: Option<&'static DoctrineFit>,
     pilot: &'a PilotData<'a>,
 
-    tags: BTreeSet<&'static str>,
+    tags: BTreeSet<EmittedTag>,
     errors: Vec<String>,
     analysis: Option<PubAnalysis>,
 }
@@ -63,6 +97,7 @@ impl<'a> FitChecker<'a> {
         };
 
         checker.check_module_skills()?;
+        checker.check_fitting_resources()?;
         checker.check_fit();
         //checker.check_fit_implants_reqs();
         checker.set_category();
@@ -92,24 +127,61 @@ impl<'a> FitChecker<'a> {
     }*/
 
     fn check_module_skills(&mut self) -> Result<(), FitError> {
-        let mut module_ids = vec![self.fit.hull];
-        for &module_id in self.fit.modules.keys() {
-            module_ids.push(module_id);
+        let tree = self.fit.skill_tree()?;
+        let have = |skill_id| self.pilot.skills.get(skill_id);
+
+        let mut type_ids = vec![self.fit.hull];
+        type_ids.extend(self.fit.modules.keys());
+        type_ids.extend(self.fit.charges.values().flat_map(|charges| charges.keys()));
+        type_ids.sort_unstable();
+        type_ids.dedup();
+        let missing: Vec<_> = type_ids
+            .into_iter()
+            .map(|type_id| (type_id, tree.missing_for(type_id, have)))
+            .filter(|(_type_id, missing)| !missing.is_empty())
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
         }
-        let types = TypeDB::load_types(&module_ids)?;
 
-        for (_type_id, typedata) in types {
-            let typedata = typedata.expect("Fit was checked so this can't happen?");
-            for (&skill_id, &level) in &typedata.skill_requirements {
-                if self.pilot.skills.get(skill_id) < level {
-                    self.errors
-                        .push(format!("Missing skills to online/use '{}'", typedata.name));
-                }
+        let mut name_ids: Vec<TypeID> = missing.iter().map(|(type_id, _)| *type_id).collect();
+        name_ids.extend(
+            missing
+                .iter()
+                .flat_map(|(_, skills)| skills.iter().map(|m| m.skill)),
+        );
+        let names = TypeDB::names_of(&name_ids)?;
+        let name = |id: TypeID| names.get(&id).map(String::as_str).unwrap_or("unknown");
+
+        for (type_id, skills) in missing {
+            for skill in skills {
+                self.errors.push(format!(
+                    "Missing {} level {} (have {}) to online/use '{}'",
+                    name(skill.skill),
+                    skill.need,
+                    skill.have,
+                    name(type_id)
+                ));
             }
         }
         Ok(())
     }
 
+    // Hull bonuses and fitting implants aren't modelled, so this is only a hint for the FC
+    fn check_fitting_resources(&mut self) -> Result<(), FitError> {
+        let layout = self
+            .fit
+            .layout(|skill_id| self.pilot.skills.get(skill_id))?;
+        match layout.check_resources() {
+            Ok(()) => Ok(()),
+            Err(FitError::Overfitted { .. }) => {
+                self.tags.insert(EmittedTag::Overfitted);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     fn check_fit(&mut self) {
         // Auto-approve Vindicator if pilot has VINDI badge
         if self.fit.hull == type_id!("Vindicator") && self.badges.contains(&String::from("VINDI")) {
@@ -123,7 +195,7 @@ impl<'a> FitChecker<'a> {
         if let Some((doctrine_fit, diff)) = fitmatch::find_fit(self.fit) {
             self.doctrine_fit = Some(doctrine_fit);
             let fit_ok = diff.module_downgraded.is_empty() && diff.module_missing.is_empty();
-            if !(diff.cargo_missing.is_empty() && fit_ok) {
+            if !(diff.cargo_missing.is_empty() && diff.charge_missing.is_empty() && fit_ok) {
                 self.approved = false;
             }
             self.analysis = Some(PubAnalysis {
@@ -132,6 +204,7 @@ impl<'a> FitChecker<'a> {
                 extra: diff.module_extra,
                 downgraded: diff.module_downgraded,
                 cargo_missing: diff.cargo_missing,
+                charge_missing: diff.charge_missing,
             });
         } else {
             self.approved = false;
@@ -151,36 +224,36 @@ impl<'a> FitChecker<'a> {
         
         // Flag combinations with tags and prevent auto-approval
         if has_hybrid_set && has_trimark {
-            self.tags.insert("HYBRID-TRIMARK");
+            self.tags.insert(EmittedTag::HybridTrimark);
             self.approved = false;
         }
         
         if !has_hybrid_set && has_hyperspatial {
-            self.tags.insert("NON-HYBRID-HYPERSPATIAL");
+            self.tags.insert(EmittedTag::NonHybridHyperspatial);
             self.approved = false;
         }
     }
     
     fn has_hybrid_implant_set(&self) -> bool {
         // HYBRID set: Amulet Alpha, Beta, Delta, Epsilon, Gamma + WS-618
-        let hybrid_implants = [
-            type_id!("High-grade Amulet Alpha"),
-            type_id!("High-grade Amulet Beta"),
-            type_id!("High-grade Amulet Delta"),
-            type_id!("High-grade Amulet Epsilon"),
-            type_id!("High-grade Amulet Gamma"),
+        const HYBRID_IMPLANTS: &[TypeID] = type_ids![
+            "High-grade Amulet Alpha",
+            "High-grade Amulet Beta",
+            "High-grade Amulet Delta",
+            "High-grade Amulet Epsilon",
+            "High-grade Amulet Gamma",
         ];
-        
-        hybrid_implants.iter().all(|&implant| self.pilot.implants.contains(&implant))
+
+        HYBRID_IMPLANTS.iter().all(|&implant| self.pilot.implants.contains(&implant))
     }
 
     fn check_time_in_fleet(&mut self) {
-        let pilot_dps = self.tags.contains("DPS");
+        let pilot_dps = self.tags.contains(&EmittedTag::Dps);
 
         if self.fit.hull == type_id!("Vindicator") {
             if self.pilot.time_in_fleet > (20 * 3600) && !pilot_dps {
                 self.approved = false;
-                self.tags.insert("DPS-HOURS-REACHED");
+                self.tags.insert(EmittedTag::DpsHoursReached);
             }
         }
     }
@@ -249,33 +322,41 @@ impl<'a> FitChecker<'a> {
         }
     */
     fn set_category(&mut self) {
-        let category = categories::categorize(self.fit).unwrap_or_else(|| "other".to_string());
+        let category = categories::categorize(&categories::CategoryInput {
+            fit: self.fit,
+            doctrine: self.doctrine_fit.map(|fit| fit.name.as_str()),
+            is_alt: self.pilot.is_alt,
+            badges: self.badges,
+        })
+        .unwrap_or_else(|| "other".to_string());
         self.category = Some(category);
     }
 
     fn add_snowflake_tags(&mut self) {
         if self.pilot.access_keys.contains("waitlist-tag:HQ-FC") {
-            self.tags.insert("HQ-FC");
+            self.tags.insert(EmittedTag::HqFc);
         } else if self.pilot.access_keys.contains("waitlist-tag:TRAINEE") {
-            self.tags.insert("TRAINEE");
+            self.tags.insert(EmittedTag::Trainee);
         } else {
             if self.badges.contains(&String::from("LOGI")) {
-                self.tags.insert("LOGI");
+                self.tags.insert(EmittedTag::Logi);
             }
 
             if self.badges.contains(&String::from("ALT")) {
-                self.tags.insert("ALT");
+                self.tags.insert(EmittedTag::Alt);
             }
             if self.badges.contains(&String::from("DPS")) {
-                self.tags.insert("DPS");
+                self.tags.insert(EmittedTag::Dps);
             }
         }
     }
 
     fn finish(self) -> Result<Output, FitError> {
+        let mut tags: Vec<&'static str> = self.tags.into_iter().map(EmittedTag::as_str).collect();
+        tags.sort_unstable();
         Ok(Output {
             approved: self.approved,
-            tags: self.tags.into_iter().collect(),
+            tags,
             errors: self.errors,
             category: self.category.expect("Category not assigned"),
             analysis: self.analysis,
@@ -1,11 +1,12 @@
 use std::collections::HashSet;
 
-use eve_data_core::{Fitting, TypeDB, TypeError, TypeID};
+use eve_data_core::{Fitting, TypeError, TypeID};
 use serde::Deserialize;
 
 use crate::data::{
     fitdiffer::{DiffResult, FitDiffer},
     fits::{self, DoctrineFit},
+    typeselector::TypeSelector,
     variations, yamlhelper,
 };
 
@@ -22,7 +23,7 @@ struct Identifier {
 fn load() -> Result<Identifier, TypeError> {
     #[derive(Deserialize, Debug)]
     struct File {
-        identification: Vec<String>,
+        identification: Vec<TypeSelector>,
     }
 
     let f: File = yamlhelper::from_file("./data/modules.yaml");
@@ -31,14 +32,15 @@ fn load() -> Result<Identifier, TypeError> {
 
     let variator = variations::get();
     let variator_guard = variator.read().unwrap();
-    for module_name in f.identification {
-        let module_id = TypeDB::id_of(&module_name)?;
-        if let Some(vars) = variator_guard.get(module_id) {
-            for var in vars {
-                result.insert(var.to);
+    for selector in f.identification {
+        for module_id in selector.resolve()? {
+            if let Some(vars) = variator_guard.get(module_id) {
+                for var in vars {
+                    result.insert(var.to);
+                }
+            } else {
+                result.insert(module_id);
             }
-        } else {
-            result.insert(module_id);
         }
     }
 
@@ -100,6 +102,12 @@ impl Identifier {
                 score += 5 * count * self.multiplier(type_id);
             }
         }
+        // Wrong ammo loaded: right fit, just needs a reload
+        for to in diff.charge_missing.values() {
+            for count in to.values() {
+                score += count;
+            }
+        }
         // Upgraded? Either rich, or not actually part of our fit
         for (&type_id, to) in &diff.module_upgraded {
             for count in to.values() {
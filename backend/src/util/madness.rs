@@ -62,7 +62,11 @@ impl<'r> rocket::response::Responder<'r, 'static> for Madness {
             Self::DatabaseError(_)
             | Self::SSEError(_)
             | Self::ESIError(
-                ESIError::HTTPError(_) | ESIError::DatabaseError(_) | ESIError::Status(_),
+                ESIError::HTTPError(_)
+                | ESIError::DatabaseError(_)
+                | ESIError::Status(_)
+                | ESIError::InvalidJSON(_)
+                | ESIError::TokenDecryption(_),
             ) => Status::InternalServerError,
 
             Self::ESIError(ESIError::WithMessage(code, _body)) => Status { code: *code },
@@ -175,7 +175,7 @@ function extractAnalysisIds(analysis) {
     if (!analysis[group]) continue;
     Object.keys(analysis[group]).forEach((id) => ids.add(id));
   }
-  for (const group of ["downgraded", "upgraded"]) {
+  for (const group of ["downgraded", "upgraded", "charge_missing"]) {
     if (!analysis[group]) continue;
     Object.entries(analysis[group]).forEach(([origModuleId, newItems]) => {
       ids.add(origModuleId);
@@ -240,6 +240,14 @@ function filterSlots(counts, analysis, moduleInfo) {
       _addCount(slots["cargo"].missing, moduleId, count);
     }
   }
+  if (analysis && analysis.charge_missing) {
+    // Charges that should be loaded: show them with the rest of the missing ammo
+    for (const charges of Object.values(analysis.charge_missing)) {
+      for (const [chargeId, count] of Object.entries(charges)) {
+        _addCount(slots["cargo"].missing, chargeId, count);
+      }
+    }
+  }
   if (analysis && analysis.missing) {
     for (const [moduleId, count] of Object.entries(analysis.missing)) {
       const slot = getSlot(moduleId, moduleInfo);
@@ -127,6 +127,14 @@ async function registerFleet({ fleetInfo, categoryMatches, authContext }) {
   });
 }
 
+async function autoRegisterFleet({ authContext }) {
+  return await apiCall("/api/fleet/register/auto", {
+    json: {
+      character_id: authContext.current.id,
+    },
+  });
+}
+
 function detectSquads({ matches, categories, wings }) {
   var newMatches = { ...matches };
   var hadChanges = false;
@@ -216,6 +224,12 @@ export function FleetRegister() {
         }
       >
         Continue
+      </Button>{" "}
+      <Button
+        onClick={(evt) => toaster(toastContext, autoRegisterFleet({ authContext }))}
+        title="Creates a squad for every category (and alts) in new wings, then registers them"
+      >
+        Create squads and continue
       </Button>
     </>
   );
@@ -25,25 +25,11 @@ import { PilotTags } from "../../Components/Badge";
 import soundFile from "../../Components/Event/notification-error-427345.mp3";
 const marauders = ["Paladin", "Kronos", "Golem", "Vargur"];
 const booster = ["Eos", "Damnation", "Claymore", "Vulture", "Sleipnir", "Astarte", "Absolution", "Nighthawk", ];
-const roleOrder = ["DDD", "MS", "LR", "MTAC", "PS"];
-
-// Hull validation mapping (same as backend)
-const getAllowedHullsForRole = (role) => {
-  const roleUpper = role.toUpperCase();
-  switch (roleUpper) {
-    case "DDD":
-      return ["vindicator"];
-    case "MS":
-      return ["damnation", "eos", "claymore"];
-    case "LR":
-      return ["kronos"];
-    case "MTAC":
-      return ["paladin", "vargur", "occator", "mastodon", "bustard", "impel"];
-    case "PS":
-      return ["paladin", "vargur"];
-    default:
-      return [];
-  }
+
+// MOTD roles and the hulls they are flown in come from the backend (motd_roles.yaml)
+const roleSuitsShip = (motdRoles, roleId, ship) => {
+  const role = (motdRoles || []).find((role) => role.id === roleId);
+  return !!role && !!ship && (role.hulls.length === 0 || role.hulls.includes(ship.id));
 };
 
 function PilotTagsFromId({ characterId }) {
@@ -53,7 +39,7 @@ function PilotTagsFromId({ characterId }) {
 }
 
 // Context menu component for role selection
-function RoleContextMenu({ open, setOpen, position, character, onRoleSelect, authContext }) {
+function RoleContextMenu({ open, setOpen, position, character, roles, onRoleSelect, authContext }) {
   const theme = React.useContext(ThemeContext);
   
   // Hooks must be called unconditionally - move before early return
@@ -113,12 +99,7 @@ function RoleContextMenu({ open, setOpen, position, character, onRoleSelect, aut
   const borderColor = theme.colors.accent2 || "#ccc";
   const itemBorderColor = theme.colors.accent2 || "#eee";
   
-  const isRoleSuitable = (role) => {
-    if (!character?.ship?.name) return false;
-    const allowedHulls = getAllowedHullsForRole(role);
-    const shipNameLower = character.ship.name.toLowerCase();
-    return allowedHulls.includes(shipNameLower);
-  };
+  const isRoleSuitable = (role) => roleSuitsShip(roles, role, character?.ship);
 
   // Early return after all hooks
   if (!open) return null;
@@ -140,12 +121,13 @@ function RoleContextMenu({ open, setOpen, position, character, onRoleSelect, aut
       }}
       onClick={(e) => e.stopPropagation()}
     >
-      {roleOrder.map((role) => {
+      {(roles || []).map(({ id: role, name }) => {
         const suitable = isRoleSuitable(role);
         
         return (
           <div
             key={role}
+            title={name}
             style={{
               padding: "8px 16px",
               cursor: "pointer",
@@ -306,7 +288,7 @@ function getFleetMembers(fleet) {
   return members;
 }
 
-function RoleAssignments({ roleAssignments }) {
+function RoleAssignments({ roleAssignments, roles }) {
   const prevRoleAssignmentsRef = React.useRef(null);
   const audioRef = React.useRef(null);
 
@@ -339,7 +321,7 @@ function RoleAssignments({ roleAssignments }) {
     let hasDeparture = false;
     const departedCharacters = [];
     
-    for (const role of roleOrder) {
+    for (const { id: role } of roles) {
       const prevChars = prevRoleAssignmentsRef.current[role] || [];
       const currentChars = roleAssignments[role] || [];
       
@@ -388,13 +370,13 @@ function RoleAssignments({ roleAssignments }) {
     }
 
     prevRoleAssignmentsRef.current = JSON.parse(JSON.stringify(roleAssignments));
-  }, [roleAssignments, roleAssignmentsSerialized]);
+  }, [roleAssignments, roleAssignmentsSerialized, roles]);
 
   return (
     <div style={{ marginBottom: "1em" }}>
       <Title>Fleet Roles</Title>
       <InputGroup>
-        {roleOrder.map((role) => {
+        {roles.map(({ id: role }) => {
           const characters = roleAssignments && roleAssignments[role];
           if (!characters || characters.length === 0) {
             return (
@@ -550,9 +532,11 @@ export function FleetMembers({ fleetpage = true, setStatTempActive = null }) {
     ) || false;
     
     // Check hull
-    const allowedHulls = getAllowedHullsForRole(role);
-    const shipNameLower = selectedCharacter.ship.name.toLowerCase();
-    const hasIncorrectHull = !allowedHulls.includes(shipNameLower);
+    const hasIncorrectHull = !roleSuitsShip(
+      fleetCompositionInfo?.motd_roles,
+      role,
+      selectedCharacter.ship
+    );
     
     const updateData = {
       character_id: characterId,
@@ -581,10 +565,10 @@ export function FleetMembers({ fleetpage = true, setStatTempActive = null }) {
     setShowDuplicateConfirm(false);
     if (pendingUpdate) {
       // Check if we also need hull confirmation
-      const allowedHulls = getAllowedHullsForRole(pendingUpdate.role);
       const character = selectedCharacter;
-      const shipNameLower = character?.ship.name.toLowerCase();
-      const hasIncorrectHull = character && !allowedHulls.includes(shipNameLower);
+      const hasIncorrectHull =
+        character &&
+        !roleSuitsShip(fleetCompositionInfo?.motd_roles, pendingUpdate.role, character.ship);
       
       if (hasIncorrectHull) {
         setShowHullConfirm(true);
@@ -593,7 +577,7 @@ export function FleetMembers({ fleetpage = true, setStatTempActive = null }) {
         setPendingUpdate(null);
       }
     }
-  }, [pendingUpdate, selectedCharacter, performMotdUpdate]);
+  }, [pendingUpdate, selectedCharacter, fleetCompositionInfo, performMotdUpdate]);
 
   // Handle hull confirmation
   const handleHullConfirm = React.useCallback(() => {
@@ -630,7 +614,10 @@ export function FleetMembers({ fleetpage = true, setStatTempActive = null }) {
               memberlist={getFleetMembers(fleetCompositionInfo)}
             />
           )}
-          <RoleAssignments roleAssignments={fleetCompositionInfo.role_assignments} />
+          <RoleAssignments
+            roleAssignments={fleetCompositionInfo.role_assignments}
+            roles={fleetCompositionInfo.motd_roles}
+          />
           <Table style={{ fontSize: "12px" }} fullWidth={fleetpage ? undefined : true}>
             <TableBody>
               {fleetCompositionInfo.wings.map((wing, wingIndex) => (
@@ -683,6 +670,7 @@ export function FleetMembers({ fleetpage = true, setStatTempActive = null }) {
         setOpen={setContextMenuOpen}
         position={contextMenuPosition}
         character={selectedCharacter}
+        roles={fleetCompositionInfo.motd_roles}
         onRoleSelect={handleRoleSelect}
         authContext={authContext}
       />
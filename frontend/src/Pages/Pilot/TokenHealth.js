@@ -0,0 +1,61 @@
+import { useApi } from "../../api";
+import { AButton } from "../../Components/Form";
+import { Title } from "../../Components/Page";
+import styled from "styled-components";
+import Spinner from "../../Components/Spinner";
+
+const TokenDisplay = styled.div`
+  margin-bottom: 25px;
+
+  div {
+    padding: 5px;
+    width: 100%;
+  }
+
+  em {
+    display: block;
+    font-size: smaller;
+  }
+
+  a {
+    margin-top: 5px;
+  }
+`;
+
+const TokenHealth = () => {
+  const [tokens] = useApi(`/api/auth/tokens`);
+
+  return (
+    <TokenDisplay>
+      <Title>ESI Access</Title>
+      {!tokens ? (
+        <Spinner />
+      ) : (
+        tokens.characters.map((status) => {
+          const missing = status.features.filter(
+            (feature) => feature.required && feature.missing_scopes.length
+          );
+          return (
+            <div key={status.character.id}>
+              {status.character.name}
+              {!status.has_token ? (
+                <em>No ESI access, log in with this character again</em>
+              ) : missing.length ? (
+                <em>Missing access for: {missing.map((feature) => feature.feature).join(", ")}</em>
+              ) : (
+                <em>OK</em>
+              )}
+              {status.reauth_url && (
+                <AButton variant="primary" href={status.reauth_url}>
+                  Re-authorize
+                </AButton>
+              )}
+            </div>
+          );
+        })
+      )}
+    </TokenDisplay>
+  );
+};
+
+export default TokenHealth;
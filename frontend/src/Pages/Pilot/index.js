@@ -24,6 +24,7 @@ import { Row, Col } from "react-awesome-styled-grid";
 import CommanderModal from "../FC/commanders/CommanderModal";
 import { AccountBannedBanner } from "../FC/bans/AccountBanned";
 import AltCharacters from "./AltCharacters";
+import TokenHealth from "./TokenHealth";
 import { usePageTitle } from "../../Util/title";
 
 
@@ -235,6 +236,7 @@ function PilotDisplay({ authContext }) {
           <ActivitySummary summary={fleetHistory && fleetHistory.summary} />
 
           <AltCharacters character={basicInfo?.id} />
+          {!queryParams.get("character_id") && <TokenHealth />}
           
         </Col>
       </Row>
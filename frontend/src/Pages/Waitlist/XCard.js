@@ -189,7 +189,7 @@ function ShipDisplay({ fit, onAction }) {
               )}
 
               <FitDisplay fit={fit} />
-              {fit.tags.includes("STARTER") ? (
+              {fit.tags.some((tag) => tag.name === "STARTER") ? (
                 <>
                   <Title>Starter skills</Title>
                   <SkillDisplay
@@ -385,14 +385,15 @@ export function XCard({ entry, fit, onAction }) {
   const is_alt = fit.is_alt;
   const accountName = entry.character ? entry.character.name : "Name hidden";
   const tags = _.sortBy(fit.tags, function (item) {
-    return badgeOrder.indexOf(item);
+    return badgeOrder.indexOf(item.name);
   });
   var isSelf = entry.character && entry.character.id === authContext.account_id;
   var tagText = [];
   var tagImages = [];
   tags.forEach((tag) => {
-    if (tag in icons) {
-      tagImages.push(<BadgeIcon type={tag} key={tag} />);
+    const icon = tag.icon || tag.name;
+    if (icon in icons) {
+      tagImages.push(<BadgeIcon type={icon} key={tag.name} />);
     } else {
       tagText.push(tag);
     }
@@ -434,7 +435,9 @@ export function XCard({ entry, fit, onAction }) {
       <XCardDOM.Content>
         <div style={{ FlexWrap: "wrap" }}>
           {tagText.map((tag) => (
-            <Badge key={tag}>{tag}</Badge>
+            <Badge key={tag.name} title={tag.description}>
+              {tag.name}
+            </Badge>
           ))}
         </div>
       </XCardDOM.Content>